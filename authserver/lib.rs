@@ -0,0 +1,133 @@
+use std::{collections::HashMap, env, io::Write, net::TcpStream, sync::{Arc, Mutex}};
+
+use shared::network::{auth::{jwt_username, random_token, CAST, SRST}, codec::encode, containers::{AuthMessages, NetworkMessage, Packet, UnauthenticatedPacket}};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream as AsyncTcpStream}};
+
+//a stand-in for the auth service, so the whole join handshake runs locally. It trusts whatever username a jwt claims
+//and never checks signatures, don't expose it
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8000";
+//requests bigger than this are cut off
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+//session token -> the request the game server made for it
+type Sessions = Arc<Mutex<HashMap<String, SRST>>>;
+
+struct Request {
+    method: String,
+    path: String,
+    body: String
+}
+
+//just enough http/1.1 for reqwest, one request per connection
+async fn read_request(stream: &mut AsyncTcpStream) -> Option<Request> {
+    let mut data = Vec::new();
+    let mut buffer = [0; 4096];
+
+    let header_end = loop {
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 || data.len() > MAX_REQUEST_SIZE {return None};
+        data.extend_from_slice(&buffer[..read]);
+
+        if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut request_line = head.lines().next()?.split_whitespace();
+    let (method, path) = (request_line.next()?.to_string(), request_line.next()?.to_string());
+
+    let content_length = head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_REQUEST_SIZE {return None};
+
+    while data.len() < header_end + content_length {
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 {return None};
+        data.extend_from_slice(&buffer[..read]);
+    }
+
+    Some(Request {
+        method,
+        path,
+        body: String::from_utf8_lossy(&data[header_end..header_end + content_length]).to_string()
+    })
+}
+
+async fn respond(stream: &mut AsyncTcpStream, status: &str, body: &str) {
+    let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+//the game server listens for AuthConfirmedUser on the callback it gave
+fn confirm_user(callback: &str, username: String) -> std::io::Result<()> {
+    let packet = Packet::Unauthenticated(UnauthenticatedPacket {
+        data: NetworkMessage::Auth(AuthMessages::AuthConfirmedUser(username))
+    });
+    let frame = encode(&packet, false).map_err(std::io::Error::other)?;
+    TcpStream::connect(callback)?.write_all(&frame)
+}
+
+fn handle_request(request: Request, sessions: &Sessions) -> (&'static str, String) {
+    match (request.method.as_str(), request.path.as_str()) {
+        //game server -> here, a user wants to join it
+        ("GET", "/servergetsessiontoken") => {
+            let Ok(srst) = serde_json::from_str::<SRST>(&request.body) else {return ("400 Bad Request", String::new())};
+            let token = random_token();
+            println!("{} wants to join {}", srst.expected_username, srst.callback);
+            sessions.lock().unwrap().insert(token.clone(), srst);
+            ("200 OK", token)
+        },
+        //client -> here, proving it's the user the game server expects
+        ("POST", "/clientauthsessiontoken") => {
+            let Ok(cast) = serde_json::from_str::<CAST>(&request.body) else {return ("400 Bad Request", String::new())};
+            let Some(srst) = sessions.lock().unwrap().remove(&cast.auth_secret) else {return ("404 Not Found", String::new())};
+
+            if jwt_username(&cast.jwt).as_deref() != Some(srst.expected_username.as_str()) {
+                println!("the jwt isn't for {}", srst.expected_username);
+                return ("401 Unauthorized", String::new());
+            }
+            if srst.callback.split(':').next() != Some(cast.expected_host.as_str()) {
+                println!("{} thinks it's joining {}, not {}", srst.expected_username, cast.expected_host, srst.callback);
+                return ("401 Unauthorized", String::new());
+            }
+
+            match confirm_user(&srst.callback, srst.expected_username.clone()) {
+                Ok(()) => {
+                    println!("confirmed {}", srst.expected_username);
+                    ("200 OK", String::new())
+                },
+                Err(e) => {
+                    println!("couldn't reach the game server at {}: {}", srst.callback, e);
+                    ("502 Bad Gateway", String::new())
+                }
+            }
+        },
+        _ => ("404 Not Found", String::new())
+    }
+}
+
+//STARFORGED_AUTH_ADDRESS changes where it listens
+#[tokio::main]
+pub async fn main() {
+    let address = env::var("STARFORGED_AUTH_ADDRESS").unwrap_or_else(|_| DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address).await.unwrap();
+    let sessions: Sessions = Arc::new(Mutex::new(HashMap::new()));
+    println!("stand-in auth server on {}", address);
+
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else {continue};
+        let sessions = sessions.clone();
+
+        tokio::spawn(async move {
+            let Some(request) = read_request(&mut stream).await else {return};
+            //confirming connects to the game server, which blocks
+            let (status, body) = tokio::task::spawn_blocking(move || handle_request(request, &sessions)).await.unwrap();
+            respond(&mut stream, status, &body).await;
+        });
+    }
+}
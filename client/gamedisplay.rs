@@ -1,23 +1,24 @@
-use std::{collections::HashMap, sync::{mpsc::{self, Receiver, Sender}, Arc}, time::{UNIX_EPOCH}};
+use std::{collections::HashMap, sync::{mpsc, Arc}, time::{UNIX_EPOCH}};
 
 use instant::{Instant, SystemTime};
 use message_io::{network::{NetEvent, Transport}, node::{self, NodeTask}};
-use miniz_oxide::inflate::decompress_to_vec;
 use nalgebra::{Point3, Vector2};
 use pollster::FutureExt;
-use shared::world::{chunk::{xz_to_index, Chunk, ChunkState}, chunkcompress::{decompress_chunk, CompressedChunk}};
+use shared::world::{blockrepr::WorldBlock, chunk::{xz_to_index, Chunk}, chunkcompress::{decompress_chunk, CompressedChunk}};
 use stopwatch::Stopwatch;
 use winit::{application::ApplicationHandler, dpi::{PhysicalPosition, PhysicalSize, Size}, event::WindowEvent, event_loop::EventLoop, window::{Window, WindowAttributes}};
 
-use crate::{global::{event_handler::EventHandler, globalstate::GlobalState, inputservice::{InputService, MouseLockState}}, network::clinet::CliNet, renderer::{gamewindow::GameWindow, renderctx::Renderctx}, view::camera::Camera, world::{chunkdraw::ChunkDraw, chunkmanager::ChunkManager, meshthread::spawn_chunk_meshing_loop}};
+use crate::{global::{event_handler::EventHandler, globalstate::GlobalState, inputservice::{InputService, MouseLockState}}, network::clinet::{CliNet, Credentials}, renderer::{gamewindow::GameWindow, renderctx::Renderctx}, view::camera::Camera, world::{chunkdraw::ChunkDraw, chunkmanager::ChunkManager, mesher::MesherKind, meshscheduler::MeshScheduler}};
 
 #[derive(Default)]
 pub struct GameDisplay<'a> {
     pub window: Option<Arc<Window>>,
     pub gamewindow: Option<GameWindow<'a>>,
     pub globalstate: Option<GlobalState>,
-    pub chunkmesher: Option<(Sender<(i32, i32, u32, std::collections::HashMap<u32, Arc<Chunk>>, Arc<crate::renderer::renderctx::Renderctx>)>, Receiver<(i32, i32, u32, ((wgpu::Buffer, wgpu::Buffer, u32), (wgpu::Buffer, wgpu::Buffer, u32)))>)>,
+    pub mesh_scheduler: Option<MeshScheduler>,
     pub network: Option<CliNet>,
+    //taken once the network starts
+    pub credentials: Option<Credentials>,
     pub event_handler: Option<EventHandler>,
     pub last_frame: u128,
     pub last_mouse_position: PhysicalPosition<f64>
@@ -35,17 +36,27 @@ impl ApplicationHandler for GameDisplay<'_> {
 
         self.globalstate = Some(GlobalState {
             chunk_manager: ChunkManager::new(),
-            camera: Camera::new(Point3::new(0.0, 0.0, 0.0), 0.0, 0.0, gamewindow.window_size.width as f32 / gamewindow.window_size.height as f32, 80.0, gamewindow.device.clone(), &gamewindow.camera_bindgroup_layout),
-            input_service: InputService::new(self.window.clone().unwrap())
+            camera: Camera::new(Point3::new(0.0, 0.0, 0.0), 0.0, 0.0, gamewindow.window_size.width.max(1) as f32 / gamewindow.window_size.height.max(1) as f32, 80.0, gamewindow.device.clone(), &gamewindow.camera_bindgroup_layout),
+            input_service: InputService::new(self.window.clone().unwrap()),
+            target: None,
+            held_block: WorldBlock::Stone(0),
+            sent_position: None,
+            disconnect_reason: None
         });
 
         self.gamewindow = Some(gamewindow);
 
-        let chunkmesh = spawn_chunk_meshing_loop(4);
+        let gamewindow = self.gamewindow.as_ref().unwrap();
+        self.mesh_scheduler = Some(MeshScheduler::new(4, MesherKind::default(), Arc::new(Renderctx::new(gamewindow.device.clone(), gamewindow.queue.clone()))));
 
-        self.chunkmesher = Some(chunkmesh);
-
-        self.network = Some(CliNet::new("127.0.0.1:3043".to_string()));
+        match CliNet::new("127.0.0.1:3043".to_string(), self.credentials.take().unwrap()) {
+            Ok(network) => self.network = Some(network),
+            Err(e) => {
+                println!("{}", e);
+                event_loop.exit();
+                return;
+            }
+        }
         self.event_handler = Some(EventHandler::new());
 
         self.globalstate.as_mut().unwrap().input_service.set_mouse_lock_state(MouseLockState::LockCenter);
@@ -81,6 +92,20 @@ impl ApplicationHandler for GameDisplay<'_> {
             WindowEvent::CloseRequested => {
                 event_loop.exit();
             },
+            WindowEvent::Resized(size) => {
+                let gamewin = self.gamewindow.as_mut().unwrap();
+                let gs = self.globalstate.as_mut().unwrap();
+
+                gamewin.resize(size, &mut gs.camera);
+            },
+            WindowEvent::ScaleFactorChanged { .. } => {
+                //the physical size may have changed with the scale factor, winit only sends Resized if the os asks for it
+                let size = self.window.as_ref().unwrap().inner_size();
+                let gamewin = self.gamewindow.as_mut().unwrap();
+                let gs = self.globalstate.as_mut().unwrap();
+
+                gamewin.resize(size, &mut gs.camera);
+            },
             WindowEvent::CursorMoved { device_id, position } => {
                 let (lx, ly) = (self.last_mouse_position.x, self.last_mouse_position.y);
                 let (cx, cy) = (position.x, position.y);
@@ -89,6 +114,11 @@ impl ApplicationHandler for GameDisplay<'_> {
                 
                 gs.input_service.process_mouse_move(delta);
             }
+            WindowEvent::MouseInput { device_id, state, button } => {
+                let gs = self.globalstate.as_mut().unwrap();
+
+                gs.input_service.process_mouse_input(&button, &state, false).block_on();
+            },
             WindowEvent::KeyboardInput { device_id, event, is_synthetic } => {
                 let gs = self.globalstate.as_mut().unwrap();
                 
@@ -98,6 +128,34 @@ impl ApplicationHandler for GameDisplay<'_> {
                     winit::keyboard::PhysicalKey::Code(x) => {
                         let gs = self.globalstate.as_mut().unwrap();
                         gs.camera.controller.process_keyboard_input(x, event.state);
+
+                        //F3 cycles the post processing buffer on screen, F4 turns bloom on and off, F5 shadows,
+                        //F6 prints how the meshing and culling are doing
+                        if event.state.is_pressed() && !event.repeat {
+                            let renderer = &mut self.gamewindow.as_mut().unwrap().renderer;
+                            let post = &mut renderer.post.settings;
+                            match x {
+                                winit::keyboard::KeyCode::F3 => {
+                                    post.debug_view = post.debug_view.next();
+                                    println!("post debug view: {:?}", post.debug_view);
+                                },
+                                winit::keyboard::KeyCode::F4 => {
+                                    post.bloom = !post.bloom;
+                                    println!("bloom: {}", post.bloom);
+                                },
+                                winit::keyboard::KeyCode::F5 => {
+                                    renderer.shadows_enabled = !renderer.shadows_enabled;
+                                    println!("shadows: {}", renderer.shadows_enabled);
+                                },
+                                winit::keyboard::KeyCode::F6 => {
+                                    let meshing = self.mesh_scheduler.as_ref().unwrap().stats();
+                                    let culling = renderer.culling_stats();
+                                    println!("meshing: {} pending, {} in flight, {} done, {} stale", meshing.pending, meshing.in_flight, meshing.completed, meshing.dropped_stale);
+                                    println!("culling: {} tested, {} culled, {} occluded, {} visible", culling.tested, culling.culled, culling.occluded, culling.visible);
+                                },
+                                _ => {}
+                            }
+                        }
                     },
                     _ => {}
                 }
@@ -119,20 +177,19 @@ impl ApplicationHandler for GameDisplay<'_> {
                 gamewin.render(dt, gs);
 
                 let network_events = net.recv().block_on();
-                self.event_handler.as_mut().unwrap().handle_network_events(&gamewin.device, &gamewin.queue, gs, net, self.chunkmesher.as_mut().unwrap(), network_events);
-
-                for _ in 0..16 {
-                    if let Ok((x, z, y, buff)) = self.chunkmesher.as_ref().unwrap().1.try_recv() {
-                        println!("Meshed chunk");
-                        let index = xz_to_index(x, z);
-                        let chunkdraw = self.globalstate.as_mut().unwrap().chunk_manager.chunks.get_mut(&index).unwrap();
-                        
-                        chunkdraw.set_solid_buffer(y, buff.0);
-                        chunkdraw.set_transparent_buffer(y, buff.1);
-                        chunkdraw.states[y as usize] = ChunkState::Ready;
-                    }
-                    else {
-                        break;
+                self.event_handler.as_mut().unwrap().handle_network_events(&gamewin.device, &gamewin.queue, gs, net, self.mesh_scheduler.as_mut().unwrap(), network_events);
+                if let Some(reason) = gs.disconnect_reason.take() {
+                    self.window.as_ref().unwrap().set_title(&format!("Starforged - disconnected: {}", reason));
+                }
+
+                let scheduler = self.mesh_scheduler.as_mut().unwrap();
+                gs.chunk_manager.queue_dirty_slices(scheduler);
+                scheduler.dispatch(&mut gs.chunk_manager, &gs.camera);
+
+                for ((x, z, y), buff) in scheduler.poll() {
+                    //the chunk may have been unloaded while it was meshing
+                    if let Some(chunkdraw) = gs.chunk_manager.chunks.get_mut(&xz_to_index(x, z)) {
+                        chunkdraw.set_meshed_slice(y, buff);
                     }
                 }
                 
@@ -1,11 +1,8 @@
-use std::{collections::HashMap, sync::{mpsc::{Receiver, Sender}, Arc}};
+use std::sync::Arc;
 
-use miniz_oxide::inflate::decompress_to_vec;
-use nalgebra::Vector2;
-use shared::{network::containers::{send_authenticated_message, ClientToServerMessage, NetworkMessage, ServerToClientMessage}, world::{chunk::{xz_to_index, Chunk}, chunkcompress::{decompress_chunk, CompressedChunk}}};
-use stopwatch::Stopwatch;
+use shared::{network::containers::{ClientToServerMessage, NetworkMessage, ServerToClientMessage}, world::chunkcompress::decompress_chunk};
 
-use crate::{network::clinet::{CliNet, ClientNetworkEvent}, renderer::renderctx::Renderctx, world::chunkdraw::ChunkDraw};
+use crate::{network::clinet::{CliNet, ClientNetworkEvent}, world::meshscheduler::MeshScheduler};
 
 use super::globalstate::GlobalState;
 
@@ -20,65 +17,48 @@ impl EventHandler {
         }
     }
 
-    pub fn handle_network_events(&mut self, device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Queue>, gs: &mut GlobalState, network: &mut CliNet, chunk_mesher: &mut (Sender<(i32, i32, u32, HashMap<u32, Arc<Chunk>>, Arc<crate::renderer::renderctx::Renderctx>)>, Receiver<(i32, i32, u32, ((wgpu::Buffer, wgpu::Buffer, u32), (wgpu::Buffer, wgpu::Buffer, u32)))>), events: Vec<ClientNetworkEvent>) {
+    pub fn handle_network_events(&mut self, device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Queue>, gs: &mut GlobalState, network: &mut CliNet, mesh_scheduler: &mut MeshScheduler, events: Vec<ClientNetworkEvent>) {
         for event in events {
             match event {
-                ClientNetworkEvent::AcquiredChunk(pos, nextchunk) => {
-                    let t = Stopwatch::start_new();
-                    let position = nextchunk.position;
-                    let (x, z) = (nextchunk.position.x, nextchunk.position.y);
-                    let index = xz_to_index(x, z);
-                    let mut chunkdraw = ChunkDraw::new(nextchunk);
-                    chunkdraw.set_slice_vertex_buffers(device);
-                    gs.chunk_manager.chunks.insert(index, chunkdraw);
-
-                    // let nh = HashMap::from_iter(gs.chunk_manager.chunks.iter().map(|(k, v)| {
-                    //     (*k, v.chunk.clone())
-                    // }));
-
-                    //let renderctx = Arc::new(Renderctx::new(device.clone(), queue.clone()));
-                    // for y in 0..16 {
-                    //     chunk_mesher.0.send((x, z, y, nh.clone(), renderctx.clone())).unwrap();
-                    // }
-                    // for x in position.x - 1..= position.x + 1 {
-                    //     for z in position.y - 1..= position.y + 1 {
-                    //         if !nh.contains_key(&xz_to_index(x, z)) || position == Vector2::new(x, z) {continue}
-                    //         for y in 0..16 {
-                    //             chunk_mesher.0.send((x, z, y, nh.clone(), renderctx.clone())).unwrap();
-                    //         }
-                    //     }
-                    // }
+                ClientNetworkEvent::AcquiredChunk(_, nextchunk) => {
+                    gs.chunk_manager.insert_chunk(nextchunk, device, mesh_scheduler);
+                },
+                ClientNetworkEvent::Disconnected(reason) => {
+                    gs.disconnect_reason = Some(reason);
                 },
                 ClientNetworkEvent::ConnectedToServer => {
                     println!("Connected");
-                    //grab chunks from server.
-                    send_authenticated_message(network.handler.network(), network.endpoint, network.session_token.clone().unwrap(), NetworkMessage::ClientToServer(ClientToServerMessage::RequestInitialChunks));
+                    //grab chunks from server, they're streamed around the player from here on
+                    for message in [
+                        ClientToServerMessage::SetViewDistance(gs.chunk_manager.render_distance),
+                        ClientToServerMessage::PlayerPosition(gs.camera.position),
+                        ClientToServerMessage::RequestInitialChunks
+                    ] {
+                        network.send(NetworkMessage::ClientToServer(message));
+                    }
+                    gs.sent_position = Some(gs.camera.position);
                 },
                 ClientNetworkEvent::ServerToClient(stc) => {
                     match stc {
                         ServerToClientMessage::ConcludeReceiveInitialChunks => {
                             println!("Got initial Chunks");
-                            let nh = HashMap::from_iter(gs.chunk_manager.chunks.iter().map(|(k, v)| {
-                                (*k, v.chunk.clone())
-                            }));
-
-                            let renderctx = Arc::new(Renderctx::new(device.clone(), queue.clone()));
-                            for (_, chunk) in &gs.chunk_manager.chunks {
-                                for y in 0..16 {
-                                    chunk_mesher.0.send((chunk.chunk.position.x, chunk.chunk.position.y, y, nh.clone(), renderctx.clone())).unwrap();
-                                }
-                            }
                         },
-                        ServerToClientMessage::ChunkProvided((pos, data)) => {
-                            let dec = decompress_to_vec(&data).unwrap();
-                                
-                            let deser = bincode::deserialize::<CompressedChunk>(&dec).unwrap();
-                            
-                            let chunk = decompress_chunk(deser);
-                            
-                            gs.chunk_manager.chunks.insert(xz_to_index(pos.x, pos.y), ChunkDraw::new(chunk));
+                        ServerToClientMessage::BlockChanged(position, block) => {
+                            gs.chunk_manager.set_block(position, block);
+                        },
+                        ServerToClientMessage::MultiBlockChange(chunk_position, changes) => {
+                            gs.chunk_manager.set_blocks(chunk_position, &changes);
+                        },
+                        ServerToClientMessage::UnloadChunk(position) => {
+                            gs.chunk_manager.unload_chunk(position, mesh_scheduler);
+                        },
+                        ServerToClientMessage::ChunkProvided((_, data)) => {
+                            let chunk = decompress_chunk(data);
+
+                            gs.chunk_manager.insert_chunk(chunk, device, mesh_scheduler);
                             println!("Added Chunk");
-                        }
+                        },
+                        ServerToClientMessage::Kicked(_) => {/* the network already dropped the connection */}
                     }
                 }
             }
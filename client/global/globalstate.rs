@@ -1,3 +1,5 @@
+use nalgebra::{Point3, Vector3};
+use shared::{network::containers::{ClientToServerMessage, NetworkMessage}, world::{blockrepr::{get_block_model_alias, is_placeable, is_replaceable, set_block_light, set_block_orientation, WorldBlock}, raycast::RaycastHit}};
 use winit::event::MouseButton;
 
 use crate::{network::clinet::CliNet, view::camera::Camera, world::chunkmanager::ChunkManager};
@@ -7,22 +9,108 @@ use super::inputservice::{InputEvent, InputService};
 pub struct GlobalState {
     pub chunk_manager: ChunkManager,
     pub camera: Camera,
-    pub input_service: InputService
+    pub input_service: InputService,
+    //the block under the crosshair, outlined by the renderer
+    pub target: Option<RaycastHit>,
+    //what right click places, middle click picks it from the target
+    pub held_block: WorldBlock,
+    //where the server was last told the eye is. It streams chunks around it and checks the reach of edits from it
+    pub sent_position: Option<Point3<f32>>,
+    //why the server turned the player away, shown in the window title
+    pub disconnect_reason: Option<String>
+}
+
+//moving further than this tells the server
+const POSITION_UPDATE_DISTANCE: f32 = 1.0;
+
+//modelled blocks are placed with their front towards the player, and upside down against the underside of a block
+pub fn placement_orientation(direction: Vector3<f32>, face: Vector3<i32>) -> (u8, bool) {
+    let towards = -direction;
+    let yaw = if towards.x.abs() > towards.z.abs() {
+        if towards.x > 0.0 {1} else {3}
+    } else if towards.z > 0.0 {0} else {2};
+    (yaw, face.y == -1)
 }
 
 impl GlobalState {
+    //once joined, the first position goes out with the join requests
+    fn send_position(&mut self, net: &CliNet) {
+        if net.session_token.is_none() {return};
+        net.send(NetworkMessage::ClientToServer(ClientToServerMessage::PlayerPosition(self.camera.position)));
+        self.sent_position = Some(self.camera.position);
+    }
+
     pub fn on_world_tick(&mut self, net: &mut CliNet, dt: f32) {
         let input_events = self.input_service.consume_events();
+        self.target = self.chunk_manager.raycast(self.camera.position, self.camera.look_vector());
+
+        if self.sent_position.is_some_and(|sent| (sent - self.camera.position).norm() > POSITION_UPDATE_DISTANCE) {
+            self.send_position(net);
+        }
         
         for event in input_events {
             match event {
                 InputEvent::MouseButtonClicked(button) => {
-                    if button == MouseButton::Left {
-                        
+                    let Some(hit) = self.target else {continue};
+                    let (eye, direction) = (self.camera.position, self.camera.look_vector());
+
+                    //changes show up right away, the server repeats the raycast and rejects what it can't reach
+                    let message = match button {
+                        MouseButton::Left => {
+                            self.chunk_manager.set_block(hit.block, WorldBlock::Air(0));
+                            ClientToServerMessage::BreakBlock(hit.block, eye, direction)
+                        },
+                        MouseButton::Right => {
+                            let position = hit.block + hit.face;
+                            //the eye is inside the target, there's no face to place against
+                            if position == hit.block {continue};
+                            if !self.chunk_manager.get_block(position).is_some_and(|b| is_replaceable(&b)) {continue};
+                            if !is_placeable(&self.held_block) {continue};
+                            if get_block_model_alias(&self.held_block).is_some() {
+                                let (yaw, flipped) = placement_orientation(direction, hit.face);
+                                set_block_orientation(&mut self.held_block, yaw, flipped);
+                            }
+
+                            self.chunk_manager.set_block(position, self.held_block);
+                            ClientToServerMessage::SetBlock(position, self.held_block, eye, direction)
+                        },
+                        MouseButton::Middle => {
+                            if let Some(mut block) = self.chunk_manager.get_block(hit.block).filter(is_placeable) {
+                                //the light it had there means nothing where it gets placed
+                                set_block_light(&mut block, 0);
+                                self.held_block = block;
+                            }
+                            continue;
+                        },
+                        _ => continue
+                    };
+                    self.target = self.chunk_manager.raycast(eye, direction);
+
+                    //the server checks the reach from the last position it got, so it has to be this one
+                    if self.sent_position != Some(eye) {
+                        self.send_position(net);
                     }
+                    if net.session_token.is_none() {continue};
+                    net.send(NetworkMessage::ClientToServer(message));
                 },
                 _ => {}
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::placement_orientation;
+
+    #[test]
+    fn placed_blocks_face_the_player() {
+        //looking along -z, the front (+z) already faces back
+        assert_eq!(placement_orientation(Vector3::new(0.1, -0.5, -1.0), Vector3::y()), (0, false));
+        assert_eq!(placement_orientation(Vector3::new(-1.0, -0.5, 0.2), Vector3::y()), (1, false));
+        assert_eq!(placement_orientation(Vector3::new(0.0, 0.3, 1.0), -Vector3::y()), (2, true));
+        assert_eq!(placement_orientation(Vector3::new(1.0, 0.0, 0.0), Vector3::x()), (3, false));
+    }
+}
@@ -1,9 +1,8 @@
 use std::{io::Read, sync::mpsc, time::Duration};
 
-use flate2::read::ZlibDecoder;
 use gamedisplay::GameDisplay;
+use network::clinet::Credentials;
 use message_io::{network::{NetEvent, Transport}, node::{self, NodeEvent}};
-use miniz_oxide::inflate::decompress_to_vec;
 use winit::event_loop::EventLoop;
 use shared::world::{self as shared_world, chunkcompress::{decompress_chunk, CompressedChunk}};
 
@@ -16,11 +15,19 @@ mod global;
 
 #[tokio::main]
 pub async fn main() {
+    //checked before the window opens, there's no joining without them
+    let credentials = match Credentials::from_env() {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
 
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
 
-    let mut display = GameDisplay::default();
+    let mut display = GameDisplay {credentials: Some(credentials), ..GameDisplay::default()};
     event_loop.run_app(&mut display);
 
 
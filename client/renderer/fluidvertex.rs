@@ -0,0 +1,47 @@
+use std::mem;
+
+use super::vertex::Vertex;
+
+//fluid surfaces aren't block aligned, so unlike SurfaceVertex the position isn't packed
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, PartialEq)]
+pub struct FluidVertex {
+    //relative to the slice origin
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    //16 bits texture index, 3 bits face direction (same order as SurfaceVertex)
+    pub d1: u32,
+    pub illumination: u32,
+}
+
+impl Vertex for FluidVertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<FluidVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                //location 3 is the chunk data instance buffer
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Uint32,
+                }
+            ]
+        }
+    }
+}
@@ -1,26 +1,81 @@
-use std::sync::Arc;
+use std::{collections::HashMap, mem, sync::Arc};
 
-use shared::world::chunk::ChunkState;
+use nalgebra::{Point3, Vector3};
 use stopwatch::Stopwatch;
 use wgpu::{Extent3d, TextureFormat, TextureViewDescriptor};
-use shared::loaders::texture::Texture;
-use crate::{view::camera::Camera, world::{chunkdraw::ChunkDataVertex, chunkmanager::ChunkManager}};
+use shared::{loaders::texture::Texture, world::chunk::xz_to_index};
+use crate::{view::{camera::Camera, frustum::CullingStats}, world::{chunkdraw::ChunkDataVertex, chunkmanager::ChunkManager, depthsort::{sort_chunk_transparent_quads, RESORT_DISTANCE}, occlusion::visible_slices}};
 
-use super::{pipelines::surface_pipeline::create_surface_pipeline, surfacevertex::SurfaceVertex, vertex::Vertex};
+use super::{fluidvertex::FluidVertex, pipelines::{fluid_pipeline::create_fluid_pipeline, model_pipeline::create_model_pipeline, outline_pipeline::create_outline_pipeline, sky_pipeline::create_sky_pipeline, surface_pipeline::{create_surface_pipeline, SurfacePass}}, postprocess::{PostProcess, HDR_FORMAT}, shadows::ShadowMaps, sky::{Sky, SkyUniform}, surfacevertex::SurfaceVertex, texturebackend::TextureBackend, vertex::{ModelVertex, Vertex}};
+
+//the msaa color target and depth buffer, both have to match the surface size
+fn create_size_dependent_textures(device: &wgpu::Device, dims: (u32, u32)) -> (wgpu::Texture, wgpu::TextureView, Texture) {
+    //zero sized textures are invalid, a minimized window still gets 1x1 attachments
+    let (width, height) = (dims.0.max(1), dims.1.max(1));
+
+    let multisample_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("MS_Texture"),
+        size: Extent3d {width, height, depth_or_array_layers: 1},
+        mip_level_count: 1,
+        sample_count: 4,
+        dimension: wgpu::TextureDimension::D2,
+        format: HDR_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+
+    let msview = multisample_texture.create_view(&TextureViewDescriptor::default());
+
+    let depth_texture = Texture::from_empty("depth texture", device, wgpu::TextureFormat::Depth32Float, width, height, wgpu::FilterMode::Linear);
+
+    (multisample_texture, msview, depth_texture)
+}
+
+//rgba multiplied into the fluid texture
+const FLUID_TINT: [f32; 4] = [0.6, 0.8, 1.0, 0.75];
+//texture scroll speed in blocks per second
+const FLUID_SCROLL_SPEED: f32 = 0.15;
+//the tops scroll half as fast along z, so after 2 blocks both axes are back where they started. The time wraps
+//around then, before it gets big enough for f32 to lose the fractions
+const FLUID_ANIMATION_PERIOD: f32 = 2.0 / FLUID_SCROLL_SPEED;
 
 pub struct GameRenderer {
     device: Arc<wgpu::Device>,
     queue: Arc<wgpu::Queue>,
     dims: (u32, u32),
-    surface_format: wgpu::TextureFormat,
     surface_pipeline: wgpu::RenderPipeline,
+    cutout_pipeline: wgpu::RenderPipeline,
+    translucent_pipeline: wgpu::RenderPipeline,
+    fluid_pipeline: wgpu::RenderPipeline,
+    model_pipeline: wgpu::RenderPipeline,
+    fluid_uniform_buffer: wgpu::Buffer,
+    fluid_bindgroup: wgpu::BindGroup,
+    sky_pipeline: wgpu::RenderPipeline,
+    sky_uniform_buffer: wgpu::Buffer,
+    sky_bindgroup: wgpu::BindGroup,
+    pub sky: Sky,
+    outline_pipeline: wgpu::RenderPipeline,
+    outline_uniform_buffer: wgpu::Buffer,
+    outline_bindgroup: wgpu::BindGroup,
+    //the block drawn with an outline, normally the one under the crosshair
+    outline_target: Option<Vector3<i32>>,
+    shadows: ShadowMaps,
+    //off skips the shadow pass and lights every surface as if the sun reached it
+    pub shadows_enabled: bool,
+    //the world renders in hdr, this takes it to the output
+    pub post: PostProcess,
+    //seconds since start, drives the fluid animation
+    time: f32,
+    //camera position the translucent geometry was last sorted from
+    last_sort_position: Option<Point3<f32>>,
+    culling_stats: CullingStats,
     depth_texture: Texture,
     multisample_texture: wgpu::Texture,
     multisample_texture_view: wgpu::TextureView
 }
 
 impl GameRenderer {
-    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>, dims: (u32, u32), surface_format: wgpu::TextureFormat, camera_bindgroup_layout: &wgpu::BindGroupLayout, texture_bindgroup_layout: &wgpu::BindGroupLayout) -> Self {
+    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>, dims: (u32, u32), surface_format: wgpu::TextureFormat, camera_bindgroup_layout: &wgpu::BindGroupLayout, texture_bindgroup_layout: &wgpu::BindGroupLayout, texture_backend: TextureBackend) -> Self {
         
         let material_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Main renderer material bind group layout"),
@@ -76,8 +131,52 @@ impl GameRenderer {
             ]
         });
 
-        let global_bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("global bindgroup layout"),
+        let sky_bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sky bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ]
+        });
+
+        let sky_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sky uniform buffer"),
+            size: mem::size_of::<SkyUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sky_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sky bindgroup"),
+            layout: &sky_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sky_uniform_buffer.as_entire_binding()
+                }
+            ]
+        });
+
+        let shadows = ShadowMaps::new(&device);
+
+        let sky_pipeline = create_sky_pipeline(&device, HDR_FORMAT, &sky_bindgroup_layout, Some(TextureFormat::Depth32Float));
+
+        let surface_pipeline = create_surface_pipeline(&device, HDR_FORMAT, texture_bindgroup_layout, camera_bindgroup_layout, &sky_bindgroup_layout, &shadows.receiver_bindgroup_layout, &[SurfaceVertex::desc(), ChunkDataVertex::desc()], Some(TextureFormat::Depth32Float), SurfacePass::Opaque, texture_backend);
+        let cutout_pipeline = create_surface_pipeline(&device, HDR_FORMAT, texture_bindgroup_layout, camera_bindgroup_layout, &sky_bindgroup_layout, &shadows.receiver_bindgroup_layout, &[SurfaceVertex::desc(), ChunkDataVertex::desc()], Some(TextureFormat::Depth32Float), SurfacePass::Cutout, texture_backend);
+        let translucent_pipeline = create_surface_pipeline(&device, HDR_FORMAT, texture_bindgroup_layout, camera_bindgroup_layout, &sky_bindgroup_layout, &shadows.receiver_bindgroup_layout, &[SurfaceVertex::desc(), ChunkDataVertex::desc()], Some(TextureFormat::Depth32Float), SurfacePass::Translucent, texture_backend);
+
+        let model_pipeline = create_model_pipeline(&device, HDR_FORMAT, texture_bindgroup_layout, camera_bindgroup_layout, &sky_bindgroup_layout, &shadows.receiver_bindgroup_layout, &[ModelVertex::desc(), ChunkDataVertex::desc()], Some(TextureFormat::Depth32Float), texture_backend);
+
+        let fluid_bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("fluid bindgroup layout"),
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
@@ -92,32 +191,184 @@ impl GameRenderer {
             ]
         });
 
-        let surface_pipeline = create_surface_pipeline(&device, surface_format, texture_bindgroup_layout, camera_bindgroup_layout, &[SurfaceVertex::desc(), ChunkDataVertex::desc()], Some(TextureFormat::Depth32Float));
-
-        let multisample_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("MS_Texture"),
-            size: Extent3d {width: dims.0, height: dims.1, depth_or_array_layers: 1},
-            mip_level_count: 1,
-            sample_count: 4,
-            dimension: wgpu::TextureDimension::D2,
-            format: surface_format,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
+        //tint, then (time, scroll speed, padding)
+        let fluid_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("fluid uniform buffer"),
+            size: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let fluid_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("fluid bindgroup"),
+            layout: &fluid_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: fluid_uniform_buffer.as_entire_binding()
+                }
+            ]
         });
 
-        let msview = multisample_texture.create_view(&TextureViewDescriptor::default());
+        let fluid_pipeline = create_fluid_pipeline(&device, HDR_FORMAT, texture_bindgroup_layout, camera_bindgroup_layout, &fluid_bindgroup_layout, &sky_bindgroup_layout, &[FluidVertex::desc(), ChunkDataVertex::desc()], Some(TextureFormat::Depth32Float), texture_backend);
 
-        let depth_texture = Texture::from_empty("depth texture", &device, wgpu::TextureFormat::Depth32Float, dims.0, dims.1, wgpu::FilterMode::Linear);
+        let outline_bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("outline bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ]
+        });
+
+        //the block's position, then padding
+        let outline_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("outline uniform buffer"),
+            size: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let outline_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("outline bindgroup"),
+            layout: &outline_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: outline_uniform_buffer.as_entire_binding()
+                }
+            ]
+        });
+
+        let outline_pipeline = create_outline_pipeline(&device, HDR_FORMAT, camera_bindgroup_layout, &outline_bindgroup_layout, Some(TextureFormat::Depth32Float));
+
+        let post = PostProcess::new(device.clone(), dims, surface_format);
+
+        let (multisample_texture, msview, depth_texture) = create_size_dependent_textures(&device, dims);
         Self {
-            device, queue, dims, surface_format, surface_pipeline, depth_texture, 
-            multisample_texture_view: msview, multisample_texture
+            device, queue, dims, surface_pipeline, cutout_pipeline, translucent_pipeline, depth_texture, 
+            multisample_texture_view: msview, multisample_texture,
+            fluid_pipeline, model_pipeline, fluid_uniform_buffer, fluid_bindgroup, time: 0.0,
+            sky_pipeline, sky_uniform_buffer, sky_bindgroup, sky: Sky::new(),
+            outline_pipeline, outline_uniform_buffer, outline_bindgroup, outline_target: None,
+            shadows, shadows_enabled: true, post,
+            last_sort_position: None,
+            culling_stats: CullingStats::default()
+        }
+    }
+
+    pub fn update_fluid_animation(&mut self, dt: f32) {
+        self.time = (self.time + dt) % FLUID_ANIMATION_PERIOD;
+        let uniform: [f32; 8] = [
+            FLUID_TINT[0], FLUID_TINT[1], FLUID_TINT[2], FLUID_TINT[3],
+            self.time, FLUID_SCROLL_SPEED, 0.0, 0.0
+        ];
+        self.queue.write_buffer(&self.fluid_uniform_buffer, 0, bytemuck::cast_slice(&uniform));
+    }
+
+    //advances the time of day and fits the fog to the render distance and the biome the camera is in
+    pub fn update_sky(&mut self, dt: f32, camera: &Camera, chunk_manager: &ChunkManager) {
+        let biome = chunk_manager.biome_at(camera.position.x.floor() as i32, camera.position.z.floor() as i32);
+        self.sky.update(dt, biome);
+
+        let uniform = self.sky.uniform(camera, chunk_manager.render_distance);
+        self.queue.write_buffer(&self.sky_uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    //None hides the outline
+    pub fn set_outline_target(&mut self, target: Option<Vector3<i32>>) {
+        if let Some(position) = target {
+            let uniform: [f32; 4] = [position.x as f32, position.y as f32, position.z as f32, 0.0];
+            self.queue.write_buffer(&self.outline_uniform_buffer, 0, bytemuck::cast_slice(&uniform));
         }
+        self.outline_target = target;
+    }
+
+    //re-sorts translucent quads back to front. Everything is re-sorted once the camera moved far enough, freshly meshed slices are sorted right away
+    pub fn sort_translucent_geometry(&mut self, camera: &Camera, chunk_manager: &mut ChunkManager) {
+        let resort_all = self.last_sort_position.map_or(true, |last| (camera.position - last).norm() > RESORT_DISTANCE);
+        let camera_pos = camera.position.coords;
+
+        for (_, chunk) in chunk_manager.chunks.iter_mut() {
+            for i in 0..16 {
+                if !chunk.states[i].has_mesh() {continue};
+                if resort_all || chunk.transparent_needs_sort[i] {
+                    sort_chunk_transparent_quads(&self.queue, camera_pos, chunk, i);
+                }
+            }
+        }
+
+        if resort_all {
+            self.last_sort_position = Some(camera.position);
+        }
+    }
+
+    pub fn resize(&mut self, dims: (u32, u32)) {
+        if dims == self.dims {return};
+
+        let (multisample_texture, msview, depth_texture) = create_size_dependent_textures(&self.device, dims);
+        self.multisample_texture = multisample_texture;
+        self.multisample_texture_view = msview;
+        self.depth_texture = depth_texture;
+        self.post.resize(dims);
+        self.dims = dims;
+    }
+
+    pub fn culling_stats(&self) -> CullingStats {
+        self.culling_stats
+    }
+
+    //which meshed slices of each chunk touch the view frustum and can be seen from the camera's slice, keyed like chunk_manager.chunks
+    fn cull_slices(&mut self, camera: &Camera, chunk_manager: &ChunkManager) -> HashMap<u32, [bool; 16]> {
+        let frustum = camera.frustum();
+        let mut stats = CullingStats::default();
+
+        let camera_slice = (
+            (camera.position.x / 16.0).floor() as i32,
+            (camera.position.z / 16.0).floor() as i32,
+            (camera.position.y / 16.0).floor().clamp(0.0, 15.0) as u32
+        );
+        //None when the camera's chunk isn't loaded yet, then only the frustum culls
+        let reachable = visible_slices(
+            camera_slice,
+            |(x, z, y)| chunk_manager.chunks.get(&xz_to_index(x, z)).map(|chunk| chunk.connectivity[y as usize]),
+            |(x, z, y)| frustum.intersects_slice(x, z, y)
+        );
+
+        let visible = chunk_manager.chunks.iter().map(|(index, chunk)| {
+            let slices = std::array::from_fn(|i| {
+                if !chunk.states[i].has_mesh() {return false};
+                stats.tested += 1;
+
+                let key = (chunk.chunk.position.x, chunk.chunk.position.y, i as u32);
+                if !frustum.intersects_slice(key.0, key.1, key.2) {
+                    stats.culled += 1;
+                    return false;
+                }
+                if reachable.as_ref().is_some_and(|reachable| !reachable.contains(&key)) {
+                    stats.occluded += 1;
+                    return false;
+                }
+                stats.visible += 1;
+                true
+            });
+            (*index, slices)
+        }).collect();
+
+        self.culling_stats = stats;
+        visible
     }
 
     pub fn render_surface(&mut self, 
         device: &Arc<wgpu::Device>,
         queue: &Arc<wgpu::Queue>,
-        output_texture: &mut wgpu::SurfaceTexture, 
         output_view: &wgpu::TextureView, 
         encoder: &mut wgpu::CommandEncoder,
         camera: &Camera,
@@ -127,12 +378,19 @@ impl GameRenderer {
         //if (workspace.chunk_manager.chunks.len() as u32) < (workspace.chunk_manager.render_distance * 2 + 1).pow(2) {return}
         let t = Stopwatch::start_new();
         let camera_bindgroup = &camera.bindgroup;
+        let visible = self.cull_slices(camera, chunk_manager);
+
+        self.shadows.update(queue, camera, &self.sky.light_direction(), self.shadows_enabled);
+        if self.shadows_enabled {
+            self.shadows.render(encoder, chunk_manager);
+        }
+        self.post.update(queue);
 
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("object render pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
                 view: &self.multisample_texture_view, 
-                resolve_target: Some(&output_view), 
+                resolve_target: None, 
                 ops: wgpu::Operations { 
                     load: wgpu::LoadOp::Clear(wgpu::Color {
                         r: 0.1,
@@ -159,9 +417,16 @@ impl GameRenderer {
             occlusion_query_set: None,
         });
 
+        //the sky is drawn over the whole target first, everything else covers it
+        render_pass.set_pipeline(&self.sky_pipeline);
+        render_pass.set_bind_group(0, &self.sky_bindgroup, &[]);
+        render_pass.draw(0..3, 0..1);
+
         render_pass.set_pipeline(&self.surface_pipeline);
         render_pass.set_bind_group(0, texture_bindgroup, &[]);
         render_pass.set_bind_group(1, camera_bindgroup, &[]);
+        render_pass.set_bind_group(2, &self.sky_bindgroup, &[]);
+        render_pass.set_bind_group(3, &self.shadows.receiver_bindgroup, &[]);
 
 
         let mut outeri = 0;
@@ -172,7 +437,7 @@ impl GameRenderer {
             let mut i = 0;
 
             for t in out {
-                if chunk.states[i] != ChunkState::Ready {i += 1; continue};
+                if !visible[index][i] {i += 1; continue};
                 let (vertex_buffer, index_buffer, ilen) = t.as_ref().unwrap();
                 if *ilen == 0 {
                     i += 1;
@@ -188,8 +453,130 @@ impl GameRenderer {
             }
             outeri += 1;
         }
+
+        //cutout geometry writes depth like the solid geometry, so it shares the pass and doesn't need sorting
+        render_pass.set_pipeline(&self.cutout_pipeline);
+
+        for (index, chunk) in chunk_manager.chunks.iter() {
+            for (i, t) in chunk.get_cutout_buffers().iter().enumerate() {
+                if !visible[index][i] {continue};
+                let Some((vertex_buffer, index_buffer, ilen)) = t.as_ref() else {continue};
+                if *ilen == 0 {continue};
+
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, chunk.slice_vertex_buffers[i].slice(..));
+                render_pass.draw_indexed(0..*ilen, 0, 0..1);
+            }
+        }
+
+        //non-cube blocks, alpha tested like the cutout geometry
+        render_pass.set_pipeline(&self.model_pipeline);
+
+        for (index, chunk) in chunk_manager.chunks.iter() {
+            for (i, t) in chunk.get_model_buffers().iter().enumerate() {
+                if !visible[index][i] {continue};
+                let Some((vertex_buffer, index_buffer, ilen)) = t.as_ref() else {continue};
+                if *ilen == 0 {continue};
+
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, chunk.slice_vertex_buffers[i].slice(..));
+                render_pass.draw_indexed(0..*ilen, 0, 0..1);
+            }
+        }
         drop(render_pass);
 
+        //translucent slices are drawn furthest first, the quads inside each slice are already sorted
+        let camera_pos: Vector3<f32> = camera.position.coords;
+        let mut translucent_slices = Vec::new();
+
+        for (index, chunk) in chunk_manager.chunks.iter() {
+            for (i, t) in chunk.get_transparent_buffers().iter().enumerate() {
+                if !visible[index][i] {continue};
+                let Some((vertex_buffer, index_buffer, ilen)) = t.as_ref() else {continue};
+                if *ilen == 0 {continue};
+
+                let center = Vector3::new(
+                    chunk.chunk.position.x as f32 * 16.0 + 8.0,
+                    i as f32 * 16.0 + 8.0,
+                    chunk.chunk.position.y as f32 * 16.0 + 8.0
+                );
+                translucent_slices.push(((center - camera_pos).norm_squared(), vertex_buffer, index_buffer, *ilen, &chunk.slice_vertex_buffers[i]));
+            }
+        }
+
+        translucent_slices.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("translucent render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment { 
+                view: &self.multisample_texture_view, 
+                resolve_target: Some(self.post.hdr_view()), 
+                ops: wgpu::Operations { 
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store
+                }
+            })],
+            depth_stencil_attachment: Some(
+                wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(
+                        wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store
+                        }
+                    ),
+                    stencil_ops: None,
+                }
+            ),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        //fluids go first so glass in front of water still blends over it
+        render_pass.set_pipeline(&self.fluid_pipeline);
+        render_pass.set_bind_group(0, texture_bindgroup, &[]);
+        render_pass.set_bind_group(1, camera_bindgroup, &[]);
+        render_pass.set_bind_group(2, &self.fluid_bindgroup, &[]);
+        render_pass.set_bind_group(3, &self.sky_bindgroup, &[]);
+
+        for (index, chunk) in chunk_manager.chunks.iter() {
+            for (i, t) in chunk.get_fluid_buffers().iter().enumerate() {
+                if !visible[index][i] {continue};
+                let Some((vertex_buffer, index_buffer, ilen)) = t.as_ref() else {continue};
+                if *ilen == 0 {continue};
+
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, chunk.slice_vertex_buffers[i].slice(..));
+                render_pass.draw_indexed(0..*ilen, 0, 0..1);
+            }
+        }
+
+        render_pass.set_pipeline(&self.translucent_pipeline);
+        //groups 2 and 3 held the fluid and sky uniforms
+        render_pass.set_bind_group(2, &self.sky_bindgroup, &[]);
+        render_pass.set_bind_group(3, &self.shadows.receiver_bindgroup, &[]);
+
+        for (_, vertex_buffer, index_buffer, ilen, slice_vertex_buffer) in translucent_slices {
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, slice_vertex_buffer.slice(..));
+            render_pass.draw_indexed(0..ilen, 0, 0..1);
+        }
+
+        //last, so the lines show through glass and water in front of the block
+        if self.outline_target.is_some() {
+            render_pass.set_pipeline(&self.outline_pipeline);
+            render_pass.set_bind_group(0, camera_bindgroup, &[]);
+            render_pass.set_bind_group(1, &self.outline_bindgroup, &[]);
+            render_pass.draw(0..24, 0..1);
+        }
+        drop(render_pass);
+
+        self.post.render(encoder, output_view);
+
         //println!("frame render: {}ms", t.elapsed_ms());
     }
 }
\ No newline at end of file
@@ -1,13 +1,12 @@
 use std::sync::Arc;
 
 use nalgebra::Point3;
-use shared::loaders::texture_loader::{initialize_load_textures, preload_textures};
-use wgpu::TextureFormat;
-use winit::window::Window;
+use shared::loaders::model_loader::preload_models;
+use winit::{dpi::PhysicalSize, window::Window};
 
 use crate::{global::globalstate::GlobalState, view::camera::Camera};
 
-use super::gamerenderer::GameRenderer;
+use super::{gamerenderer::GameRenderer, gpuinit::{choose_surface_format, create_camera_bindgroup_layout, request_device, GpuOptions}};
 
 pub struct GameWindow<'a> {
     surface: wgpu::Surface<'a>,
@@ -27,87 +26,49 @@ impl <'a> GameWindow<'a> {
     pub async fn new(window: Arc<Window>) -> Self {
         let window_size = window.inner_size();
 
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            flags: wgpu::InstanceFlags::empty(),
-            backends: wgpu::Backends::DX12,
-            dx12_shader_compiler: Default::default(),
-            gles_minor_version: wgpu::Gles3MinorVersion::Automatic
-        });
+        let options = GpuOptions::from_env();
+        let instance = options.create_instance();
 
         let surface = instance.create_surface(window.clone()).unwrap();
 
-        let adapter = instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false
-            }
-        ).await.unwrap();
-
-        println!("ADAPTER: {:?}", adapter.get_info());
-
-        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
-            required_features: wgpu::Features::TEXTURE_BINDING_ARRAY
-             | wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING 
-             | wgpu::Features::BGRA8UNORM_STORAGE | wgpu::Features::DEPTH_CLIP_CONTROL,
-            required_limits: wgpu::Limits {
-                max_sampled_textures_per_shader_stage: 121,
-                max_samplers_per_shader_stage: 121,
-                max_bind_groups: 5,
-                ..Default::default()
-            },
-            label: None,
-            memory_hints: wgpu::MemoryHints::Performance,
-        }, None).await.unwrap();
+        let adapter = options.request_adapter(&instance, Some(&surface)).await
+            .expect("no graphics adapter supports this window, try setting WGPU_BACKEND");
 
-        let surface_capabilities = surface.get_capabilities(&adapter);
+        let texture_backend = options.texture_backend_for(&adapter).expect("the graphics adapter doesn't support the texture backend");
+        println!("texture backend: {:?}", texture_backend);
 
-        println!("{:?}", surface_capabilities.formats);
+        let (device, queue) = request_device(&adapter, texture_backend).await.expect("the graphics adapter doesn't support the renderer");
 
-        // let surface_format = surface_capabilities.formats.iter()
-        //     .copied().find(|f| f.is_srgb())
-        //     .unwrap_or(surface_capabilities.formats[surface_capabilities.formats.len() - 1]);
-        let surface_format = TextureFormat::Rgba8UnormSrgb;
+        let surface_capabilities = surface.get_capabilities(&adapter);
+        let surface_format = choose_surface_format(&surface_capabilities)
+            .expect("the graphics adapter can't present to this window, try setting WGPU_BACKEND");
 
         let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | (surface_capabilities.usages & wgpu::TextureUsages::COPY_DST),
             format: surface_format,
-            width: window_size.width,
-            height: window_size.height,
+            width: window_size.width.max(1),
+            height: window_size.height.max(1),
             present_mode: surface_capabilities.present_modes[0],
             alpha_mode: surface_capabilities.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2
         };
 
-        let camera_bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }
-            ],
-            label: Some("camera bind group layout :)"),
-        });
+        let camera_bindgroup_layout = create_camera_bindgroup_layout(&device);
 
         surface.configure(&device, &surface_config);
 
         let device_arc = Arc::new(device);
         let queue_arc = Arc::new(queue);
 
-        preload_textures(&device_arc, &queue_arc, surface_format);
+        let (texture_bindgroup, texture_bindgroup_layout) = texture_backend.load_textures(&device_arc, &queue_arc);
 
-        let (texture_bindgroup, texture_bindgroup_layout) = initialize_load_textures(&device_arc, &queue_arc, surface_format);
+        //models look their textures up by alias, so they're loaded once the textures are indexed
+        preload_models();
 
         let renderer = GameRenderer::new(
             device_arc.clone(), queue_arc.clone(), (window_size.width, window_size.height), surface_format, 
-            &camera_bindgroup_layout, &texture_bindgroup_layout);
+            &camera_bindgroup_layout, &texture_bindgroup_layout, texture_backend);
 
         Self {
             device: device_arc,
@@ -123,19 +84,49 @@ impl <'a> GameWindow<'a> {
             texture_bindgroup_layout
         }
     }
+    //new_size is in physical pixels, so dpi changes come through here too
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>, camera: &mut Camera) {
+        self.window_size = new_size;
+
+        //minimized, keep the old configuration until the window comes back
+        if new_size.width == 0 || new_size.height == 0 {return};
+
+        self.surface_config.width = new_size.width;
+        self.surface_config.height = new_size.height;
+        self.surface.configure(&self.device, &self.surface_config);
+
+        self.renderer.resize((new_size.width, new_size.height));
+        camera.set_aspect_ratio(new_size.width as f32 / new_size.height as f32);
+    }
+
     pub fn render(&mut self, dt: f32, globalstate: &mut GlobalState) {
         
         globalstate.camera.update_camera(dt);
         globalstate.camera.update_matrices(&self.queue);
-
-        let mut output = self.surface.get_current_texture().unwrap();
-        let mut view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.renderer.update_fluid_animation(dt);
+        self.renderer.update_sky(dt, &globalstate.camera, &globalstate.chunk_manager);
+        self.renderer.sort_translucent_geometry(&globalstate.camera, &mut globalstate.chunk_manager);
+        self.renderer.set_outline_target(globalstate.target.map(|hit| hit.block));
+
+        if self.window_size.width == 0 || self.window_size.height == 0 {return};
+
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            //the surface went out of date between the resize event and now, reconfigure and skip this frame
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.surface_config);
+                return;
+            },
+            Err(wgpu::SurfaceError::Timeout) => return,
+            Err(e) => panic!("failed to get the surface texture: {:?}", e)
+        };
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Primary Encoder")
         });
 
-        self.renderer.render_surface(&self.device, &self.queue, &mut output, &mut view, &mut encoder, &globalstate.camera, &self.texture_bindgroup, &globalstate.chunk_manager);
+        self.renderer.render_surface(&self.device, &self.queue, &view, &mut encoder, &globalstate.camera, &self.texture_bindgroup, &globalstate.chunk_manager);
     
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
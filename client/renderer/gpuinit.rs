@@ -0,0 +1,146 @@
+use wgpu::TextureFormat;
+
+use super::texturebackend::TextureBackend;
+
+//block textures are always uploaded as rgba, whatever the surface ends up being
+pub const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+//requested when the adapter has them
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::BGRA8UNORM_STORAGE
+    .union(wgpu::Features::DEPTH_CLIP_CONTROL);
+
+#[derive(Clone, Copy, Debug)]
+pub struct GpuOptions {
+    pub backends: wgpu::Backends,
+    pub power_preference: wgpu::PowerPreference,
+    //use a software adapter (lavapipe, warp, llvmpipe) instead of a real gpu
+    pub force_fallback_adapter: bool,
+    //None picks whatever the adapter supports
+    pub texture_backend: Option<TextureBackend>
+}
+
+impl Default for GpuOptions {
+    fn default() -> Self {
+        Self {
+            //vulkan, metal, dx12 and gl, whichever the platform has
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            texture_backend: None
+        }
+    }
+}
+
+impl GpuOptions {
+    //WGPU_BACKEND (vulkan, dx12, metal, gl, comma separated), WGPU_POWER_PREF (low, high) and
+    //TEXTURE_BACKEND (binding_arrays, texture_array) override the defaults
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(default.backends),
+            power_preference: wgpu::util::power_preference_from_env().unwrap_or(default.power_preference),
+            texture_backend: std::env::var("TEXTURE_BACKEND").ok().and_then(|name| TextureBackend::from_name(&name.to_lowercase())),
+            ..default
+        }
+    }
+
+    //None if the requested texture backend isn't supported by the adapter
+    pub fn texture_backend_for(&self, adapter: &wgpu::Adapter) -> Option<TextureBackend> {
+        match self.texture_backend {
+            Some(backend) if backend.is_supported(adapter) => Some(backend),
+            Some(backend) => {
+                println!("adapter doesn't support the {:?} texture backend", backend);
+                None
+            },
+            None => Some(TextureBackend::choose(adapter))
+        }
+    }
+
+    pub fn create_instance(&self) -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            flags: wgpu::InstanceFlags::empty(),
+            backends: self.backends,
+            dx12_shader_compiler: wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default(),
+            gles_minor_version: wgpu::Gles3MinorVersion::Automatic
+        })
+    }
+
+    pub async fn request_adapter(&self, instance: &wgpu::Instance, surface: Option<&wgpu::Surface<'_>>) -> Option<wgpu::Adapter> {
+        instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: self.power_preference,
+            compatible_surface: surface,
+            force_fallback_adapter: self.force_fallback_adapter
+        }).await
+    }
+}
+
+//None if the adapter is missing a feature the texture backend needs or the limits can't be met
+pub async fn request_device(adapter: &wgpu::Adapter, texture_backend: TextureBackend) -> Option<(wgpu::Device, wgpu::Queue)> {
+    let features = adapter.features();
+    let required = texture_backend.required_features();
+    if !features.contains(required) {
+        println!("adapter is missing features: {:?}", required - features);
+        return None;
+    }
+
+    adapter.request_device(&wgpu::DeviceDescriptor {
+        required_features: required | (OPTIONAL_FEATURES & features),
+        required_limits: texture_backend.required_limits(wgpu::Limits {
+            max_bind_groups: 5,
+            ..wgpu::Limits::downlevel_defaults()
+        }).using_resolution(adapter.limits()),
+        label: None,
+        memory_hints: wgpu::MemoryHints::Performance,
+    }, None).await.map_err(|e| println!("failed to create device: {}", e)).ok()
+}
+
+//prefers an srgb format so the shaders' linear output is encoded the same everywhere.
+//None if the surface has no formats, which is how wgpu reports an adapter that can't present to it
+pub fn choose_surface_format(capabilities: &wgpu::SurfaceCapabilities) -> Option<TextureFormat> {
+    capabilities.formats.iter()
+        .copied()
+        .find(|f| f.is_srgb())
+        .or(capabilities.formats.first().copied())
+}
+
+pub fn create_camera_bindgroup_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }
+        ],
+        label: Some("camera bind group layout :)"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::{SurfaceCapabilities, TextureFormat};
+
+    use super::choose_surface_format;
+
+    #[test]
+    fn surface_format_prefers_srgb() {
+        let capabilities = SurfaceCapabilities {
+            formats: vec![TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb],
+            ..Default::default()
+        };
+        assert_eq!(choose_surface_format(&capabilities), Some(TextureFormat::Bgra8UnormSrgb));
+
+        let capabilities = SurfaceCapabilities {formats: vec![TextureFormat::Rgba16Float], ..Default::default()};
+        assert_eq!(choose_surface_format(&capabilities), Some(TextureFormat::Rgba16Float));
+    }
+
+    #[test]
+    fn no_surface_formats_is_an_error() {
+        assert_eq!(choose_surface_format(&SurfaceCapabilities::default()), None);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{path::Path, sync::Arc};
+
+use image::RgbaImage;
+use nalgebra::Point3;
+use shared::loaders::model_loader::preload_models;
+
+use crate::{view::camera::Camera, world::{chunkmanager::ChunkManager, mesher::MesherKind, meshthread::build_meshed_slice}};
+
+use super::{gamerenderer::GameRenderer, gpuinit::{create_camera_bindgroup_layout, request_device, GpuOptions}, renderctx::Renderctx};
+
+//the offscreen target, readable back to the cpu
+const TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+//draws a world into an offscreen texture instead of a window. With GpuOptions::force_fallback_adapter it runs on
+//software adapters, which is what the screenshot tests use on machines without a gpu
+pub struct HeadlessRenderer {
+    pub device: Arc<wgpu::Device>,
+    pub queue: Arc<wgpu::Queue>,
+    pub renderer: GameRenderer,
+    pub camera_bindgroup_layout: wgpu::BindGroupLayout,
+    texture_bindgroup: wgpu::BindGroup,
+    target: wgpu::Texture,
+    target_view: wgpu::TextureView,
+    dims: (u32, u32)
+}
+
+impl HeadlessRenderer {
+    //None if no adapter matching the options can run the renderer
+    pub async fn new(dims: (u32, u32), options: GpuOptions) -> Option<Self> {
+        let instance = options.create_instance();
+        let adapter = options.request_adapter(&instance, None).await?;
+
+        let texture_backend = options.texture_backend_for(&adapter)?;
+        let (device, queue) = request_device(&adapter, texture_backend).await?;
+        let camera_bindgroup_layout = create_camera_bindgroup_layout(&device);
+
+        let device = Arc::new(device);
+        let queue = Arc::new(queue);
+
+        let (texture_bindgroup, texture_bindgroup_layout) = texture_backend.load_textures(&device, &queue);
+        preload_models();
+
+        let renderer = GameRenderer::new(device.clone(), queue.clone(), dims, TARGET_FORMAT, &camera_bindgroup_layout, &texture_bindgroup_layout, texture_backend);
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("headless target"),
+            size: wgpu::Extent3d {width: dims.0, height: dims.1, depth_or_array_layers: 1},
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TARGET_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Some(Self {
+            device,
+            queue,
+            renderer,
+            camera_bindgroup_layout,
+            texture_bindgroup,
+            target,
+            target_view,
+            dims
+        })
+    }
+
+    //a camera with the target's aspect ratio. yaw, pitch and fov are in degrees like Camera::new
+    pub fn create_camera(&self, position: Point3<f32>, yaw: f32, pitch: f32, fov: f32) -> Camera {
+        Camera::new(position, yaw, pitch, self.dims.0 as f32 / self.dims.1 as f32, fov, self.device.clone(), &self.camera_bindgroup_layout)
+    }
+
+    //meshes every slice of every loaded chunk on this thread, so the next render sees the whole world
+    pub fn mesh_world(&self, chunk_manager: &mut ChunkManager, mesher: MesherKind) {
+        let ctx = Renderctx::new(self.device.clone(), self.queue.clone());
+
+        let slices = chunk_manager.chunks.iter()
+            .flat_map(|(index, chunkdraw)| (0..16).map(move |y| (*index, chunkdraw.chunk.position, y)))
+            .collect::<Vec<_>>();
+
+        for (index, position, y) in slices {
+            let Some(input) = chunk_manager.mesh_input(position.x, position.y, y) else {continue};
+            let meshed = build_meshed_slice(&ctx, &input, mesher);
+
+            let chunkdraw = chunk_manager.chunks.get_mut(&index).unwrap();
+            if chunkdraw.slice_vertex_buffers.is_empty() {
+                chunkdraw.set_slice_vertex_buffers(&self.device);
+            }
+            chunkdraw.set_meshed_slice(y, meshed);
+        }
+    }
+
+    pub fn render(&mut self, camera: &mut Camera, chunk_manager: &mut ChunkManager) -> RgbaImage {
+        camera.update_matrices(&self.queue);
+        //the clock doesn't move between screenshots
+        self.renderer.update_sky(0.0, camera, chunk_manager);
+        self.renderer.sort_translucent_geometry(camera, chunk_manager);
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Headless Encoder")
+        });
+
+        self.renderer.render_surface(&self.device, &self.queue, &self.target_view, &mut encoder, camera, &self.texture_bindgroup, chunk_manager);
+
+        //rows of a texture to buffer copy have to be padded to 256 bytes
+        let (width, height) = self.dims;
+        let unpadded_row = width * 4;
+        let padded_row = unpadded_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("headless readback"),
+            size: (padded_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.target,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height)
+                }
+            },
+            wgpu::Extent3d {width, height, depth_or_array_layers: 1}
+        );
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let data = slice.get_mapped_range();
+        let pixels = data.chunks(padded_row as usize)
+            .flat_map(|row| &row[..unpadded_row as usize])
+            .copied()
+            .collect::<Vec<u8>>();
+        drop(data);
+        readback.unmap();
+
+        RgbaImage::from_raw(width, height, pixels).unwrap()
+    }
+
+    pub fn render_to_png(&mut self, camera: &mut Camera, chunk_manager: &mut ChunkManager, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        self.render(camera, chunk_manager).save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, Vector2};
+    use pollster::FutureExt;
+    use shared::world::{blockrepr::WorldBlock, chunk::{local_xyz_to_index, xz_to_index, Chunk}};
+
+    use crate::{renderer::gpuinit::GpuOptions, world::{chunkdraw::ChunkDraw, chunkmanager::ChunkManager, mesher::MesherKind}};
+
+    use super::HeadlessRenderer;
+
+    //a 3x3 chunk stone floor, 4 blocks thick
+    fn floor_world() -> ChunkManager {
+        let mut chunk_manager = ChunkManager::new();
+        for cx in -1..=1 {
+            for cz in -1..=1 {
+                let grid = (0..16).map(|y_slice| {
+                    let mut slice = vec![WorldBlock::Air(0); 4096];
+                    if y_slice == 0 {
+                        for x in 0..16 {
+                            for z in 0..16 {
+                                for y in 0..4 {
+                                    slice[local_xyz_to_index(x, y, z) as usize] = WorldBlock::Stone(0);
+                                }
+                            }
+                        }
+                    }
+                    slice
+                }).collect();
+                let chunk = std::sync::Arc::new(Chunk::from_blocks(Vector2::new(cx, cz), grid));
+                chunk_manager.chunks.insert(xz_to_index(cx, cz), ChunkDraw::new(chunk));
+            }
+        }
+        chunk_manager
+    }
+
+    //the rendered floor is compared against this. After a change that's meant to alter the picture, regenerate it with
+    //STARFORGED_UPDATE_SCREENSHOTS=1 cargo test --bin client renders_a_floor_offscreen and check the new png by eye
+    const FLOOR_REFERENCE: &str = "res/tests/floor_offscreen.png";
+    //software adapters don't rasterize bit for bit the same, so small differences are allowed
+    const CHANNEL_TOLERANCE: u8 = 8;
+    const MAX_DIFFERENT_PIXELS: usize = 64 * 48 / 100;
+
+    //skipped on machines without a software adapter that can run the renderer
+    #[test]
+    fn renders_a_floor_offscreen() {
+        let options = GpuOptions {force_fallback_adapter: true, ..GpuOptions::default()};
+        let Some(mut headless) = HeadlessRenderer::new((64, 48), options).block_on() else {
+            println!("no software adapter can run the renderer, skipping");
+            return;
+        };
+
+        let mut chunk_manager = floor_world();
+        headless.mesh_world(&mut chunk_manager, MesherKind::default());
+
+        //looking down at the floor, the bottom of the image is stone and the top is the clear color
+        let mut camera = headless.create_camera(Point3::new(8.0, 12.0, 8.0), 0.0, -30.0, 80.0);
+        let image = headless.render(&mut camera, &mut chunk_manager);
+
+        assert_eq!(image.dimensions(), (64, 48));
+        assert_ne!(image.get_pixel(32, 2), image.get_pixel(32, 45));
+
+        if std::env::var_os("STARFORGED_UPDATE_SCREENSHOTS").is_some() {
+            image.save(FLOOR_REFERENCE).unwrap();
+        }
+
+        let reference = image::open(FLOOR_REFERENCE).unwrap().to_rgba8();
+        assert_eq!(reference.dimensions(), image.dimensions());
+
+        let different = image.pixels().zip(reference.pixels())
+            .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+            .count();
+        assert!(different <= MAX_DIFFERENT_PIXELS, "{} pixels differ from {}", different, FLOOR_REFERENCE);
+    }
+}
@@ -3,4 +3,11 @@ pub mod gamewindow;
 pub mod gamerenderer;
 pub mod vertex;
 pub mod surfacevertex;
-pub mod renderctx;
\ No newline at end of file
+pub mod fluidvertex;
+pub mod renderctx;
+pub mod gpuinit;
+pub mod headless;
+pub mod texturebackend;
+pub mod sky;
+pub mod shadows;
+pub mod postprocess;
@@ -1 +1,7 @@
-pub mod surface_pipeline;
\ No newline at end of file
+pub mod surface_pipeline;
+pub mod fluid_pipeline;
+pub mod model_pipeline;
+pub mod sky_pipeline;
+pub mod shadow_pipeline;
+pub mod post_pipeline;
+pub mod outline_pipeline;
@@ -0,0 +1,71 @@
+use std::{fs, path::Path, sync::Arc};
+
+//lines around the targeted block, depth tested against the world but not written so they don't hide each other
+pub fn create_outline_pipeline(
+    device: &Arc<wgpu::Device>,
+    texture_format: wgpu::TextureFormat,
+    camera_bindgroup_layout: &wgpu::BindGroupLayout,
+    outline_bindgroup_layout: &wgpu::BindGroupLayout,
+    depth_format: Option<wgpu::TextureFormat>
+) -> wgpu::RenderPipeline {
+    let shader_descriptor = wgpu::ShaderModuleDescriptor {
+        label: Some("outline shader descriptor"),
+        source: wgpu::ShaderSource::Wgsl(fs::read_to_string(Path::new("res/shaders/outlineshader.wgsl")).unwrap().into())
+    };
+
+    let shader = device.create_shader_module(shader_descriptor);
+
+    let color_targetstate = [Some(wgpu::ColorTargetState {
+        format: texture_format,
+        blend: None,
+        write_mask: wgpu::ColorWrites::ALL,
+    })];
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("outline pipeline layout"),
+        bind_group_layouts: &[camera_bindgroup_layout, outline_bindgroup_layout],
+        push_constant_ranges: &[]
+    });
+
+    let desc = wgpu::RenderPipelineDescriptor {
+        label: Some("outline pipeline descriptor"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default()
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &color_targetstate,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 4,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    };
+
+    device.create_render_pipeline(&desc)
+}
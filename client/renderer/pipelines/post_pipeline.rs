@@ -0,0 +1,61 @@
+use std::{fs, path::Path, sync::Arc};
+
+//one full screen pass of the post processing chain. Every pass shares blit.wgsl's vertex stage and differs in the fragment entry point
+pub fn create_post_pipeline(
+    device: &Arc<wgpu::Device>,
+    texture_format: wgpu::TextureFormat,
+    post_bindgroup_layout: &wgpu::BindGroupLayout,
+    fragment_entry_point: &str
+) -> wgpu::RenderPipeline {
+    let source = fs::read_to_string(Path::new("res/shaders/blit.wgsl")).unwrap() + "\n" + &fs::read_to_string(Path::new("res/shaders/postprocess.wgsl")).unwrap();
+    let shader_descriptor = wgpu::ShaderModuleDescriptor {
+        label: Some("post shader descriptor"),
+        source: wgpu::ShaderSource::Wgsl(source.into())
+    };
+
+    let shader = device.create_shader_module(shader_descriptor);
+
+    let color_targetstate = [Some(wgpu::ColorTargetState {
+        format: texture_format,
+        blend: None,
+        write_mask: wgpu::ColorWrites::ALL,
+    })];
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("post pipeline layout"),
+        bind_group_layouts: &[post_bindgroup_layout],
+        push_constant_ranges: &[]
+    });
+
+    let desc = wgpu::RenderPipelineDescriptor {
+        label: Some(fragment_entry_point),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default()
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: fragment_entry_point,
+            targets: &color_targetstate,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    };
+
+    device.create_render_pipeline(&desc)
+}
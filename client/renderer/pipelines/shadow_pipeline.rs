@@ -0,0 +1,60 @@
+use std::{fs, path::Path, sync::Arc};
+
+//depth only with no fragment stage. The bias pushes the stored depth away from the light so lit faces don't shadow themselves
+pub fn create_shadow_pipeline(
+    device: &Arc<wgpu::Device>,
+    caster_bindgroup_layout: &wgpu::BindGroupLayout,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+    depth_format: wgpu::TextureFormat
+) -> wgpu::RenderPipeline {
+    let shader_descriptor = wgpu::ShaderModuleDescriptor {
+        label: Some("shadow shader descriptor"),
+        source: wgpu::ShaderSource::Wgsl(fs::read_to_string(Path::new("res/shaders/shadowshader.wgsl")).unwrap().into())
+    };
+
+    let shader = device.create_shader_module(shader_descriptor);
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("shadow pipeline layout"),
+        bind_group_layouts: &[caster_bindgroup_layout],
+        push_constant_ranges: &[]
+    });
+
+    let desc = wgpu::RenderPipelineDescriptor {
+        label: Some("shadow pipeline descriptor"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: vertex_layouts,
+            compilation_options: wgpu::PipelineCompilationOptions::default()
+        },
+        fragment: None,
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            //cutout quads are single sided, both sides have to cast
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_format,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0
+            },
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    };
+
+    device.create_render_pipeline(&desc)
+}
@@ -1,16 +1,31 @@
-use std::{fs, path::Path, sync::Arc};
+use std::sync::Arc;
+
+use crate::renderer::{shadows::with_shadow_prelude, texturebackend::TextureBackend};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SurfacePass {
+    Opaque,
+    //alpha tested, pixels below the threshold are discarded so depth can still be written
+    Cutout,
+    //alpha blended and sorted back to front, doesn't write depth so everything behind still shows through
+    Translucent
+}
 
 pub fn create_surface_pipeline(
     device: &Arc<wgpu::Device>, 
     texture_format: wgpu::TextureFormat, 
     texture_bindgroup_layout: &wgpu::BindGroupLayout, 
     camera_bindgroup_layout: &wgpu::BindGroupLayout,
+    sky_bindgroup_layout: &wgpu::BindGroupLayout,
+    shadow_bindgroup_layout: &wgpu::BindGroupLayout,
     vertex_layouts: &[wgpu::VertexBufferLayout],
-    depth_format: Option<wgpu::TextureFormat>
+    depth_format: Option<wgpu::TextureFormat>,
+    pass: SurfacePass,
+    texture_backend: TextureBackend
 ) -> wgpu::RenderPipeline {
     let shader_descriptor = wgpu::ShaderModuleDescriptor {
         label: Some("shader descriptor"),
-        source: wgpu::ShaderSource::Wgsl(fs::read_to_string(Path::new("res/shaders/surfaceshader.wgsl")).unwrap().into())
+        source: wgpu::ShaderSource::Wgsl(with_shadow_prelude(texture_backend.shader_source("res/shaders/surfaceshader.wgsl")).into())
     };
 
     let shader = device.create_shader_module(shader_descriptor);
@@ -30,12 +45,16 @@ pub fn create_surface_pipeline(
 
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("surface pipeline layout"),
-        bind_group_layouts: &[&texture_bindgroup_layout, &camera_bindgroup_layout],
+        bind_group_layouts: &[&texture_bindgroup_layout, &camera_bindgroup_layout, &sky_bindgroup_layout, &shadow_bindgroup_layout],
         push_constant_ranges: &[]
     });
 
     let desc = wgpu::RenderPipelineDescriptor {
-        label: Some("surface pipeline descriptor"),
+        label: Some(match pass {
+            SurfacePass::Opaque => "surface pipeline descriptor",
+            SurfacePass::Cutout => "cutout surface pipeline descriptor",
+            SurfacePass::Translucent => "translucent surface pipeline descriptor",
+        }),
         layout: Some(&layout),
         vertex: wgpu::VertexState {
             module: &shader,
@@ -45,7 +64,10 @@ pub fn create_surface_pipeline(
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
-            entry_point: "fs_main",
+            entry_point: match pass {
+                SurfacePass::Cutout => "fs_cutout",
+                _ => "fs_main"
+            },
             targets: &color_targetstate,
             compilation_options: wgpu::PipelineCompilationOptions::default(),
         }),
@@ -60,7 +82,7 @@ pub fn create_surface_pipeline(
         },
         depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
             format,
-            depth_write_enabled: true,
+            depth_write_enabled: pass != SurfacePass::Translucent,
             depth_compare: wgpu::CompareFunction::Less,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
@@ -0,0 +1,323 @@
+use std::{mem, sync::Arc};
+
+use wgpu::{Extent3d, TextureViewDescriptor};
+
+use super::pipelines::post_pipeline::create_post_pipeline;
+
+//the world is drawn into this, lit values above 1 survive until the tonemapping
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+//the bloom buffers are this many times smaller than the screen on each side
+const BLOOM_DOWNSCALE: u32 = 2;
+
+//which buffer ends up on screen, for checking the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostDebugView {
+    Final,
+    //the hdr scene after exposure, clipped at 1
+    Scene,
+    //the blurred bright parts before they're added back
+    Bloom,
+    Luminance
+}
+
+impl PostDebugView {
+    pub fn next(self) -> Self {
+        match self {
+            PostDebugView::Final => PostDebugView::Scene,
+            PostDebugView::Scene => PostDebugView::Bloom,
+            PostDebugView::Bloom => PostDebugView::Luminance,
+            PostDebugView::Luminance => PostDebugView::Final
+        }
+    }
+}
+
+//read every frame, so changes show up on the next one
+#[derive(Debug, Clone, Copy)]
+pub struct PostSettings {
+    pub exposure: f32,
+    pub tonemapping: bool,
+    pub bloom: bool,
+    //luminance after exposure that starts to bloom
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub gamma: f32,
+    pub debug_view: PostDebugView
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            tonemapping: true,
+            bloom: true,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.6,
+            gamma: 2.2,
+            debug_view: PostDebugView::Final
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PostUniform {
+    exposure: f32,
+    bloom_threshold: f32,
+    bloom_intensity: f32,
+    gamma: f32,
+    debug_view: u32,
+    tonemapping: u32,
+    bloom: u32,
+    //the output encodes srgb by itself, the shader only corrects for a gamma other than 2.2
+    srgb_target: u32
+}
+
+impl PostSettings {
+    pub fn uniform(&self, srgb_target: bool) -> PostUniform {
+        PostUniform {
+            exposure: self.exposure,
+            bloom_threshold: self.bloom_threshold,
+            bloom_intensity: self.bloom_intensity,
+            gamma: self.gamma,
+            debug_view: self.debug_view as u32,
+            tonemapping: self.tonemapping as u32,
+            bloom: self.bloom as u32,
+            srgb_target: srgb_target as u32
+        }
+    }
+
+    //the blur only has to run when its result is shown
+    pub fn needs_bloom(&self) -> bool {
+        self.bloom || self.debug_view == PostDebugView::Bloom
+    }
+}
+
+fn create_target(device: &wgpu::Device, label: &str, dims: (u32, u32)) -> wgpu::TextureView {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: Extent3d {width: dims.0.max(1), height: dims.1.max(1), depth_or_array_layers: 1},
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: HDR_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    }).create_view(&TextureViewDescriptor::default())
+}
+
+//everything that has to match the screen size
+struct PostTargets {
+    hdr_view: wgpu::TextureView,
+    //the bright pass and the vertical blur write a, the horizontal blur writes b
+    bloom_a_view: wgpu::TextureView,
+    bloom_b_view: wgpu::TextureView,
+    bright_bindgroup: wgpu::BindGroup,
+    blur_horizontal_bindgroup: wgpu::BindGroup,
+    blur_vertical_bindgroup: wgpu::BindGroup,
+    composite_bindgroup: wgpu::BindGroup
+}
+
+impl PostTargets {
+    fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, sampler: &wgpu::Sampler, uniform_buffer: &wgpu::Buffer, dims: (u32, u32)) -> Self {
+        let hdr_view = create_target(device, "hdr target", dims);
+        let bloom_dims = (dims.0 / BLOOM_DOWNSCALE, dims.1 / BLOOM_DOWNSCALE);
+        let bloom_a_view = create_target(device, "bloom target a", bloom_dims);
+        let bloom_b_view = create_target(device, "bloom target b", bloom_dims);
+
+        //binding 2 is only read by the composite, the other passes get the hdr target there so nothing they render into is bound
+        let bindgroup = |label, color: &wgpu::TextureView, bloom: &wgpu::TextureView| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(color)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(bloom)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: uniform_buffer.as_entire_binding()
+                }
+            ]
+        });
+
+        Self {
+            bright_bindgroup: bindgroup("post bright bindgroup", &hdr_view, &hdr_view),
+            blur_horizontal_bindgroup: bindgroup("post horizontal blur bindgroup", &bloom_a_view, &hdr_view),
+            blur_vertical_bindgroup: bindgroup("post vertical blur bindgroup", &bloom_b_view, &hdr_view),
+            composite_bindgroup: bindgroup("post composite bindgroup", &hdr_view, &bloom_a_view),
+            hdr_view,
+            bloom_a_view,
+            bloom_b_view
+        }
+    }
+}
+
+fn full_screen_pass(encoder: &mut wgpu::CommandEncoder, label: &str, view: &wgpu::TextureView, pipeline: &wgpu::RenderPipeline, bindgroup: &wgpu::BindGroup) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store
+            }
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bindgroup, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+//takes the resolved hdr scene to the output: exposure, bloom from everything brighter than the threshold, tonemapping and gamma
+pub struct PostProcess {
+    device: Arc<wgpu::Device>,
+    dims: (u32, u32),
+    srgb_output: bool,
+    pub settings: PostSettings,
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bindgroup_layout: wgpu::BindGroupLayout,
+    targets: PostTargets,
+    bright_pipeline: wgpu::RenderPipeline,
+    blur_horizontal_pipeline: wgpu::RenderPipeline,
+    blur_vertical_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline
+}
+
+impl PostProcess {
+    pub fn new(device: Arc<wgpu::Device>, dims: (u32, u32), output_format: wgpu::TextureFormat) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true }
+            },
+            count: None
+        };
+
+        let bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post bindgroup layout"),
+            entries: &[
+                texture_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ]
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("post uniform buffer"),
+            size: mem::size_of::<PostUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        //linear filtering does the downsampling into the bloom targets
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let targets = PostTargets::new(&device, &bindgroup_layout, &sampler, &uniform_buffer, dims);
+
+        let bright_pipeline = create_post_pipeline(&device, HDR_FORMAT, &bindgroup_layout, "fs_bright");
+        let blur_horizontal_pipeline = create_post_pipeline(&device, HDR_FORMAT, &bindgroup_layout, "fs_blur_horizontal");
+        let blur_vertical_pipeline = create_post_pipeline(&device, HDR_FORMAT, &bindgroup_layout, "fs_blur_vertical");
+        let composite_pipeline = create_post_pipeline(&device, output_format, &bindgroup_layout, "fs_composite");
+
+        Self {
+            device, dims, srgb_output: output_format.is_srgb(), settings: PostSettings::default(),
+            uniform_buffer, sampler, bindgroup_layout, targets,
+            bright_pipeline, blur_horizontal_pipeline, blur_vertical_pipeline, composite_pipeline
+        }
+    }
+
+    //the msaa scene resolves into this
+    pub fn hdr_view(&self) -> &wgpu::TextureView {
+        &self.targets.hdr_view
+    }
+
+    pub fn resize(&mut self, dims: (u32, u32)) {
+        if dims == self.dims {return};
+
+        self.targets = PostTargets::new(&self.device, &self.bindgroup_layout, &self.sampler, &self.uniform_buffer, dims);
+        self.dims = dims;
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.settings.uniform(self.srgb_output)]));
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, output_view: &wgpu::TextureView) {
+        let targets = &self.targets;
+
+        if self.settings.needs_bloom() {
+            full_screen_pass(encoder, "bloom bright pass", &targets.bloom_a_view, &self.bright_pipeline, &targets.bright_bindgroup);
+            full_screen_pass(encoder, "bloom horizontal blur pass", &targets.bloom_b_view, &self.blur_horizontal_pipeline, &targets.blur_horizontal_bindgroup);
+            full_screen_pass(encoder, "bloom vertical blur pass", &targets.bloom_a_view, &self.blur_vertical_pipeline, &targets.blur_vertical_bindgroup);
+        }
+
+        full_screen_pass(encoder, "post composite pass", output_view, &self.composite_pipeline, &targets.composite_bindgroup);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PostDebugView, PostSettings};
+
+    #[test]
+    fn debug_view_cycles_back_to_final() {
+        assert_eq!(PostSettings::default().debug_view, PostDebugView::Final);
+
+        let mut view = PostDebugView::Final;
+        for _ in 0..3 {
+            view = view.next();
+            assert_ne!(view, PostDebugView::Final);
+        }
+        assert_eq!(view.next(), PostDebugView::Final);
+    }
+
+    #[test]
+    fn bloom_runs_for_its_debug_view() {
+        let mut settings = PostSettings {bloom: false, ..PostSettings::default()};
+        assert!(!settings.needs_bloom());
+
+        settings.debug_view = PostDebugView::Bloom;
+        assert!(settings.needs_bloom());
+    }
+}
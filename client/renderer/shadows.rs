@@ -0,0 +1,311 @@
+use std::{fs, mem, path::Path, sync::Arc};
+
+use nalgebra::{Matrix4, Orthographic3, Point3, Vector3, Vector4};
+use shared::loaders::texture::Texture;
+
+use crate::{view::{camera::{Camera, OPENGL_TO_WGPU_MATRIX}, frustum::Frustum}, world::{chunkdraw::ChunkDataVertex, chunkmanager::ChunkManager}};
+
+use super::{pipelines::shadow_pipeline::create_shadow_pipeline, surfacevertex::SurfaceVertex, vertex::Vertex};
+
+pub const CASCADE_COUNT: usize = 3;
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+//shadows end this far from the camera, past it everything is lit
+pub const SHADOW_DISTANCE: f32 = 128.0;
+//0 splits the cascades evenly, 1 logarithmically. In between keeps the first cascade from getting tiny
+const SPLIT_LAMBDA: f32 = 0.7;
+//how far towards the light the cascades reach, so mountains outside the view still cast into it
+const SHADOW_CASTER_DISTANCE: f32 = 96.0;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowUniform {
+    cascades: [[[f32; 4]; 4]; CASCADE_COUNT],
+    //enabled, texel size, 0, 0
+    settings: [f32; 4]
+}
+
+//the Shadows uniform, the shadow map bindings and shadow_factor, for the shaders that receive shadows
+const SHADOW_PRELUDE_PATH: &str = "res/shaders/shadows.wgsl";
+
+//a shader with the shadow bindings in front of it, they go in group 3
+pub fn with_shadow_prelude(shader: String) -> String {
+    let prelude = fs::read_to_string(Path::new(SHADOW_PRELUDE_PATH)).unwrap();
+    prelude + "\n" + &shader
+}
+
+//far distance of each cascade, the first one starts at near
+pub fn cascade_splits(near: f32, far: f32) -> [f32; CASCADE_COUNT] {
+    std::array::from_fn(|i| {
+        let p = (i + 1) as f32 / CASCADE_COUNT as f32;
+        let log = near * (far / near).powf(p);
+        let uniform = near + (far - near) * p;
+        uniform + (log - uniform) * SPLIT_LAMBDA
+    })
+}
+
+//an orthographic view down the light direction around a bounding sphere of the corners. The sphere keeps the size
+//constant while the camera turns and the origin is snapped to whole texels while it moves, so the edges don't shimmer
+pub fn cascade_matrix(corners: &[Point3<f32>; 8], light_direction: &Vector3<f32>) -> Matrix4<f32> {
+    let center = corners.iter().map(|c| c.coords).sum::<Vector3<f32>>() / 8.0;
+    let radius = corners.iter().map(|c| (c.coords - center).norm()).fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let up = if light_direction.y.abs() > 0.99 {Vector3::z()} else {Vector3::y()};
+    let eye = Point3::from(center + light_direction.normalize() * (radius + SHADOW_CASTER_DISTANCE));
+    let view = Matrix4::look_at_rh(&eye, &Point3::from(center), &up);
+    let mut projection = OPENGL_TO_WGPU_MATRIX * Orthographic3::new(-radius, radius, -radius, radius, 0.0, 2.0 * radius + SHADOW_CASTER_DISTANCE).to_homogeneous();
+
+    let texels = SHADOW_MAP_SIZE as f32 / 2.0;
+    let origin = projection * view * Vector4::new(0.0, 0.0, 0.0, 1.0);
+    projection[(0, 3)] += (origin.x * texels).round() / texels - origin.x;
+    projection[(1, 3)] += (origin.y * texels).round() / texels - origin.y;
+
+    projection * view
+}
+
+//the sun's shadow map, one layer per cascade
+pub struct ShadowMaps {
+    pipeline: wgpu::RenderPipeline,
+    texture: Texture,
+    layer_views: Vec<wgpu::TextureView>,
+    //each cascade's matrix for the depth pass
+    caster_buffers: Vec<wgpu::Buffer>,
+    caster_bindgroups: Vec<wgpu::BindGroup>,
+    cascades: [Matrix4<f32>; CASCADE_COUNT],
+    receiver_buffer: wgpu::Buffer,
+    //group 3 of the surface and model shaders
+    pub receiver_bindgroup: wgpu::BindGroup,
+    pub receiver_bindgroup_layout: wgpu::BindGroupLayout
+}
+
+impl ShadowMaps {
+    pub fn new(device: &Arc<wgpu::Device>) -> Self {
+        let texture = Texture::from_empty_shadows("shadow map", device, wgpu::TextureFormat::Depth32Float, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE, wgpu::FilterMode::Linear, CASCADE_COUNT as u32);
+
+        let layer_views = (0..CASCADE_COUNT as u32).map(|layer| {
+            texture.texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("shadow cascade view"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: layer,
+                array_layer_count: Some(1),
+                ..Default::default()
+            })
+        }).collect();
+
+        let caster_bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("shadow caster bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            ]
+        });
+
+        let caster_buffers = (0..CASCADE_COUNT).map(|_| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("shadow caster buffer"),
+                size: mem::size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        }).collect::<Vec<_>>();
+
+        let caster_bindgroups = caster_buffers.iter().map(|buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("shadow caster bindgroup"),
+                layout: &caster_bindgroup_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding()
+                    }
+                ]
+            })
+        }).collect();
+
+        let receiver_bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("shadow receiver bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Depth
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                }
+            ]
+        });
+
+        let receiver_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow receiver buffer"),
+            size: mem::size_of::<ShadowUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let receiver_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shadow receiver bindgroup"),
+            layout: &receiver_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: receiver_buffer.as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture.view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler)
+                }
+            ]
+        });
+
+        let pipeline = create_shadow_pipeline(device, &caster_bindgroup_layout, &[SurfaceVertex::desc(), ChunkDataVertex::desc()], wgpu::TextureFormat::Depth32Float);
+
+        Self {
+            pipeline,
+            texture,
+            layer_views,
+            caster_buffers,
+            caster_bindgroups,
+            cascades: [Matrix4::identity(); CASCADE_COUNT],
+            receiver_buffer,
+            receiver_bindgroup,
+            receiver_bindgroup_layout
+        }
+    }
+
+    //fits the cascades to the camera. When disabled the shaders skip the lookup and every surface is lit
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &Camera, light_direction: &Vector3<f32>, enabled: bool) {
+        let splits = cascade_splits(camera.znear(), SHADOW_DISTANCE.min(camera.zfar()));
+
+        let mut near = camera.znear();
+        for (i, far) in splits.into_iter().enumerate() {
+            self.cascades[i] = cascade_matrix(&camera.frustum_corners(near, far), light_direction);
+            queue.write_buffer(&self.caster_buffers[i], 0, bytemuck::cast_slice(&[Into::<[[f32; 4]; 4]>::into(self.cascades[i])]));
+            near = far;
+        }
+
+        let uniform = ShadowUniform {
+            cascades: self.cascades.map(|m| m.into()),
+            settings: [if enabled {1.0} else {0.0}, 1.0 / SHADOW_MAP_SIZE as f32, 0.0, 0.0]
+        };
+        queue.write_buffer(&self.receiver_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    //draws the opaque and cutout geometry into every cascade. Cutout quads cast whole, leaves shade like solid blocks
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, chunk_manager: &ChunkManager) {
+        for (i, view) in self.layer_views.iter().enumerate() {
+            let frustum = Frustum::from_matrix(&self.cascades[i]);
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("shadow render pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(
+                    wgpu::RenderPassDepthStencilAttachment {
+                        view,
+                        depth_ops: Some(
+                            wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(1.0),
+                                store: wgpu::StoreOp::Store
+                            }
+                        ),
+                        stencil_ops: None,
+                    }
+                ),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.caster_bindgroups[i], &[]);
+
+            for chunk in chunk_manager.chunks.values() {
+                for y in 0..16 {
+                    if !chunk.states[y].has_mesh() {continue};
+                    if !frustum.intersects_slice(chunk.chunk.position.x, chunk.chunk.position.y, y as u32) {continue};
+
+                    for buffers in [&chunk.get_solid_buffers()[y], &chunk.get_cutout_buffers()[y]] {
+                        let Some((vertex_buffer, index_buffer, ilen)) = buffers.as_ref() else {continue};
+                        if *ilen == 0 {continue};
+
+                        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                        render_pass.set_vertex_buffer(1, chunk.slice_vertex_buffers[y].slice(..));
+                        render_pass.draw_indexed(0..*ilen, 0, 0..1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, Vector3};
+
+    use super::{cascade_matrix, cascade_splits, CASCADE_COUNT, SHADOW_PRELUDE_PATH};
+
+    #[test]
+    fn prelude_has_every_cascade() {
+        let prelude = std::fs::read_to_string(SHADOW_PRELUDE_PATH).unwrap();
+        assert!(prelude.contains(&format!("array<mat4x4<f32>, {}>", CASCADE_COUNT)));
+        assert!(prelude.contains(&format!("const CASCADE_COUNT: u32 = {}u;", CASCADE_COUNT)));
+    }
+
+    #[test]
+    fn splits_grow_towards_the_far_plane() {
+        let splits = cascade_splits(0.1, 128.0);
+        assert!((splits[CASCADE_COUNT - 1] - 128.0).abs() < 0.001);
+        for i in 1..CASCADE_COUNT {
+            assert!(splits[i] > splits[i - 1]);
+        }
+        //the near cascades cover less than an even split would
+        assert!(splits[0] < 128.0 / CASCADE_COUNT as f32);
+    }
+
+    #[test]
+    fn cascades_contain_their_corners() {
+        let corners = [
+            Point3::new(-4.0, 60.0, -4.0), Point3::new(4.0, 60.0, -4.0), Point3::new(-4.0, 68.0, -4.0), Point3::new(4.0, 68.0, -4.0),
+            Point3::new(-20.0, 50.0, -40.0), Point3::new(20.0, 50.0, -40.0), Point3::new(-20.0, 80.0, -40.0), Point3::new(20.0, 80.0, -40.0),
+        ];
+
+        for light in [Vector3::new(0.3, 0.9, 0.2), Vector3::y(), Vector3::new(1.0, 0.05, 0.3)] {
+            let matrix = cascade_matrix(&corners, &light.normalize());
+            for corner in corners.iter() {
+                let clip = matrix * corner.to_homogeneous();
+                assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0, "{:?} is outside the cascade", corner);
+                assert!((0.0..=1.0).contains(&clip.z), "{:?} is outside the cascade depth", corner);
+            }
+        }
+    }
+}
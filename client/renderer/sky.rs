@@ -0,0 +1,154 @@
+use std::f32::consts::TAU;
+
+use nalgebra::{Matrix4, Vector3};
+use shared::world::biomemap::Biome;
+
+use crate::view::camera::Camera;
+
+//seconds for a whole day and night
+pub const DAY_LENGTH: f32 = 600.0;
+//0 is sunrise, 0.25 noon, 0.5 sunset and 0.75 midnight
+const START_TIME_OF_DAY: f32 = 0.2;
+//the fog starts this far into the visible distance and hides everything past it
+const FOG_START_FRACTION: f32 = 0.6;
+//how quickly the fog follows the biome under the camera, higher is faster
+const FOG_BLEND_SPEED: f32 = 0.5;
+
+const DAY_ZENITH: Vector3<f32> = Vector3::new(0.25, 0.45, 0.85);
+const DAY_HORIZON: Vector3<f32> = Vector3::new(0.65, 0.8, 0.95);
+const NIGHT_ZENITH: Vector3<f32> = Vector3::new(0.01, 0.01, 0.04);
+const NIGHT_HORIZON: Vector3<f32> = Vector3::new(0.04, 0.05, 0.1);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyUniform {
+    inv_view_proj: [[f32; 4]; 4],
+    //xyz towards the sun, w is 1 in daylight and 0 at night
+    sun_direction: [f32; 4],
+    zenith_color: [f32; 4],
+    //the sky at the horizon, everything far away fades into it
+    fog_color: [f32; 4],
+    //start, end in blocks from the camera
+    fog_range: [f32; 4]
+}
+
+//fog colour multiplier and the share of the render distance that stays visible
+pub fn biome_fog(biome: Biome) -> (Vector3<f32>, f32) {
+    match biome {
+        Biome::HauntedWoodlands => (Vector3::new(0.45, 0.5, 0.4), 0.35),
+        Biome::SnowyPlains => (Vector3::new(1.05, 1.05, 1.1), 0.8),
+        Biome::Desert => (Vector3::new(1.1, 0.95, 0.75), 1.0),
+        _ => (Vector3::repeat(1.0), 1.0)
+    }
+}
+
+//the sun rises in +x and sets in -x, tilted a little towards +z so it never passes straight overhead
+pub fn sun_direction(time_of_day: f32) -> Vector3<f32> {
+    let angle = time_of_day * TAU;
+    Vector3::new(angle.cos(), angle.sin(), 0.3).normalize()
+}
+
+//1 during the day, 0 at night, fading while the sun crosses the horizon
+pub fn daylight(sun_direction: &Vector3<f32>) -> f32 {
+    let t = ((sun_direction.y + 0.15) / 0.35).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+//(fog start, fog end) in blocks. The end sits at the edge of the loaded chunks so they never show a hard edge
+pub fn fog_range(render_distance: u32, visibility: f32) -> (f32, f32) {
+    let end = render_distance as f32 * 16.0 * visibility;
+    (end * FOG_START_FRACTION, end)
+}
+
+pub struct Sky {
+    pub time_of_day: f32,
+    //eased towards biome_fog of the biome under the camera
+    fog_tint: Vector3<f32>,
+    fog_visibility: f32
+}
+
+impl Sky {
+    pub fn new() -> Self {
+        Self {
+            time_of_day: START_TIME_OF_DAY,
+            fog_tint: Vector3::repeat(1.0),
+            fog_visibility: 1.0
+        }
+    }
+
+    //biome is None while the camera's chunk isn't loaded, the fog stays as it was
+    pub fn update(&mut self, dt: f32, biome: Option<Biome>) {
+        self.time_of_day = (self.time_of_day + dt / DAY_LENGTH).fract();
+
+        if let Some(biome) = biome {
+            let (tint, visibility) = biome_fog(biome);
+            let blend = 1.0 - (-dt * FOG_BLEND_SPEED).exp();
+            self.fog_tint = self.fog_tint.lerp(&tint, blend);
+            self.fog_visibility += (visibility - self.fog_visibility) * blend;
+        }
+    }
+
+    //the sun, or the moon opposite it once the sun has set
+    pub fn light_direction(&self) -> Vector3<f32> {
+        let sun = sun_direction(self.time_of_day);
+        if sun.y >= 0.0 {sun} else {-sun}
+    }
+
+    pub fn uniform(&self, camera: &Camera, render_distance: u32) -> SkyUniform {
+        let sun = sun_direction(self.time_of_day);
+        let daylight = daylight(&sun);
+
+        let horizon = NIGHT_HORIZON.lerp(&DAY_HORIZON, daylight).component_mul(&self.fog_tint);
+        //thick fog hides the blue overhead too
+        let zenith = NIGHT_ZENITH.lerp(&DAY_ZENITH, daylight).lerp(&horizon, 1.0 - self.fog_visibility);
+        let (fog_start, fog_end) = fog_range(render_distance, self.fog_visibility);
+
+        SkyUniform {
+            inv_view_proj: camera.view_proj_matrix().try_inverse().unwrap_or(Matrix4::identity()).into(),
+            sun_direction: [sun.x, sun.y, sun.z, daylight],
+            zenith_color: [zenith.x, zenith.y, zenith.z, 1.0],
+            fog_color: [horizon.x, horizon.y, horizon.z, 1.0],
+            fog_range: [fog_start, fog_end, 0.0, 0.0]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::world::biomemap::Biome;
+
+    use super::{biome_fog, daylight, fog_range, sun_direction, Sky};
+
+    #[test]
+    fn sun_follows_the_clock() {
+        assert!(sun_direction(0.25).y > 0.9);
+        assert!(sun_direction(0.75).y < -0.9);
+        assert!(sun_direction(0.0).y.abs() < 0.01);
+
+        assert_eq!(daylight(&sun_direction(0.25)), 1.0);
+        assert_eq!(daylight(&sun_direction(0.75)), 0.0);
+    }
+
+    #[test]
+    fn fog_ends_at_the_render_distance() {
+        assert_eq!(fog_range(8, 1.0).1, 128.0);
+        assert!(fog_range(8, 1.0).0 < 128.0);
+
+        //haunted woodlands are murkier than the plains
+        let (_, haunted) = biome_fog(Biome::HauntedWoodlands);
+        assert!(fog_range(8, haunted).1 < fog_range(8, biome_fog(Biome::Plains).1).1);
+    }
+
+    #[test]
+    fn fog_eases_into_the_biome() {
+        let mut sky = Sky::new();
+        sky.update(1.0, Some(Biome::HauntedWoodlands));
+        let (_, haunted) = biome_fog(Biome::HauntedWoodlands);
+        assert!(sky.fog_visibility < 1.0 && sky.fog_visibility > haunted);
+
+        for _ in 0..100 {
+            sky.update(1.0, Some(Biome::HauntedWoodlands));
+        }
+        assert!((sky.fog_visibility - haunted).abs() < 0.01);
+    }
+}
@@ -47,12 +47,12 @@ impl SurfaceVertex {
         }
 
         match texture_indices.1 {
-            FaceTexture::Static(v) => d1.bitor_assign((v as u32) << 8),
+            FaceTexture::Static(v) => d1.bitor_assign((v as u32) << 12),
             //FaceTexture::Dynamic(v, _) => d1.bitor_assign(1 << 1),
         }
 
         match texture_indices.2 {
-            FaceTexture::Static(v) => d1.bitor_assign((v as u32) << 16),
+            FaceTexture::Static(v) => d1.bitor_assign((v as u32) << 22),
             //FaceTexture::Dynamic(v, _) => {},
         }
 
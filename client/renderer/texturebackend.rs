@@ -0,0 +1,107 @@
+use std::{fs, path::Path, sync::Arc};
+
+use shared::loaders::texture_loader::{initialize_load_textures, initialize_texture_array_textures, preload_textures};
+
+use super::gpuinit::TEXTURE_FORMAT;
+
+//how the block textures are bound. The block shaders only call sample_diffuse/normal/emissive, the backend's prelude
+//supplies the group 0 bindings behind them
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextureBackend {
+    //one binding array slot per texture, any size per texture
+    BindingArrays,
+    //one texture_2d_array per texture type, for adapters (and webgpu) without binding arrays
+    TextureArray
+}
+
+impl TextureBackend {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "binding_arrays" => Some(TextureBackend::BindingArrays),
+            "texture_array" => Some(TextureBackend::TextureArray),
+            _ => None
+        }
+    }
+
+    pub fn required_features(&self) -> wgpu::Features {
+        match self {
+            TextureBackend::BindingArrays => wgpu::Features::TEXTURE_BINDING_ARRAY
+                | wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+            TextureBackend::TextureArray => wgpu::Features::empty()
+        }
+    }
+
+    pub fn required_limits(&self, limits: wgpu::Limits) -> wgpu::Limits {
+        match self {
+            TextureBackend::BindingArrays => wgpu::Limits {
+                max_sampled_textures_per_shader_stage: 121,
+                max_samplers_per_shader_stage: 121,
+                ..limits
+            },
+            TextureBackend::TextureArray => limits
+        }
+    }
+
+    pub fn is_supported(&self, adapter: &wgpu::Adapter) -> bool {
+        adapter.features().contains(self.required_features())
+    }
+
+    pub fn choose(adapter: &wgpu::Adapter) -> Self {
+        Self::choose_for(adapter.features())
+    }
+
+    //binding arrays when the features allow them, the texture array otherwise
+    pub fn choose_for(features: wgpu::Features) -> Self {
+        if features.contains(TextureBackend::BindingArrays.required_features()) {
+            TextureBackend::BindingArrays
+        } else {
+            TextureBackend::TextureArray
+        }
+    }
+
+    //loads the texture manifest and binds it the way this backend's shaders expect
+    pub fn load_textures(&self, device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Queue>) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {
+        preload_textures(device, queue, TEXTURE_FORMAT);
+        match self {
+            TextureBackend::BindingArrays => initialize_load_textures(device, queue, TEXTURE_FORMAT),
+            TextureBackend::TextureArray => initialize_texture_array_textures(device, queue, TEXTURE_FORMAT)
+        }
+    }
+
+    fn prelude_path(&self) -> &'static str {
+        match self {
+            TextureBackend::BindingArrays => "res/shaders/textures_binding_array.wgsl",
+            TextureBackend::TextureArray => "res/shaders/textures_array.wgsl"
+        }
+    }
+
+    //a block shader with this backend's texture bindings in front of it
+    pub fn shader_source(&self, path: &str) -> String {
+        let prelude = fs::read_to_string(Path::new(self.prelude_path())).unwrap();
+        let shader = fs::read_to_string(Path::new(path)).unwrap();
+        prelude + "\n" + &shader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextureBackend;
+
+    #[test]
+    fn backends_parse_from_their_names() {
+        assert_eq!(TextureBackend::from_name("binding_arrays"), Some(TextureBackend::BindingArrays));
+        assert_eq!(TextureBackend::from_name("texture_array"), Some(TextureBackend::TextureArray));
+        assert_eq!(TextureBackend::from_name("bindless"), None);
+        assert_eq!(TextureBackend::from_name(""), None);
+    }
+
+    #[test]
+    fn binding_arrays_need_both_features() {
+        let binding_arrays = TextureBackend::BindingArrays.required_features();
+        assert_eq!(TextureBackend::choose_for(binding_arrays), TextureBackend::BindingArrays);
+        assert_eq!(TextureBackend::choose_for(binding_arrays | wgpu::Features::DEPTH_CLIP_CONTROL), TextureBackend::BindingArrays);
+
+        assert_eq!(TextureBackend::choose_for(wgpu::Features::empty()), TextureBackend::TextureArray);
+        assert_eq!(TextureBackend::choose_for(wgpu::Features::TEXTURE_BINDING_ARRAY), TextureBackend::TextureArray);
+    }
+}
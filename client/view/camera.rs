@@ -5,11 +5,14 @@ use nalgebra::{Matrix4, Perspective3, Point3, Vector2, Vector3};
 use wgpu::util::DeviceExt;
 use winit::{event::ElementState, keyboard::KeyCode};
 
+use super::frustum::Frustum;
+
+//maps opengl's -1..1 depth to wgpu's 0..1. nalgebra takes the elements row by row
 pub const OPENGL_TO_WGPU_MATRIX: nalgebra::Matrix4<f32> = nalgebra::Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
     0.0, 1.0, 0.0, 0.0,
-    0.0, 0.0, 0.5, 0.0,
-    0.0, 0.0, 0.5, 1.0,
+    0.0, 0.0, 0.5, 0.5,
+    0.0, 0.0, 0.0, 1.0,
 );
 
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
@@ -140,6 +143,40 @@ impl Camera {
         }]));
     }
 
+    pub fn view_proj_matrix(&self) -> Matrix4<f32> {
+        self.view_proj_matrix
+    }
+
+    pub fn znear(&self) -> f32 {
+        self.znear
+    }
+
+    pub fn zfar(&self) -> f32 {
+        self.zfar
+    }
+
+    //corners of the part of the view frustum between two distances along the look vector, near ones first
+    pub fn frustum_corners(&self, near: f32, far: f32) -> [Point3<f32>; 8] {
+        let (look, right, up) = (self.look_vector(), self.right_vector(), self.up_vector());
+        let tan_half_fov = (self.fov / 2.0).tan();
+
+        let mut corners = [Point3::origin(); 8];
+        for (i, distance) in [near, far].into_iter().enumerate() {
+            let center = self.position + look * distance;
+            let half_height = distance * tan_half_fov;
+            let half_width = half_height * self.aspect_ratio;
+            for (j, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].into_iter().enumerate() {
+                corners[i * 4 + j] = center + right * (x * half_width) + up * (y * half_height);
+            }
+        }
+        corners
+    }
+
+    //planes of the last matrices sent to the gpu
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&self.view_proj_matrix)
+    }
+
     pub fn look_vector(&self) -> Vector3<f32> {
         let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
         let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
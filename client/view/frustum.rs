@@ -0,0 +1,134 @@
+use nalgebra::{Matrix4, Vector3, Vector4};
+
+//planes point inwards, a point p is inside a plane when dot(plane.xyz, p) + plane.w >= 0
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    pub planes: [Vector4<f32>; 6]
+}
+
+impl Frustum {
+    //Gribb/Hartmann plane extraction. wgpu clip space has z in 0..w, so the near plane is just the third row
+    pub fn from_matrix(view_proj: &Matrix4<f32>) -> Self {
+        let row = |i: usize| -> Vector4<f32> {view_proj.row(i).transpose()};
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        let planes = [
+            r3 + r0, //left
+            r3 - r0, //right
+            r3 + r1, //bottom
+            r3 - r1, //top
+            r2,      //near
+            r3 - r2, //far
+        ].map(|p| {
+            let length = p.xyz().norm();
+            if length > 0.0 {p / length} else {p}
+        });
+
+        Self { planes }
+    }
+
+    //conservative: boxes crossing a corner of the frustum can pass even though they're outside
+    pub fn intersects_aabb(&self, min: Vector3<f32>, max: Vector3<f32>) -> bool {
+        for plane in self.planes.iter() {
+            //the corner furthest along the plane normal
+            let positive = Vector3::new(
+                if plane.x >= 0.0 {max.x} else {min.x},
+                if plane.y >= 0.0 {max.y} else {min.y},
+                if plane.z >= 0.0 {max.z} else {min.z},
+            );
+
+            if plane.xyz().dot(&positive) + plane.w < 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn intersects_slice(&self, chunk_x: i32, chunk_z: i32, y_slice: u32) -> bool {
+        let min = Vector3::new(chunk_x as f32 * 16.0, y_slice as f32 * 16.0, chunk_z as f32 * 16.0);
+        self.intersects_aabb(min, min + Vector3::repeat(16.0))
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CullingStats {
+    //ready slices with geometry that were tested against the frustum this frame
+    pub tested: u32,
+    pub culled: u32,
+    //inside the frustum but hidden behind solid slices, see world::occlusion
+    pub occluded: u32,
+    pub visible: u32
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Matrix4, Perspective3, Point3, Vector3};
+
+    use crate::view::camera::OPENGL_TO_WGPU_MATRIX;
+
+    use super::Frustum;
+
+    //90 degree fov looking down -z from the origin, near 0.1, far 100
+    fn test_frustum() -> Frustum {
+        let view = Matrix4::look_at_rh(&Point3::origin(), &Point3::new(0.0, 0.0, -1.0), &Vector3::y());
+        let projection = OPENGL_TO_WGPU_MATRIX * Perspective3::new(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0).as_matrix();
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    fn unit_box_at(x: f32, y: f32, z: f32) -> (Vector3<f32>, Vector3<f32>) {
+        let center = Vector3::new(x, y, z);
+        (center - Vector3::repeat(0.5), center + Vector3::repeat(0.5))
+    }
+
+    //culling, mesh priority and the occlusion flood all take the camera's matrix, a wrong depth remap skews every one
+    #[test]
+    fn wgpu_depth_runs_from_zero_at_near_to_one_at_far() {
+        let projection = OPENGL_TO_WGPU_MATRIX * Perspective3::new(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0).as_matrix();
+        let depth = |z: f32| {
+            let clip = projection * Point3::new(0.0, 0.0, z).to_homogeneous();
+            clip.z / clip.w
+        };
+
+        assert!(depth(-0.1).abs() < 1e-5);
+        assert!((depth(-100.0) - 1.0).abs() < 1e-5);
+        assert!(depth(-10.0) > 0.0 && depth(-10.0) < 1.0);
+    }
+
+    #[test]
+    fn boxes_in_front_are_visible() {
+        let frustum = test_frustum();
+        for (x, y, z) in [(0.0, 0.0, -5.0), (3.0, 0.0, -5.0), (0.0, -4.0, -5.0), (0.0, 0.0, -99.0)] {
+            let (min, max) = unit_box_at(x, y, z);
+            assert!(frustum.intersects_aabb(min, max), "box at {} {} {} should be visible", x, y, z);
+        }
+    }
+
+    #[test]
+    fn boxes_outside_are_culled() {
+        let frustum = test_frustum();
+        //behind, too far left/right/up/down for the 45 degree half angle, past the far plane
+        for (x, y, z) in [(0.0, 0.0, 5.0), (-8.0, 0.0, -5.0), (8.0, 0.0, -5.0), (0.0, 8.0, -5.0), (0.0, -8.0, -5.0), (0.0, 0.0, -102.0)] {
+            let (min, max) = unit_box_at(x, y, z);
+            assert!(!frustum.intersects_aabb(min, max), "box at {} {} {} should be culled", x, y, z);
+        }
+    }
+
+    #[test]
+    fn boxes_straddling_a_plane_are_kept() {
+        let frustum = test_frustum();
+        //the right plane passes through x = 5 at z = -5
+        let (min, max) = unit_box_at(5.3, 0.0, -5.0);
+        assert!(frustum.intersects_aabb(min, max));
+        //the camera sits inside this one
+        assert!(frustum.intersects_aabb(Vector3::repeat(-1.0), Vector3::repeat(1.0)));
+    }
+
+    #[test]
+    fn slices_use_chunk_coordinates() {
+        let frustum = test_frustum();
+        //slice 0 of chunk (0, -1) spans z -16..0 and y 0..16, which the camera looks into
+        assert!(frustum.intersects_slice(0, -1, 0));
+        assert!(!frustum.intersects_slice(0, 1, 0));
+        assert!(!frustum.intersects_slice(0, -1, 10));
+    }
+}
@@ -1,11 +1,11 @@
-use std::{collections::{HashMap, VecDeque}, sync::Arc};
+use std::collections::{HashMap, VecDeque};
 
 use nalgebra::Vector3;
-use shared::world::{block::BlockFace, blockrepr::{does_not_render, get_block_id, get_surface_texture_indices, has_partial_transparency, is_fluid, WorldBlock}, chunk::{get_block_at_absolute, xz_to_index, Chunk}};
+use shared::world::{block::{BlockFace, FaceTexture}, blockrepr::{does_not_render, get_block_model_alias, get_surface_texture_indices, has_partial_transparency, is_cutout, is_fluid, WorldBlock}};
 
 use crate::renderer::surfacevertex::{calculate_illumination_bytes, SurfaceVertex};
 
-use super::depthsort::Quad;
+use super::{depthsort::Quad, meshinput::MeshInput};
 
 pub fn generate_indices(vertex_count: usize) -> Vec<u32> {
     let indices_count = vertex_count / 4;
@@ -25,192 +25,139 @@ pub fn generate_indices(vertex_count: usize) -> Vec<u32> {
 #[derive(PartialEq, Clone)]
 pub enum MeshStageType {
     Solid,
+    Cutout,
     Transparent,
     Fluid
 }
 
-pub fn binary_mesh(chunk_x: i32, chunk_z: i32, y_slice: u32, chunks: &HashMap<u32, Arc<Chunk>>, stage: MeshStageType) -> (Vec<SurfaceVertex>, Vec<u32>, u32, Vec<Quad>) {
-    //if it is entirely air, return early
-    if chunks.get(&xz_to_index(chunk_x, chunk_z)).unwrap().fullair[y_slice as usize] {
-        return (vec![], vec![], 0, vec![]);
+impl MeshStageType {
+    //whether the block gets faces emitted in this stage
+    pub fn contains(&self, block: &WorldBlock) -> bool {
+        if does_not_render(block) || get_block_model_alias(block).is_some() {return false};
+        match self {
+            MeshStageType::Solid => !has_partial_transparency(block) && !is_fluid(block),
+            MeshStageType::Cutout => is_cutout(block),
+            MeshStageType::Transparent => has_partial_transparency(block) && !is_fluid(block) && !is_cutout(block),
+            MeshStageType::Fluid => is_fluid(block),
+        }
     }
-    
-    let mut axis_columns = [[[0u32; 18]; 18]; 3];
-
-    let mut column_face_masks = [[[0u32; 18]; 18]; 6];
 
-    #[inline]
-    fn add_voxel_to_axis_cols(
-        is_transparent: bool,
-        is_fluid: bool,
-        x: usize,
-        y: usize,
-        z: usize,
-        axis_cols: &mut [[[u32; 18]; 18]; 3],
-        stage: &MeshStageType
-    ) {
-        if 
-            (!is_transparent && *stage == MeshStageType::Solid) ||
-            (is_transparent && *stage == MeshStageType::Transparent) ||
-            (is_fluid && *stage == MeshStageType::Fluid)
-          {
-            // x,z - y axis
-            axis_cols[0][z][x] |= 1u32 << y as u32;
-            // z,y - x axis
-            axis_cols[1][y][z] |= 1u32 << x as u32;
-            // x,y - z axis
-            axis_cols[2][y][x] |= 1u32 << z as u32;
+    //whether a face of this stage is hidden by the neighbouring block.
+    //neighbours that aren't loaded (or are outside the world) hide the face, same as the fast mesher.
+    //cutout blocks don't hide each other since you can see through the gaps in them
+    pub fn is_occluded_by(&self, neighbor: Option<&WorldBlock>) -> bool {
+        match neighbor {
+            Some(b) => !has_partial_transparency(b) || (*self != MeshStageType::Cutout && self.contains(b)),
+            None => true
         }
     }
+}
 
-    let chunk = chunks.get(&xz_to_index(chunk_x, chunk_z)).unwrap();
+//face direction of each column face mask, descending then ascending for the y, x and z axis
+const MASK_FACES: [BlockFace; 6] = [
+    BlockFace::Bottom,
+    BlockFace::Top,
+    BlockFace::Left,
+    BlockFace::Right,
+    BlockFace::Back,
+    BlockFace::Front,
+];
 
-    for z in 0..16 {
-        for y in 0..16 {
-            for x in 0..16 {
-                let b = chunk.get_block_at(x as u32, y as u32 + y_slice * 16, z as u32);
-                if does_not_render(b) {continue;}
-                add_voxel_to_axis_cols(has_partial_transparency(b), is_fluid(b), x + 1, y + 1, z + 1, &mut axis_columns, &stage);
-            }
-        }
+type PlaneKey = (u32, (FaceTexture, FaceTexture, FaceTexture), u32);
+
+pub fn binary_mesh(input: &MeshInput, stage: MeshStageType) -> (Vec<SurfaceVertex>, Vec<u32>, u32, Vec<Quad>) {
+    //if it is entirely air, return early
+    if input.fullair {
+        return (vec![], vec![], 0, vec![]);
     }
 
-    for z in [0, 18 - 1] {
-        for y in 0..18 {
-            for x in 0..18 {
-                let pos = Vector3::new(x as i32 - 1, y as i32 - 1, z as i32 - 1);
-                let block = get_block_at_absolute(pos.x + chunk_x * 16, pos.y + y_slice as i32 * 16, pos.z + chunk_z * 16, chunks);
-                
-                let hastrans = match &block {
-                    Some(b) => has_partial_transparency(b),
-                    None => false
-                };
-
-                let isfluid = match &block {
-                    Some(b) => is_fluid(b),
-                    None => false
-                };
-
-                if block.is_none() || does_not_render(block.unwrap()) {continue;}
-
-                add_voxel_to_axis_cols(hastrans, isfluid, x, y, z, &mut axis_columns, &stage);
-            }
-        }
+    let origin = Vector3::from(input.origin());
+
+    let padded_at = |x: usize, y: usize, z: usize| -> Option<&WorldBlock> {
+        input.get_padded(x, y, z)
+    };
+
+    //one bit per voxel along the column. [0] is the y axis (z, x), [1] is the x axis (y, z), [2] is the z axis (y, x)
+    let mut stage_columns = [[[0u32; 18]; 18]; 3];
+    let mut occluder_columns = [[[0u32; 18]; 18]; 3];
+
+    #[inline]
+    fn add_voxel_to_axis_cols(x: usize, y: usize, z: usize, axis_cols: &mut [[[u32; 18]; 18]; 3]) {
+        // x,z - y axis
+        axis_cols[0][z][x] |= 1u32 << y as u32;
+        // z,y - x axis
+        axis_cols[1][y][z] |= 1u32 << x as u32;
+        // x,y - z axis
+        axis_cols[2][y][x] |= 1u32 << z as u32;
     }
+
     for z in 0..18 {
-        for y in [0, 18 - 1] {
+        for y in 0..18 {
             for x in 0..18 {
-                let pos = Vector3::new(x as i32 - 1, y as i32 - 1, z as i32 - 1);
-                let block = get_block_at_absolute(pos.x + chunk_x * 16, pos.y + y_slice as i32 * 16, pos.z + chunk_z * 16, chunks);
-                
-                let hastrans = match &block {
-                    Some(b) => has_partial_transparency(block.unwrap()),
-                    None => false
-                };
-
-                let isfluid = match &block {
-                    Some(b) => is_fluid(block.unwrap()),
-                    None => false
-                };
-
-                if block.is_none() || does_not_render(block.unwrap()) {continue;}
-
-                add_voxel_to_axis_cols(hastrans, isfluid, x, y, z, &mut axis_columns, &stage);
-            }
-        }
-    }
-    for z in 0..18 {
-        for x in [0, 18 - 1] {
-            for y in 0..18 {
-                let pos = Vector3::new(x as i32 - 1, y as i32 - 1, z as i32 - 1);
-                let block = get_block_at_absolute(pos.x + chunk_x * 16, pos.y + y_slice as i32 * 16, pos.z + chunk_z * 16, chunks);
-                
-                let hastrans = match &block {
-                    Some(b) => has_partial_transparency(b),
-                    None => false
-                };
-
-                let isfluid = match &block {
-                    Some(b) => is_fluid(b),
-                    None => false
-                };
-
-                if block.is_none() || does_not_render(block.unwrap()) {continue;}
-
-                add_voxel_to_axis_cols(hastrans, isfluid, x, y, z, &mut axis_columns, &stage);
+                let block = padded_at(x, y, z);
+
+                if block.is_some_and(|b| stage.contains(b)) {
+                    add_voxel_to_axis_cols(x, y, z, &mut stage_columns);
+                }
+                if stage.is_occluded_by(block) {
+                    add_voxel_to_axis_cols(x, y, z, &mut occluder_columns);
+                }
             }
         }
     }
 
-    for axis in 0..3 {
-        for z in 0..18 {
-            for x in 0..18 {
-                // set if current is solid, and next is air
-                let col = axis_columns[axis][z][x];
+    let mut column_face_masks = [[[0u32; 18]; 18]; 6];
 
-                // sample descending axis, and set true when air meets solid
-                column_face_masks[2 * axis + 0][z][x] = col & !(col << 1);
-                // sample ascending axis, and set true when air meets solid
-                column_face_masks[2 * axis + 1][z][x] = col & !(col >> 1);
+    for axis in 0..3 {
+        for c1 in 0..18 {
+            for c0 in 0..18 {
+                let col = stage_columns[axis][c1][c0];
+                let occ = occluder_columns[axis][c1][c0];
+
+                // sample descending axis, set when the voxel before this one doesn't hide the face
+                column_face_masks[2 * axis + 0][c1][c0] = col & !(occ << 1);
+                // sample ascending axis, set when the voxel after this one doesn't hide the face
+                column_face_masks[2 * axis + 1][c1][c0] = col & !(occ >> 1);
             }
         }
     }
 
-    let mut data: [HashMap<u64, HashMap<u32, ([u32; 16], Option<&WorldBlock>, Vector3<i32>)>>; 6];
-    data = [
-        HashMap::new(),
-        HashMap::new(),
-        HashMap::new(),
-        HashMap::new(),
-        HashMap::new(),
-        HashMap::new(),
-    ];
-
-    // find faces and build binary planes based on the voxel block+ao etc...
-    for axis in 0..6 {
-        let facedir = match axis {
-            0 => BlockFace::Bottom,
-            1 => BlockFace::Top,
-            2 => BlockFace::Left,
-            3 => BlockFace::Right,
-            4 => BlockFace::Front,
-            _ => BlockFace::Back,
-        };
-        for z in 0..16 {
-            for x in 0..16 {
-                // skip padded by adding 1(for x padding) and (z+1) for (z padding)
-                let mut col = column_face_masks[axis][z + 1][x + 1];
-
-                col >>= 1;
-                col &= !(1 << 16 as u32);
+    //binary planes grouped by face depth, textures and illumination so merged quads never mix them
+    let mut data: [HashMap<PlaneKey, [u32; 16]>; 6] = Default::default();
+
+    for (mask_index, facedir) in MASK_FACES.iter().enumerate() {
+        let normal = facedir.normal();
+
+        for c1 in 0..16 {
+            for c0 in 0..16 {
+                // skip padding, both for the column position and the first/last bit of the column
+                let mut col = (column_face_masks[mask_index][c1 + 1][c0 + 1] >> 1) & 0xFFFF;
 
                 while col != 0 {
-                    let y = col.trailing_zeros();
+                    let depth = col.trailing_zeros();
                     col &= col - 1;
 
-                    let voxel_pos = match axis {
-                        0 | 1 => Vector3::new(x as i32, y as i32, z as i32),
-                        2 | 3 => Vector3::new(y as i32, z as i32, x as i32),
-                        _ => Vector3::new(x as i32, z as i32, y as i32),
+                    let voxel_pos = match mask_index {
+                        0 | 1 => Vector3::new(c0, depth as usize, c1),
+                        2 | 3 => Vector3::new(depth as usize, c1, c0),
+                        _ => Vector3::new(c0, c1, depth as usize),
                     };
 
-                    let current_voxel = chunk.get_block_at(voxel_pos.x as u32, voxel_pos.y as u32 + y_slice * 16, voxel_pos.z as u32);
+                    let current_voxel = padded_at(voxel_pos.x + 1, voxel_pos.y + 1, voxel_pos.z + 1).unwrap();
 
-                    let abs_pos = Vector3::new(chunk.position.x * 16 + voxel_pos.x, voxel_pos.y + y_slice as i32 * 16, chunk.position.y * 16 + voxel_pos.z);
-                    
-                    let nextdoorpos = abs_pos + facedir.normal();
+                    let illumination = padded_at(
+                        (voxel_pos.x as i32 + 1 + normal.x) as usize,
+                        (voxel_pos.y as i32 + 1 + normal.y) as usize,
+                        (voxel_pos.z as i32 + 1 + normal.z) as usize
+                    ).map_or(0, calculate_illumination_bytes);
 
-                    let illumination = get_block_at_absolute(nextdoorpos.x, nextdoorpos.y, nextdoorpos.z, chunks).map_or(0, |v| calculate_illumination_bytes(&v));
+                    let tex = get_surface_texture_indices(current_voxel, *facedir);
 
-                    let block_hash = illumination as u64 | (get_block_id(current_voxel) << 32);
-                    let data = data[axis]
-                        .entry(block_hash)
-                        .or_default()
-                        .entry(y)
-                        .or_default();
-                    data.0[x as usize] |= 1u32 << z as u32;
-                    data.1 = Some(&current_voxel);
-                    data.2 = abs_pos;
+                    let plane = data[mask_index]
+                        .entry((depth, tex, illumination))
+                        .or_insert([0u32; 16]);
+
+                    plane[c0] |= 1u32 << c1 as u32;
                 }
             }
         }
@@ -219,22 +166,12 @@ pub fn binary_mesh(chunk_x: i32, chunk_z: i32, y_slice: u32, chunks: &HashMap<u3
     let mut vertices = vec![];
     let mut quads: Vec<Quad> = vec![];
 
-    for (axis, blockdata) in data.into_iter().enumerate() {
-        let facedir = match axis {
-            0 => BlockFace::Bottom,
-            1 => BlockFace::Top,
-            2 => BlockFace::Left,
-            3 => BlockFace::Right,
-            4 => BlockFace::Front,
-            _ => BlockFace::Back,
-        };
-        for (_, axis_plane) in blockdata.into_iter() {
-            for (axis_pos, plane) in axis_plane.into_iter() {
-                let quads_from_axis = greedy_mesh_binary_plane(plane.0);
-
-                quads_from_axis.into_iter().for_each(|q| {
-                    q.append_vertices(&mut vertices, facedir, axis_pos, plane.1.unwrap(), chunks, &mut quads, plane.2);
-                });
+    for (mask_index, planes) in data.into_iter().enumerate() {
+        let facedir = MASK_FACES[mask_index];
+
+        for ((depth, tex, illumination), plane) in planes.into_iter() {
+            for q in greedy_mesh_binary_plane(plane) {
+                q.append_vertices(&mut vertices, &mut quads, facedir, depth, tex, illumination, origin);
             }
         }
     }
@@ -242,7 +179,6 @@ pub fn binary_mesh(chunk_x: i32, chunk_z: i32, y_slice: u32, chunks: &HashMap<u3
     let indices = generate_indices(vertices.len());
     let ilen = indices.len();
 
-
     (vertices, indices, ilen as u32, quads)
 }
 
@@ -257,36 +193,26 @@ impl GreedyQuad {
     pub fn append_vertices(
         &self,
         vertices: &mut Vec<SurfaceVertex>,
+        quads: &mut Vec<Quad>,
         face_dir: BlockFace,
         axis: u32,
-        block: &WorldBlock,
-        chunks: &HashMap<u32, Arc<Chunk>>,
-        quads: &mut Vec<Quad>,
-        abs_pos: Vector3<i32>
+        tex: (FaceTexture, FaceTexture, FaceTexture),
+        illumination: u32,
+        origin: Vector3<i32>
     ) {
         let axis = axis as i32;
 
-        let nextdoorpos = abs_pos + face_dir.normal();
-
-        let illumination = get_block_at_absolute(nextdoorpos.x, nextdoorpos.y, nextdoorpos.z, chunks).map_or(0, |v| calculate_illumination_bytes(&v));
-
-        let tex = get_surface_texture_indices(block, face_dir);
-
-        let v1 = SurfaceVertex::from_position(
-            face_dir.world_to_sample(axis as i32, self.x as i32, self.y as i32), face_dir, 0, tex, illumination
-        );
-        let v2 = SurfaceVertex::from_position(
-            face_dir.world_to_sample(axis as i32, self.x as i32 + self.w as i32, self.y as i32), face_dir, 1, tex, illumination
-        );
-        let v3 = SurfaceVertex::from_position(
-            face_dir.world_to_sample(axis as i32, self.x as i32 + self.w as i32, self.y as i32 + self.h as i32), face_dir, 2, tex, illumination
-        );
-        let v4 = SurfaceVertex::from_position(
-            face_dir.world_to_sample(axis as i32, self.x as i32, self.y as i32 + self.h as i32), face_dir, 3, tex, illumination
-        );
+        let corners = [
+            face_dir.world_to_sample(axis, self.x as i32, self.y as i32),
+            face_dir.world_to_sample(axis, self.x as i32 + self.w as i32, self.y as i32),
+            face_dir.world_to_sample(axis, self.x as i32 + self.w as i32, self.y as i32 + self.h as i32),
+            face_dir.world_to_sample(axis, self.x as i32, self.y as i32 + self.h as i32),
+        ];
 
         // the quad vertices to be added
-        let mut new_vertices = VecDeque::from([v1, v2, v3, v4]);
+        let mut new_vertices = corners.iter().enumerate().map(|(nth, pos)| {
+            SurfaceVertex::from_position(*pos, face_dir, nth as u32, tex, illumination)
+        }).collect::<VecDeque<_>>();
 
         // triangle vertex order is different depending on the facing direction
         // due to indices always being the same
@@ -296,14 +222,15 @@ impl GreedyQuad {
             o.into_iter().rev().for_each(|i| new_vertices.push_back(i));
         }
 
-        //todo: actually calculate the center. Untodo: will remove this.
-        let center = abs_pos.map(|v| v as f32);
+        let center = corners.iter()
+            .fold(Vector3::zeros(), |acc, c| acc + Vector3::new(c[0] as f32, c[1] as f32, c[2] as f32)) / 4.0
+            + origin.map(|v| v as f32);
 
         quads.push(Quad {
             center,
-            vertices: [v1, v2, v3, v4]
+            vertices: [new_vertices[0], new_vertices[1], new_vertices[2], new_vertices[3]]
         });
-        
+
         vertices.extend(new_vertices);
     }
 }
@@ -348,4 +275,172 @@ pub fn greedy_mesh_binary_plane(mut data: [u32; 16]) -> Vec<GreedyQuad> {
         }
     }
     greedy_quads
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use nalgebra::{Vector2, Vector3};
+    use shared::world::{blockrepr::WorldBlock, chunk::{local_xyz_to_index, xz_to_index, Chunk}};
+
+    use crate::{renderer::surfacevertex::SurfaceVertex, world::{fastmesher::fast_mesh, meshinput::MeshInput}};
+
+    use super::{binary_mesh, MeshStageType};
+
+    //a few layers of ground plus some scattered pillars and holes so that faces land on every axis and slice border
+    fn test_world() -> HashMap<u32, Arc<Chunk>> {
+        let mut chunks = HashMap::new();
+        for cx in -1..=1 {
+            for cz in -1..=1 {
+                let grid = (0..16).map(|y_slice| {
+                    let mut slice = vec![WorldBlock::Air(0); 4096];
+                    for x in 0..16 {
+                        for z in 0..16 {
+                            for y in 0..16 {
+                                let (ax, ay, az) = (cx * 16 + x as i32, y_slice * 16 + y as i32, cz * 16 + z as i32);
+                                let hash = ax.wrapping_mul(73856093) ^ ay.wrapping_mul(19349663) ^ az.wrapping_mul(83492791);
+                                let block = if ay < 14 && hash.rem_euclid(7) == 0 {
+                                    WorldBlock::Air(15)
+                                } else if ay < 12 {
+                                    WorldBlock::Stone((ay % 16) as u8)
+                                } else if ay < 16 {
+                                    WorldBlock::Dirt(3)
+                                } else if ay < 20 && hash.rem_euclid(5) == 0 {
+                                    WorldBlock::Grass(0)
+                                } else if ay < 40 && hash.rem_euclid(31) == 0 {
+                                    WorldBlock::Sand(0)
+                                } else {
+                                    WorldBlock::Air(15)
+                                };
+                                slice[local_xyz_to_index(x, y, z) as usize] = block;
+                            }
+                        }
+                    }
+                    slice
+                }).collect();
+                chunks.insert(xz_to_index(cx, cz), Arc::new(Chunk::from_blocks(Vector2::new(cx, cz), grid)));
+            }
+        }
+        chunks
+    }
+
+    fn face_normal(face: u32) -> Vector3<i32> {
+        match face {
+            0 => Vector3::new(0, 1, 0),
+            1 => Vector3::new(0, -1, 0),
+            2 => Vector3::new(1, 0, 0),
+            3 => Vector3::new(-1, 0, 0),
+            4 => Vector3::new(0, 0, 1),
+            _ => Vector3::new(0, 0, -1),
+        }
+    }
+
+    fn unpack(v: &SurfaceVertex) -> (Vector3<i32>, u32) {
+        let pos = Vector3::new((v.d0 & 31) as i32, ((v.d0 >> 5) & 31) as i32, ((v.d0 >> 10) & 31) as i32);
+        (pos, (v.d0 >> 15) & 7)
+    }
+
+    //splits every quad into the unit faces it covers: (face, min corner, textures, illumination)
+    fn unit_faces(vertices: &[SurfaceVertex], indices: &[u32]) -> Vec<(u32, [i32; 3], u32, u32)> {
+        let mut out = Vec::new();
+        for (quad, tris) in vertices.chunks(4).zip(indices.chunks(6)) {
+            let (p0, face) = unpack(&vertices[tris[0] as usize]);
+            let (p1, _) = unpack(&vertices[tris[1] as usize]);
+            let (p2, _) = unpack(&vertices[tris[2] as usize]);
+            let normal = face_normal(face);
+
+            //triangles have to wind counter clockwise when seen from outside
+            let winding = (p1 - p0).cross(&(p2 - p0));
+            assert!(winding.dot(&normal) > 0, "quad facing {:?} winds the wrong way", normal);
+
+            let positions = quad.iter().map(|v| unpack(v).0).collect::<Vec<_>>();
+            let min = positions.iter().fold(Vector3::repeat(i32::MAX), |a, p| a.inf(p));
+            let max = positions.iter().fold(Vector3::repeat(i32::MIN), |a, p| a.sup(p));
+
+            for x in min.x..max.x.max(min.x + 1) {
+                for y in min.y..max.y.max(min.y + 1) {
+                    for z in min.z..max.z.max(min.z + 1) {
+                        out.push((face, [x, y, z], quad[0].d1, quad[0].illumination));
+                    }
+                }
+            }
+        }
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn binary_mesh_covers_same_faces_as_fast_mesh() {
+        let chunks = test_world();
+        for y_slice in 0..3 {
+            let input = MeshInput::from_chunk_map(0, 0, y_slice, &chunks).unwrap();
+            let (fast_vertices, fast_indices, _) = fast_mesh(&input, MeshStageType::Solid);
+            let (binary_vertices, binary_indices, ilen, quads) = binary_mesh(&input, MeshStageType::Solid);
+
+            assert_eq!(ilen as usize, binary_indices.len());
+            assert_eq!(quads.len() * 4, binary_vertices.len());
+            assert!(binary_vertices.len() <= fast_vertices.len());
+
+            let fast_faces = unit_faces(&fast_vertices, &fast_indices);
+            let binary_faces = unit_faces(&binary_vertices, &binary_indices);
+
+            assert!(!fast_faces.is_empty() || y_slice == 2);
+            assert_eq!(fast_faces, binary_faces, "faces differ in slice {}", y_slice);
+        }
+    }
+
+    #[test]
+    fn binary_mesh_merges_flat_ground() {
+        let grid = (0..16).map(|y_slice| {
+            let block = if y_slice == 0 {WorldBlock::Stone(0)} else {WorldBlock::Air(15)};
+            vec![block; 4096]
+        }).collect();
+        let chunks = HashMap::from([(xz_to_index(0, 0), Arc::new(Chunk::from_blocks(Vector2::new(0, 0), grid)))]);
+
+        let (_, _, _, quads) = binary_mesh(&MeshInput::from_chunk_map(0, 0, 0, &chunks).unwrap(), MeshStageType::Solid);
+
+        //only the top is exposed, the sides and bottom border unloaded chunks or the bottom of the world
+        assert_eq!(quads.len(), 1);
+        assert_eq!(quads[0].center, Vector3::new(8.0, 16.0, 8.0));
+
+        let (_, _, ilen, _) = binary_mesh(&MeshInput::from_chunk_map(0, 0, 1, &chunks).unwrap(), MeshStageType::Solid);
+        assert_eq!(ilen, 0);
+    }
+
+    //one slice of air with the given blocks in it
+    fn slice_with(blocks: &[((u32, u32, u32), WorldBlock)]) -> HashMap<u32, Arc<Chunk>> {
+        let mut slice = vec![WorldBlock::Air(15); 4096];
+        for &((x, y, z), block) in blocks {
+            slice[local_xyz_to_index(x, y, z) as usize] = block;
+        }
+        let grid = std::iter::once(slice).chain((1..16).map(|_| vec![WorldBlock::Air(15); 4096])).collect();
+        HashMap::from([(xz_to_index(0, 0), Arc::new(Chunk::from_blocks(Vector2::new(0, 0), grid)))])
+    }
+
+    #[test]
+    fn cutout_faces_are_only_hidden_by_opaque_blocks() {
+        assert!(!MeshStageType::Cutout.is_occluded_by(Some(&WorldBlock::Leaf(0))));
+        assert!(!MeshStageType::Cutout.is_occluded_by(Some(&WorldBlock::ArcaneLeaf(0))));
+        assert!(MeshStageType::Cutout.is_occluded_by(Some(&WorldBlock::Stone(0))));
+        //and leaves don't hide the solid blocks behind them
+        assert!(!MeshStageType::Solid.is_occluded_by(Some(&WorldBlock::Leaf(0))));
+
+        //leaf next to leaf keeps both faces between them, 6 each
+        let chunks = slice_with(&[((4, 4, 4), WorldBlock::Leaf(0)), ((5, 4, 4), WorldBlock::Leaf(0))]);
+        let input = MeshInput::from_chunk_map(0, 0, 0, &chunks).unwrap();
+        let (vertices, indices, _, _) = binary_mesh(&input, MeshStageType::Cutout);
+        let faces = unit_faces(&vertices, &indices);
+        assert_eq!(faces.len(), 12);
+        assert!(faces.iter().any(|(face, min, _, _)| *face == 2 && *min == [5, 4, 4]));
+        assert!(faces.iter().any(|(face, min, _, _)| *face == 3 && *min == [5, 4, 4]));
+
+        //leaf next to stone loses the face against it, the stone isn't in the cutout stage
+        let chunks = slice_with(&[((4, 4, 4), WorldBlock::Leaf(0)), ((5, 4, 4), WorldBlock::Stone(0))]);
+        let input = MeshInput::from_chunk_map(0, 0, 0, &chunks).unwrap();
+        let (vertices, indices, _, _) = binary_mesh(&input, MeshStageType::Cutout);
+        let faces = unit_faces(&vertices, &indices);
+        assert_eq!(faces.len(), 5);
+        assert!(!faces.iter().any(|(face, _, _, _)| *face == 2));
+    }
+}
@@ -5,6 +5,8 @@ use wgpu::util::DeviceExt;
 
 use crate::renderer::vertex::Vertex;
 
+use super::{depthsort::Quad, meshthread::MeshedSlice, occlusion::SliceConnectivity};
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct ChunkDataVertex {
@@ -29,7 +31,17 @@ impl Vertex for ChunkDataVertex {
 pub struct ChunkDraw {
     pub chunk: Arc<Chunk>,
     pub solid_buffers: Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>>,
+    pub cutout_buffers: Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>>,
     pub transparent_buffers: Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>>,
+    pub fluid_buffers: Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>>,
+    pub model_buffers: Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>>,
+    //quads of the transparent buffers in vertex order, kept around for depth sorting
+    pub transparent_quads: Vec<Vec<Quad>>,
+    pub transparent_needs_sort: Vec<bool>,
+    //slices whose blocks changed since they were last queued for meshing
+    pub dirty: Vec<bool>,
+    //which faces of each slice see each other, open until the slice is meshed
+    pub connectivity: Vec<SliceConnectivity>,
     pub slice_vertex_buffers: Vec<wgpu::Buffer>,
     pub states: Vec<ChunkState>
 }
@@ -39,11 +51,29 @@ impl ChunkDraw {
         Self {
             chunk,
             solid_buffers: iter::repeat_with(|| None).take(16).collect(),
+            cutout_buffers: iter::repeat_with(|| None).take(16).collect(),
             transparent_buffers: iter::repeat_with(|| None).take(16).collect(),
+            fluid_buffers: iter::repeat_with(|| None).take(16).collect(),
+            model_buffers: iter::repeat_with(|| None).take(16).collect(),
+            transparent_quads: iter::repeat_with(Vec::new).take(16).collect(),
+            transparent_needs_sort: iter::repeat(false).take(16).collect(),
+            dirty: iter::repeat(false).take(16).collect(),
+            connectivity: iter::repeat(SliceConnectivity::OPEN).take(16).collect(),
             slice_vertex_buffers: Vec::new(),
             states: iter::repeat(ChunkState::PreMesh).take(16).collect()
         }
     }
+    //swaps every buffer of the slice in one go, so a remeshed slice never shows half old and half new geometry
+    pub fn set_meshed_slice(&mut self, slice: u32, meshed: MeshedSlice) {
+        self.set_solid_buffer(slice, meshed.solid);
+        self.set_cutout_buffer(slice, meshed.cutout);
+        self.set_transparent_buffer(slice, meshed.transparent);
+        self.set_transparent_quads(slice, meshed.transparent_quads);
+        self.set_fluid_buffer(slice, meshed.fluid);
+        self.set_model_buffer(slice, meshed.model);
+        self.connectivity[slice as usize] = meshed.connectivity;
+        self.states[slice as usize] = ChunkState::Ready;
+    }
     pub fn set_slice_vertex_buffers(&mut self, device: &Arc<wgpu::Device>) {
         let slice_vertex_buffers = (0..16).map(|y| {
             device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -68,9 +98,19 @@ impl ChunkDraw {
     pub fn get_solid_buffers(&self) -> &Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>> {
         &self.solid_buffers
     }
+    pub fn set_cutout_buffer(&mut self, slice: u32, buffers: (wgpu::Buffer, wgpu::Buffer, u32)) {
+        self.cutout_buffers[slice as usize] = Some(buffers);
+    }
+    pub fn get_cutout_buffers(&self) -> &Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>> {
+        &self.cutout_buffers
+    }
     pub fn set_transparent_buffer(&mut self, slice: u32, buffers: (wgpu::Buffer, wgpu::Buffer, u32)) {
         self.transparent_buffers[slice as usize] = Some(buffers);
     }
+    pub fn set_transparent_quads(&mut self, slice: u32, quads: Vec<Quad>) {
+        self.transparent_needs_sort[slice as usize] = !quads.is_empty();
+        self.transparent_quads[slice as usize] = quads;
+    }
     pub fn set_transparent_buffers(&mut self, buffers: Vec<Option< (wgpu::Buffer, wgpu::Buffer, u32)>>) {
         self.transparent_buffers = buffers;
     }
@@ -80,4 +120,16 @@ impl ChunkDraw {
     pub fn get_transparent_buffers(&self) -> &Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>> {
         &self.transparent_buffers
     }
+    pub fn set_fluid_buffer(&mut self, slice: u32, buffers: (wgpu::Buffer, wgpu::Buffer, u32)) {
+        self.fluid_buffers[slice as usize] = Some(buffers);
+    }
+    pub fn get_fluid_buffers(&self) -> &Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>> {
+        &self.fluid_buffers
+    }
+    pub fn set_model_buffer(&mut self, slice: u32, buffers: (wgpu::Buffer, wgpu::Buffer, u32)) {
+        self.model_buffers[slice as usize] = Some(buffers);
+    }
+    pub fn get_model_buffers(&self) -> &Vec<Option<(wgpu::Buffer, wgpu::Buffer, u32)>> {
+        &self.model_buffers
+    }
 }
\ No newline at end of file
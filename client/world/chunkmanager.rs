@@ -1,17 +1,267 @@
 use std::{collections::HashMap, sync::Arc};
 
-use shared::world::chunk::{xz_to_index, Chunk};
+use nalgebra::{Point3, Vector2, Vector3};
+use shared::world::{biomemap::Biome, blockrepr::WorldBlock, chunk::{xz_to_index, Chunk, ChunkState}, raycast::{raycast, RaycastHit, REACH}};
 
-use super::chunkdraw::ChunkDraw;
+use super::{chunkdraw::ChunkDraw, meshinput::MeshInput, meshscheduler::{MeshScheduler, SliceKey}};
+
+//chunks loaded around the player in each direction, the fog ends at the edge of it
+pub const DEFAULT_RENDER_DISTANCE: u32 = 8;
 
 pub struct ChunkManager {
-    pub chunks: HashMap<u32, ChunkDraw>
+    pub chunks: HashMap<u32, ChunkDraw>,
+    pub render_distance: u32
 }
 
 impl ChunkManager {
     pub fn new() -> Self {
         Self {
-            chunks: HashMap::new()
+            chunks: HashMap::new(),
+            render_distance: DEFAULT_RENDER_DISTANCE
+        }
+    }
+
+    //None if the column's chunk isn't loaded
+    pub fn biome_at(&self, x: i32, z: i32) -> Option<Biome> {
+        self.chunks.get(&xz_to_index(x.div_euclid(16), z.div_euclid(16)))
+            .map(|chunkdraw| chunkdraw.chunk.get_biome_at(x.rem_euclid(16) as u32, z.rem_euclid(16) as u32))
+    }
+
+    //None outside the world or in chunks that aren't loaded
+    pub fn get_block(&self, position: Vector3<i32>) -> Option<WorldBlock> {
+        if !(0..=255).contains(&position.y) {return None};
+        self.chunks.get(&xz_to_index(position.x.div_euclid(16), position.z.div_euclid(16)))
+            .map(|chunkdraw| *chunkdraw.chunk.get_block_at(position.x.rem_euclid(16) as u32, position.y as u32, position.z.rem_euclid(16) as u32))
+    }
+
+    //the block within reach the eye is looking at, the server repeats this before accepting a change
+    pub fn raycast(&self, eye: Point3<f32>, direction: Vector3<f32>) -> Option<RaycastHit> {
+        raycast(eye, direction, REACH, |position| self.get_block(position))
+    }
+
+    pub fn mesh_input(&self, chunk_x: i32, chunk_z: i32, y_slice: u32) -> Option<MeshInput> {
+        MeshInput::new(chunk_x, chunk_z, y_slice, |x, z| self.chunks.get(&xz_to_index(x, z)).map(|c| c.chunk.as_ref()))
+    }
+
+    //the chunk is copied on write if a mesh job still holds the old one. Returns false if the chunk isn't loaded
+    pub fn set_block(&mut self, position: Vector3<i32>, block: WorldBlock) -> bool {
+        if !(0..=255).contains(&position.y) {return false};
+        let Some(chunkdraw) = self.chunks.get_mut(&xz_to_index(position.x.div_euclid(16), position.z.div_euclid(16))) else {return false};
+
+        Arc::make_mut(&mut chunkdraw.chunk).set_block_at(position.x.rem_euclid(16) as u32, position.y as u32, position.z.rem_euclid(16) as u32, block);
+        self.mark_dirty_around(position);
+        true
+    }
+
+    //a batch of (local x, y, local z, block) changes in one chunk, copied on write once. Returns false if the chunk isn't loaded
+    pub fn set_blocks(&mut self, chunk_position: Vector2<i32>, changes: &[(u8, u8, u8, WorldBlock)]) -> bool {
+        let Some(chunkdraw) = self.chunks.get_mut(&xz_to_index(chunk_position.x, chunk_position.y)) else {return false};
+
+        let chunk = Arc::make_mut(&mut chunkdraw.chunk);
+        for &(x, y, z, block) in changes {
+            chunk.set_block_at(x as u32, y as u32, z as u32, block);
+        }
+        for &(x, y, z, _) in changes {
+            self.mark_dirty_around(Vector3::new(chunk_position.x * 16 + x as i32, y as i32, chunk_position.y * 16 + z as i32));
         }
+        true
     }
-}
\ No newline at end of file
+
+    //the meshers look one block past the slice (and diagonally for fluids), so every slice touching the 3x3x3 around the block is affected
+    pub fn mark_dirty_around(&mut self, position: Vector3<i32>) {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let (x, y, z) = (position.x + dx, position.y + dy, position.z + dz);
+                    if !(0..=255).contains(&y) {continue};
+
+                    let Some(chunkdraw) = self.chunks.get_mut(&xz_to_index(x.div_euclid(16), z.div_euclid(16))) else {continue};
+                    let y_slice = (y / 16) as usize;
+
+                    //slices that were never queued will be meshed from the current blocks anyway
+                    if chunkdraw.states[y_slice] != ChunkState::PreMesh {
+                        chunkdraw.dirty[y_slice] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    //requests a remesh for every dirty slice. A request for a slice that's already queued or in flight replaces it
+    pub fn queue_dirty_slices(&mut self, scheduler: &mut MeshScheduler) {
+        for chunkdraw in self.chunks.values_mut() {
+            for y in 0..16 {
+                if !chunkdraw.dirty[y] {continue};
+
+                chunkdraw.dirty[y] = false;
+                if chunkdraw.states[y] == ChunkState::Ready {
+                    chunkdraw.states[y] = ChunkState::Stale;
+                }
+                scheduler.request((chunkdraw.chunk.position.x, chunkdraw.chunk.position.y, y as u32));
+            }
+        }
+    }
+
+    //a chunk from the server is meshed right away, and the neighbour slices whose edges were meshed without it
+    pub fn insert_chunk(&mut self, chunk: Arc<Chunk>, device: &Arc<wgpu::Device>, scheduler: &mut MeshScheduler) {
+        let position = chunk.position;
+        let mut chunkdraw = ChunkDraw::new(chunk);
+        chunkdraw.set_slice_vertex_buffers(device);
+        self.chunks.insert(xz_to_index(position.x, position.y), chunkdraw);
+
+        self.queue_chunk(position.x, position.y, scheduler);
+        self.mark_neighbours_dirty(position);
+    }
+
+    //drops the chunk's buffers and any meshing still waiting for it
+    pub fn unload_chunk(&mut self, position: Vector2<i32>, scheduler: &mut MeshScheduler) {
+        if self.chunks.remove(&xz_to_index(position.x, position.y)).is_none() {return};
+
+        scheduler.cancel_chunk(position.x, position.y);
+        self.mark_neighbours_dirty(position);
+    }
+
+    //fluids look at diagonal neighbours too, so all 8 around. The meshers only see one block into the chunk, so a
+    //neighbour slice whose blocks along that border are all air meshes the same either way and is left alone
+    fn mark_neighbours_dirty(&mut self, position: Vector2<i32>) {
+        for dx in -1i32..=1 {
+            for dz in -1i32..=1 {
+                if dx == 0 && dz == 0 {continue};
+                let Some(chunkdraw) = self.chunks.get_mut(&xz_to_index(position.x + dx, position.y + dz)) else {continue};
+
+                //the neighbour's columns along the side or corner it shares with the chunk
+                let border = |d: i32| match d {
+                    -1 => 15..16,
+                    1 => 0..1,
+                    _ => 0..16
+                };
+                let (xs, zs) = (border(dx), border(dz));
+
+                for y in 0..16 {
+                    if chunkdraw.states[y] == ChunkState::PreMesh || chunkdraw.chunk.fullair[y] {continue};
+
+                    let faces_chunk = xs.clone().any(|x| zs.clone().any(|z| (0..16).any(|ly| {
+                        !matches!(chunkdraw.chunk.get_block_at(x, (y * 16 + ly) as u32, z), WorldBlock::Air(_))
+                    })));
+                    if faces_chunk {
+                        chunkdraw.dirty[y] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    //a queued slice the scheduler couldn't mesh. It goes back to how it was before it was queued, so the next
+    //change or neighbour queues it again instead of it waiting for a mesh that never comes
+    pub fn unqueue_slice(&mut self, key: SliceKey) {
+        let Some(chunkdraw) = self.chunks.get_mut(&xz_to_index(key.0, key.1)) else {return};
+        let y = key.2 as usize;
+        chunkdraw.states[y] = match chunkdraw.states[y] {
+            ChunkState::Mesh => ChunkState::PreMesh,
+            ChunkState::Stale => ChunkState::Ready,
+            state => state
+        };
+    }
+
+    //first mesh of every slice of the chunk
+    pub fn queue_chunk(&mut self, chunk_x: i32, chunk_z: i32, scheduler: &mut MeshScheduler) {
+        let Some(chunkdraw) = self.chunks.get_mut(&xz_to_index(chunk_x, chunk_z)) else {return};
+        for y in 0..16 {
+            chunkdraw.states[y] = ChunkState::Mesh;
+            scheduler.request((chunk_x, chunk_z, y as u32));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use nalgebra::{Vector2, Vector3};
+    use shared::world::{blockrepr::WorldBlock, chunk::{xz_to_index, Chunk, ChunkState}};
+
+    use crate::world::chunkdraw::ChunkDraw;
+
+    use super::ChunkManager;
+
+    //3x3 chunks of air around the origin, every slice meshed
+    fn meshed_world() -> ChunkManager {
+        let mut chunk_manager = ChunkManager::new();
+        for cx in -1..=1 {
+            for cz in -1..=1 {
+                let grid = (0..16).map(|_| vec![WorldBlock::Air(15); 4096]).collect();
+                let mut chunkdraw = ChunkDraw::new(Arc::new(Chunk::from_blocks(Vector2::new(cx, cz), grid)));
+                chunkdraw.states = vec![ChunkState::Ready; 16];
+                chunk_manager.chunks.insert(xz_to_index(cx, cz), chunkdraw);
+            }
+        }
+        chunk_manager
+    }
+
+    //chunk x, chunk z, y slice of every dirty slice, sorted
+    fn dirty_slices(chunk_manager: &ChunkManager) -> Vec<(i32, i32, usize)> {
+        let mut dirty = chunk_manager.chunks.values()
+            .flat_map(|chunkdraw| (0..16).filter(|y| chunkdraw.dirty[*y]).map(|y| (chunkdraw.chunk.position.x, chunkdraw.chunk.position.y, y)))
+            .collect::<Vec<_>>();
+        dirty.sort();
+        dirty
+    }
+
+    #[test]
+    fn changes_inside_a_slice_only_dirty_it() {
+        let mut chunk_manager = meshed_world();
+        chunk_manager.set_block(Vector3::new(8, 40, 8), WorldBlock::Stone(0));
+        assert_eq!(dirty_slices(&chunk_manager), vec![(0, 0, 2)]);
+    }
+
+    #[test]
+    fn changes_on_borders_dirty_the_neighbours() {
+        //the +x and -z chunk border, and the bottom of slice 1
+        let mut chunk_manager = meshed_world();
+        chunk_manager.set_block(Vector3::new(15, 16, 0), WorldBlock::Stone(0));
+        assert_eq!(dirty_slices(&chunk_manager), vec![
+            (0, -1, 0), (0, -1, 1),
+            (0, 0, 0), (0, 0, 1),
+            (1, -1, 0), (1, -1, 1),
+            (1, 0, 0), (1, 0, 1)
+        ]);
+
+        //negative coordinates are in the chunk below zero, the top of slice 0 touches slice 1
+        let mut chunk_manager = meshed_world();
+        chunk_manager.set_block(Vector3::new(-16, 15, -5), WorldBlock::Stone(0));
+        assert_eq!(dirty_slices(&chunk_manager), vec![(-1, -1, 0), (-1, -1, 1)]);
+    }
+
+    #[test]
+    fn unmeshed_and_missing_neighbours_are_skipped() {
+        let mut chunk_manager = meshed_world();
+        chunk_manager.chunks.get_mut(&xz_to_index(1, 0)).unwrap().states[0] = ChunkState::PreMesh;
+
+        //x 31 borders chunk 2, which isn't loaded. The bottom of the world has no slice below it
+        chunk_manager.set_block(Vector3::new(16, 0, 8), WorldBlock::Stone(0));
+        chunk_manager.set_block(Vector3::new(31, 40, 8), WorldBlock::Stone(0));
+        assert_eq!(dirty_slices(&chunk_manager), vec![(0, 0, 0), (1, 0, 2)]);
+
+        //a batch marks around every block in it
+        let mut chunk_manager = meshed_world();
+        assert!(chunk_manager.set_blocks(Vector2::new(0, 0), &[(0, 47, 8, WorldBlock::Stone(0)), (8, 100, 8, WorldBlock::Stone(0))]));
+        assert_eq!(dirty_slices(&chunk_manager), vec![(-1, 0, 2), (-1, 0, 3), (0, 0, 2), (0, 0, 3), (0, 0, 6)]);
+    }
+
+    #[test]
+    fn neighbours_only_remesh_slices_facing_the_chunk() {
+        let mut chunk_manager = meshed_world();
+        //on the border with chunk 0 0, and inside the chunk away from it
+        chunk_manager.set_block(Vector3::new(-1, 40, 5), WorldBlock::Stone(0));
+        chunk_manager.set_block(Vector3::new(-8, 100, 5), WorldBlock::Stone(0));
+        //the diagonal only shares its corner column
+        chunk_manager.set_block(Vector3::new(-1, 200, -1), WorldBlock::Stone(0));
+        chunk_manager.set_block(Vector3::new(-1, 150, -8), WorldBlock::Stone(0));
+        for chunkdraw in chunk_manager.chunks.values_mut() {
+            chunkdraw.dirty = vec![false; 16];
+        }
+
+        chunk_manager.mark_neighbours_dirty(Vector2::new(0, 0));
+        assert_eq!(dirty_slices(&chunk_manager), vec![(-1, -1, 12), (-1, 0, 2)]);
+    }
+}
@@ -1,11 +1,11 @@
-use std::{collections::HashMap, sync::Arc};
 use nalgebra::Vector3;
-use shared::world::chunk::{Chunk, ChunkState};
-use wgpu::util::DeviceExt;
 
-use crate::{renderer::surfacevertex::SurfaceVertex, view::camera::Camera};
+use crate::renderer::surfacevertex::SurfaceVertex;
 
-use super::binarymesher::generate_indices;
+use super::chunkdraw::ChunkDraw;
+
+//how far the camera has to move before translucent geometry gets sorted again
+pub const RESORT_DISTANCE: f32 = 1.0;
 
 #[derive(Clone, Copy)]
 pub struct Quad {
@@ -13,39 +13,78 @@ pub struct Quad {
     pub vertices: [SurfaceVertex; 4]
 }
 
-// pub fn sort_chunk_transparent_quads(device: &Arc<wgpu::Device>, camera: &Camera, chunka: &mut Arc<Chunk>, chunkbuff: &mut ChunkDraw, i: usize) {
-    
-//     // let chunk = Arc::make_mut(chunka);
-//     // let camera_pos = Vector3::new(camera.position.x, camera.position.y, camera.position.z);
-    
-//     // if chunkbuff.states[i] != ChunkState::Ready { return };
-        
-//     // chunk.transparent_quads[i].sort_by(|a, b| {
-//     //     let dista = a.center.distance(camera_pos);
-//     //     let distb = b.center.distance(camera_pos);
-
-//     //     dista.partial_cmp(&distb).unwrap()
-//     // });
-
-//     // let vertices = chunk.transparent_quads[i].clone().iter().flat_map(|v| {
-//     //     v.vertices
-//     // }).collect::<Vec<_>>();
-
-//     // let indices = generate_indices(vertices.len());
-
-//     // let ilen = indices.len();
-
-//     // let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-//     //     label: Some(&format!("Transparent Quad Vertex Buffer")),
-//     //     contents: bytemuck::cast_slice(&vertices),
-//     //     usage: wgpu::BufferUsages::VERTEX,
-//     // });
-
-//     // let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-//     //     label: Some(&format!("Transparent Quad Index Buffer")),
-//     //     contents: bytemuck::cast_slice(&indices),
-//     //     usage: wgpu::BufferUsages::INDEX,
-//     // });
-    
-//     // chunkbuff.set_transparent_buffer(i as u32, (vertex_buffer, index_buffer, ilen as u32));
-// }
\ No newline at end of file
+//indices for the quads ordered furthest to closest. Quad n always owns vertices 4n..4n+4 so only the index buffer changes
+pub fn sort_quad_indices(quads: &[Quad], camera_pos: Vector3<f32>) -> Vec<u32> {
+    let mut order = quads.iter().enumerate().map(|(i, quad)| {
+        (i as u32, (quad.center - camera_pos).norm_squared())
+    }).collect::<Vec<_>>();
+
+    order.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut indices = Vec::<u32>::with_capacity(quads.len() * 6);
+    for (quad_index, _) in order {
+        let vert_index = quad_index * 4;
+        indices.push(vert_index);
+        indices.push(vert_index + 1);
+        indices.push(vert_index + 2);
+        indices.push(vert_index);
+        indices.push(vert_index + 2);
+        indices.push(vert_index + 3);
+    }
+    indices
+}
+
+pub fn sort_chunk_transparent_quads(queue: &wgpu::Queue, camera_pos: Vector3<f32>, chunkbuff: &mut ChunkDraw, i: usize) {
+    chunkbuff.transparent_needs_sort[i] = false;
+
+    let quads = &chunkbuff.transparent_quads[i];
+    if quads.is_empty() {return};
+
+    let Some((_, index_buffer, _)) = chunkbuff.transparent_buffers[i].as_ref() else {return};
+
+    let indices = sort_quad_indices(quads, camera_pos);
+
+    queue.write_buffer(index_buffer, 0, bytemuck::cast_slice(&indices));
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::{sort_quad_indices, Quad};
+
+    fn quad(center: Vector3<f32>) -> Quad {
+        Quad {center, vertices: [bytemuck::Zeroable::zeroed(); 4]}
+    }
+
+    //the quad each group of 6 indices draws, in draw order
+    fn draw_order(indices: &[u32]) -> Vec<u32> {
+        indices.chunks(6).map(|triangles| triangles[0] / 4).collect()
+    }
+
+    #[test]
+    fn quads_are_drawn_back_to_front() {
+        let quads = [
+            quad(Vector3::new(0.0, 0.0, 2.0)),
+            quad(Vector3::new(0.0, 0.0, 10.0)),
+            quad(Vector3::new(0.0, 5.0, 0.0)),
+            quad(Vector3::new(1.0, 0.0, 0.0))
+        ];
+
+        let indices = sort_quad_indices(&quads, Vector3::zeros());
+        assert_eq!(draw_order(&indices), vec![1, 2, 0, 3]);
+
+        //from the other side the order turns around
+        let indices = sort_quad_indices(&quads, Vector3::new(0.0, 0.0, 20.0));
+        assert_eq!(draw_order(&indices), vec![2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn quads_keep_their_own_vertices() {
+        let quads = [quad(Vector3::new(0.0, 0.0, 1.0)), quad(Vector3::new(0.0, 0.0, 3.0))];
+        let indices = sort_quad_indices(&quads, Vector3::zeros());
+
+        //two triangles over the far quad's 4 vertices, then the near one's
+        assert_eq!(indices, vec![4, 5, 6, 4, 6, 7, 0, 1, 2, 0, 2, 3]);
+    }
+}
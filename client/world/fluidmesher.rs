@@ -0,0 +1,286 @@
+use shared::world::{block::{BlockFace, FaceTexture}, blockrepr::{get_block_id, get_fluid_level, get_surface_texture_indices, has_partial_transparency, is_fluid, WorldBlock, MAX_FLUID_LEVEL}};
+
+use crate::renderer::{fluidvertex::FluidVertex, surfacevertex::calculate_illumination_bytes};
+
+use super::{binarymesher::generate_indices, meshinput::MeshInput};
+
+//surface height of a full fluid block with nothing of the same fluid above it
+pub const FULL_FLUID_HEIGHT: f32 = 0.875;
+
+pub fn fluid_surface_height(block: &WorldBlock) -> f32 {
+    FULL_FLUID_HEIGHT * (MAX_FLUID_LEVEL + 1 - get_fluid_level(block)) as f32 / (MAX_FLUID_LEVEL + 1) as f32
+}
+
+fn same_fluid(block: &WorldBlock, other: Option<&WorldBlock>) -> bool {
+    other.is_some_and(|o| is_fluid(o) && get_block_id(o) == get_block_id(block))
+}
+
+fn push_quad(vertices: &mut Vec<FluidVertex>, corners: [[f32; 3]; 4], uvs: [[f32; 2]; 4], face: BlockFace, texture: FaceTexture, illumination: u32) {
+    let face_dir = match face {
+        BlockFace::Top => 0,
+        BlockFace::Bottom => 1,
+        BlockFace::Right => 2,
+        BlockFace::Left => 3,
+        BlockFace::Front => 4,
+        BlockFace::Back => 5,
+    };
+
+    let texture_index = match texture {
+        FaceTexture::Static(v) => v as u32,
+    };
+
+    for (position, tex_coords) in corners.into_iter().zip(uvs) {
+        vertices.push(FluidVertex {
+            position,
+            tex_coords,
+            d1: texture_index | (face_dir << 16),
+            illumination
+        });
+    }
+}
+
+//corners are wound counter clockwise seen from outside, so the indices from generate_indices work as is
+pub fn fluid_mesh(input: &MeshInput) -> (Vec<FluidVertex>, Vec<u32>, u32) {
+    if input.fullair {
+        return (Vec::new(), Vec::new(), 0);
+    }
+
+    let get_block_at = |x: i32, y: i32, z: i32| -> Option<&WorldBlock> {
+        input.get(x, y, z)
+    };
+
+    //the corner at (x, z) is shared by the columns x - 1..=x, z - 1..=z.
+    //fluid neighbours are averaged, open neighbours pull the corner down and solid ones are ignored
+    let corner_height = |block: &WorldBlock, x: i32, y: i32, z: i32| -> f32 {
+        let mut total = 0.0;
+        let mut count = 0;
+
+        for (cx, cz) in [(x - 1, z - 1), (x, z - 1), (x - 1, z), (x, z)] {
+            let neighbor = get_block_at(cx, y, cz);
+
+            if same_fluid(block, neighbor) {
+                if same_fluid(block, get_block_at(cx, y + 1, cz)) {
+                    return 1.0;
+                }
+                total += fluid_surface_height(neighbor.unwrap());
+                count += 1;
+            }
+            else if neighbor.is_some_and(has_partial_transparency) {
+                count += 1;
+            }
+        }
+
+        if count == 0 {FULL_FLUID_HEIGHT} else {total / count as f32}
+    };
+
+    let mut vertices = Vec::new();
+
+    for x in 0..16 {
+        for z in 0..16 {
+            for y in 0..16 {
+                let block = get_block_at(x, y, z).unwrap();
+
+                if !is_fluid(block) {
+                    continue;
+                }
+
+                let above = get_block_at(x, y + 1, z);
+
+                let [h00, h10, h01, h11] = if same_fluid(block, above) {
+                    [1.0; 4]
+                } else {
+                    [
+                        corner_height(block, x, y, z),
+                        corner_height(block, x + 1, y, z),
+                        corner_height(block, x, y, z + 1),
+                        corner_height(block, x + 1, y, z + 1),
+                    ]
+                };
+
+                let (fx, fy, fz) = (x as f32, y as f32, z as f32);
+
+                //open sides only, same fluid and opaque neighbours hide the face
+                let exposed = |neighbor: Option<&WorldBlock>| -> bool {
+                    !same_fluid(block, neighbor) && neighbor.is_some_and(has_partial_transparency)
+                };
+
+                let illumination = |neighbor: Option<&WorldBlock>| -> u32 {
+                    calculate_illumination_bytes(neighbor.unwrap_or(block))
+                };
+
+                //the top is drawn even under solid blocks since it sits below the top of the block
+                if !same_fluid(block, above) {
+                    push_quad(&mut vertices, [
+                        [fx, fy + h00, fz],
+                        [fx, fy + h01, fz + 1.0],
+                        [fx + 1.0, fy + h11, fz + 1.0],
+                        [fx + 1.0, fy + h10, fz],
+                    ], [
+                        [fx, fz], [fx, fz + 1.0], [fx + 1.0, fz + 1.0], [fx + 1.0, fz]
+                    ], BlockFace::Top, get_surface_texture_indices(block, BlockFace::Top).0, illumination(above));
+                }
+
+                let below = get_block_at(x, y - 1, z);
+                if exposed(below) {
+                    push_quad(&mut vertices, [
+                        [fx, fy, fz],
+                        [fx + 1.0, fy, fz],
+                        [fx + 1.0, fy, fz + 1.0],
+                        [fx, fy, fz + 1.0],
+                    ], [
+                        [fx, fz], [fx + 1.0, fz], [fx + 1.0, fz + 1.0], [fx, fz + 1.0]
+                    ], BlockFace::Bottom, get_surface_texture_indices(block, BlockFace::Bottom).0, illumination(below));
+                }
+
+                let right = get_block_at(x + 1, y, z);
+                if exposed(right) {
+                    push_quad(&mut vertices, [
+                        [fx + 1.0, fy, fz],
+                        [fx + 1.0, fy + h10, fz],
+                        [fx + 1.0, fy + h11, fz + 1.0],
+                        [fx + 1.0, fy, fz + 1.0],
+                    ], [
+                        [fz, -fy], [fz, -fy - h10], [fz + 1.0, -fy - h11], [fz + 1.0, -fy]
+                    ], BlockFace::Right, get_surface_texture_indices(block, BlockFace::Right).0, illumination(right));
+                }
+
+                let left = get_block_at(x - 1, y, z);
+                if exposed(left) {
+                    push_quad(&mut vertices, [
+                        [fx, fy, fz],
+                        [fx, fy, fz + 1.0],
+                        [fx, fy + h01, fz + 1.0],
+                        [fx, fy + h00, fz],
+                    ], [
+                        [fz, -fy], [fz + 1.0, -fy], [fz + 1.0, -fy - h01], [fz, -fy - h00]
+                    ], BlockFace::Left, get_surface_texture_indices(block, BlockFace::Left).0, illumination(left));
+                }
+
+                let front = get_block_at(x, y, z + 1);
+                if exposed(front) {
+                    push_quad(&mut vertices, [
+                        [fx, fy, fz + 1.0],
+                        [fx + 1.0, fy, fz + 1.0],
+                        [fx + 1.0, fy + h11, fz + 1.0],
+                        [fx, fy + h01, fz + 1.0],
+                    ], [
+                        [fx, -fy], [fx + 1.0, -fy], [fx + 1.0, -fy - h11], [fx, -fy - h01]
+                    ], BlockFace::Front, get_surface_texture_indices(block, BlockFace::Front).0, illumination(front));
+                }
+
+                let back = get_block_at(x, y, z - 1);
+                if exposed(back) {
+                    push_quad(&mut vertices, [
+                        [fx, fy, fz],
+                        [fx, fy + h00, fz],
+                        [fx + 1.0, fy + h10, fz],
+                        [fx + 1.0, fy, fz],
+                    ], [
+                        [fx, -fy], [fx, -fy - h00], [fx + 1.0, -fy - h10], [fx + 1.0, -fy]
+                    ], BlockFace::Back, get_surface_texture_indices(block, BlockFace::Back).0, illumination(back));
+                }
+            }
+        }
+    }
+
+    let indices = generate_indices(vertices.len());
+    let l = indices.len();
+    (vertices, indices, l as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use nalgebra::Vector2;
+    use shared::world::{blockrepr::{set_fluid_level, WorldBlock, MAX_FLUID_LEVEL}, chunk::{local_xyz_to_index, xz_to_index, Chunk}};
+
+    use crate::{renderer::fluidvertex::FluidVertex, world::meshinput::MeshInput};
+
+    use super::{fluid_mesh, fluid_surface_height, FULL_FLUID_HEIGHT};
+
+    //a stone floor at y 0 with the blocks on top, air everywhere else
+    fn mesh(blocks: &[([u32; 3], WorldBlock)]) -> Vec<FluidVertex> {
+        let grid = (0..16).map(|y_slice| {
+            let mut slice = vec![WorldBlock::Air(15); 4096];
+            if y_slice == 0 {
+                for x in 0..16 {
+                    for z in 0..16 {
+                        slice[local_xyz_to_index(x, 0, z) as usize] = WorldBlock::Stone(0);
+                    }
+                }
+                for ([x, y, z], block) in blocks {
+                    slice[local_xyz_to_index(*x, *y, *z) as usize] = *block;
+                }
+            }
+            slice
+        }).collect();
+        let chunks = HashMap::from([(xz_to_index(0, 0), Arc::new(Chunk::from_blocks(Vector2::new(0, 0), grid)))]);
+
+        let (vertices, indices, count) = fluid_mesh(&MeshInput::from_chunk_map(0, 0, 0, &chunks).unwrap());
+        assert_eq!(indices.len(), count as usize);
+        assert_eq!(vertices.len() / 4 * 6, indices.len());
+        vertices
+    }
+
+    fn water(level: u8) -> WorldBlock {
+        let mut block = WorldBlock::Water(15);
+        set_fluid_level(&mut block, level);
+        block
+    }
+
+    //the face direction of every quad, same order as SurfaceVertex
+    fn faces(vertices: &[FluidVertex]) -> Vec<u32> {
+        vertices.chunks(4).map(|quad| quad[0].d1 >> 16).collect()
+    }
+
+    #[test]
+    fn surface_height_falls_with_the_level() {
+        assert_eq!(fluid_surface_height(&water(0)), FULL_FLUID_HEIGHT);
+        for level in 1..=MAX_FLUID_LEVEL {
+            assert!(fluid_surface_height(&water(level)) < fluid_surface_height(&water(level - 1)));
+        }
+        assert!(fluid_surface_height(&water(MAX_FLUID_LEVEL)) > 0.0);
+        //levels past the last are the thinnest layer, not nothing
+        assert_eq!(fluid_surface_height(&water(MAX_FLUID_LEVEL + 5)), fluid_surface_height(&water(MAX_FLUID_LEVEL)));
+    }
+
+    #[test]
+    fn walled_in_fluid_sits_at_its_level() {
+        //stone all around, the diagonals too since the corners look at them
+        let mut blocks = Vec::new();
+        for x in 4..=6 {
+            for z in 4..=6 {
+                blocks.push(([x, 1, z], WorldBlock::Stone(0)));
+            }
+        }
+        blocks.push(([5, 1, 5], water(3)));
+
+        let vertices = mesh(&blocks);
+        assert_eq!(faces(&vertices), vec![0]);
+        for vertex in &vertices {
+            assert_eq!(vertex.position[1], 1.0 + fluid_surface_height(&water(3)));
+        }
+    }
+
+    #[test]
+    fn faces_between_fluids_are_culled() {
+        //two side by side, one of them with another on top
+        let vertices = mesh(&[([5, 1, 5], water(0)), ([6, 1, 5], water(0)), ([6, 2, 5], water(0))]);
+
+        let quads = vertices.chunks(4).map(|quad| (quad[0].d1 >> 16, quad.iter().map(|v| v.position).collect::<Vec<_>>())).collect::<Vec<_>>();
+
+        //nothing on the plane between the two side by side, the one on top has air next to it
+        assert!(!quads.iter().any(|(face, corners)| (*face == 2 || *face == 3) && corners.iter().all(|p| p[0] == 6.0 && p[1] <= 2.0)));
+        //nor between the stacked two, the lower one is full to the top under the other
+        assert!(!quads.iter().any(|(face, corners)| (*face == 0 || *face == 1) && corners.iter().all(|p| p[1] == 2.0)));
+        assert!(quads.iter().filter(|(face, _)| *face != 0 && *face != 1).flat_map(|(_, corners)| corners).any(|p| p[1] == 2.0));
+
+        //two tops, 3 open sides on the first, 2 on the lower second and 4 on the one on top. The floor hides the bottoms
+        let mut counts = [0; 6];
+        for face in faces(&vertices) {
+            counts[face as usize] += 1;
+        }
+        assert_eq!(counts, [2, 0, 2, 2, 3, 3]);
+    }
+}
@@ -1,20 +1,64 @@
-use std::{collections::HashMap, sync::Arc};
-
-use shared::world::chunk::Chunk;
 use stopwatch::Stopwatch;
 
-use crate::{renderer::surfacevertex::SurfaceVertex, world::fastmesher::fast_mesh};
+use crate::{renderer::{fluidvertex::FluidVertex, surfacevertex::SurfaceVertex, vertex::ModelVertex}, world::{fastmesher::fast_mesh, fluidmesher::fluid_mesh, modelmesher::model_mesh}};
+
+use super::{binarymesher::{binary_mesh, MeshStageType}, depthsort::Quad, meshinput::MeshInput, occlusion::{compute_connectivity, SliceConnectivity}};
+
+//which mesher the worker threads use. The fast mesher emits one quad per face and is kept around as a reference.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MesherKind {
+    Fast,
+    #[default]
+    Binary
+}
+
+//vertices, indices, index count
+pub type SliceMesh = (Vec<SurfaceVertex>, Vec<u32>, u32);
 
-use super::{binarymesher::{binary_mesh, MeshStageType}, depthsort::Quad};
+pub struct SliceMeshes {
+    pub solid: SliceMesh,
+    pub cutout: SliceMesh,
+    pub transparent: SliceMesh,
+    pub fluid: (Vec<FluidVertex>, Vec<u32>, u32),
+    //non-cube blocks, see modelmesher
+    pub model: (Vec<ModelVertex>, Vec<u32>, u32),
+    //quads of the transparent mesh in vertex order, for depth sorting
+    pub transparent_quads: Vec<Quad>,
+    pub connectivity: SliceConnectivity
+}
 
-pub fn mesh_slice_arrayed(chunk_x: i32, chunk_z: i32, y_slice: u32, chunks: &HashMap<u32, Arc<Chunk>>) -> ((Vec<SurfaceVertex>, Vec<u32>, u32), (Vec<SurfaceVertex>, Vec<u32>, u32, Vec<Quad>)) {
+pub fn mesh_slice_arrayed(input: &MeshInput, mesher: MesherKind) -> SliceMeshes {
     let t = Stopwatch::start_new();
-    let solidmesh = fast_mesh(chunk_x, chunk_z, y_slice, chunks, MeshStageType::Solid);
-    let tz = fast_mesh(chunk_x, chunk_z, y_slice, chunks, MeshStageType::Transparent);
-    let b = (
-        (solidmesh.0, solidmesh.1, solidmesh.2),
-        (tz.0, tz.1, tz.2, Vec::new())
-    );
+    let b = match mesher {
+        MesherKind::Fast => {
+            let solidmesh = fast_mesh(input, MeshStageType::Solid);
+            let cutout = fast_mesh(input, MeshStageType::Cutout);
+            let tz = fast_mesh(input, MeshStageType::Transparent);
+            SliceMeshes {
+                solid: solidmesh,
+                cutout,
+                transparent: tz,
+                fluid: fluid_mesh(input),
+                model: model_mesh(input),
+                transparent_quads: Vec::new(),
+                connectivity: compute_connectivity(input)
+            }
+        },
+        MesherKind::Binary => {
+            let solidmesh = binary_mesh(input, MeshStageType::Solid);
+            let cutout = binary_mesh(input, MeshStageType::Cutout);
+            let tz = binary_mesh(input, MeshStageType::Transparent);
+            SliceMeshes {
+                solid: (solidmesh.0, solidmesh.1, solidmesh.2),
+                cutout: (cutout.0, cutout.1, cutout.2),
+                transparent: (tz.0, tz.1, tz.2),
+                fluid: fluid_mesh(input),
+                model: model_mesh(input),
+                transparent_quads: tz.3,
+                connectivity: compute_connectivity(input)
+            }
+        }
+    };
     println!("MESH {}ms", t.elapsed_ms());
     b
-}
\ No newline at end of file
+}
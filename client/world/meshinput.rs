@@ -0,0 +1,145 @@
+use std::{collections::HashMap, sync::Arc};
+
+use shared::world::{blockrepr::WorldBlock, chunk::{xz_to_index, Chunk}};
+
+//everything a mesher needs for one slice: the 16³ blocks plus a 1 block border from the 26 neighbours.
+//it's built on the main thread and owned by the job, so the workers never touch the chunk map
+pub struct MeshInput {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub y_slice: u32,
+    pub fullair: bool,
+    //18³, z major then y then x. None where the neighbour isn't loaded or is outside the world
+    blocks: Vec<Option<WorldBlock>>
+}
+
+//padded index 0 and 17 are the border, map them to the neighbouring chunk and its local coordinate
+fn split_padded(p: usize) -> (i32, u32) {
+    match p {
+        0 => (-1, 15),
+        17 => (1, 0),
+        _ => (0, p as u32 - 1)
+    }
+}
+
+impl MeshInput {
+    //None if the slice's own chunk isn't loaded
+    pub fn new<'a>(chunk_x: i32, chunk_z: i32, y_slice: u32, get_chunk: impl Fn(i32, i32) -> Option<&'a Chunk>) -> Option<Self> {
+        let center = get_chunk(chunk_x, chunk_z)?;
+
+        //[dz + 1][dx + 1]
+        let neighbours: [[Option<&Chunk>; 3]; 3] = std::array::from_fn(|dz| {
+            std::array::from_fn(|dx| get_chunk(chunk_x + dx as i32 - 1, chunk_z + dz as i32 - 1))
+        });
+
+        let mut blocks = vec![None; 18 * 18 * 18];
+
+        for z in 0..18 {
+            let (dz, lz) = split_padded(z);
+            for x in 0..18 {
+                let (dx, lx) = split_padded(x);
+                let Some(chunk) = neighbours[(dz + 1) as usize][(dx + 1) as usize] else {continue};
+
+                for y in 0..18 {
+                    let ay = y_slice as i32 * 16 + y as i32 - 1;
+                    if !(0..=255).contains(&ay) {continue};
+                    blocks[(z * 18 + y) * 18 + x] = Some(*chunk.get_block_at(lx, ay as u32, lz));
+                }
+            }
+        }
+
+        Some(Self {
+            chunk_x,
+            chunk_z,
+            y_slice,
+            fullair: center.fullair[y_slice as usize],
+            blocks
+        })
+    }
+
+    pub fn from_chunk_map(chunk_x: i32, chunk_z: i32, y_slice: u32, chunks: &HashMap<u32, Arc<Chunk>>) -> Option<Self> {
+        Self::new(chunk_x, chunk_z, y_slice, |x, z| chunks.get(&xz_to_index(x, z)).map(|c| c.as_ref()))
+    }
+
+    //slice local coordinates, -1 and 16 reach into the neighbours
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Option<&WorldBlock> {
+        self.get_padded((x + 1) as usize, (y + 1) as usize, (z + 1) as usize)
+    }
+
+    //0..18 on every axis, 0 and 17 are the border
+    pub fn get_padded(&self, x: usize, y: usize, z: usize) -> Option<&WorldBlock> {
+        self.blocks[(z * 18 + y) * 18 + x].as_ref()
+    }
+
+    //absolute block position of the slice's (0, 0, 0)
+    pub fn origin(&self) -> [i32; 3] {
+        [self.chunk_x * 16, self.y_slice as i32 * 16, self.chunk_z * 16]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use nalgebra::Vector2;
+    use shared::world::{blockrepr::WorldBlock, chunk::{local_xyz_to_index, xz_to_index, Chunk}};
+
+    use super::MeshInput;
+
+    //every block stores its chunk and slice so the border can be traced back to where it came from
+    fn tagged_chunk(cx: i32, cz: i32) -> Arc<Chunk> {
+        let grid = (0..16).map(|y_slice| {
+            let mut slice = vec![WorldBlock::Air(0); 4096];
+            for x in 0..16 {
+                for z in 0..16 {
+                    for y in 0..16 {
+                        let tag = ((cx + 1) * 3 + (cz + 1)) as u8 * 16 + y_slice as u8;
+                        slice[local_xyz_to_index(x, y, z) as usize] = WorldBlock::Stone(tag);
+                    }
+                }
+            }
+            slice
+        }).collect();
+        Arc::new(Chunk::from_blocks(Vector2::new(cx, cz), grid))
+    }
+
+    fn tag(cx: i32, cz: i32, y_slice: u8) -> WorldBlock {
+        WorldBlock::Stone(((cx + 1) * 3 + (cz + 1)) as u8 * 16 + y_slice)
+    }
+
+    #[test]
+    fn border_comes_from_the_neighbours() {
+        let mut chunks = HashMap::new();
+        for cx in -1..=1 {
+            for cz in -1..=1 {
+                chunks.insert(xz_to_index(cx, cz), tagged_chunk(cx, cz));
+            }
+        }
+
+        let input = MeshInput::from_chunk_map(0, 0, 3, &chunks).unwrap();
+
+        assert_eq!(input.get(0, 0, 0), Some(&tag(0, 0, 3)));
+        assert_eq!(input.get(15, 15, 15), Some(&tag(0, 0, 3)));
+        assert_eq!(input.get(-1, 5, 5), Some(&tag(-1, 0, 3)));
+        assert_eq!(input.get(16, 5, 5), Some(&tag(1, 0, 3)));
+        assert_eq!(input.get(5, 5, -1), Some(&tag(0, -1, 3)));
+        assert_eq!(input.get(16, 5, 16), Some(&tag(1, 1, 3)));
+        assert_eq!(input.get(5, -1, 5), Some(&tag(0, 0, 2)));
+        assert_eq!(input.get(-1, 16, -1), Some(&tag(-1, -1, 4)));
+    }
+
+    #[test]
+    fn missing_neighbours_and_world_edges_are_empty() {
+        let chunks = HashMap::from([(xz_to_index(0, 0), tagged_chunk(0, 0))]);
+
+        let bottom = MeshInput::from_chunk_map(0, 0, 0, &chunks).unwrap();
+        assert_eq!(bottom.get(5, -1, 5), None);
+        assert_eq!(bottom.get(-1, 5, 5), None);
+        assert_eq!(bottom.get(5, 16, 5), Some(&tag(0, 0, 1)));
+
+        let top = MeshInput::from_chunk_map(0, 0, 15, &chunks).unwrap();
+        assert_eq!(top.get(5, 16, 5), None);
+
+        assert!(MeshInput::from_chunk_map(1, 0, 0, &chunks).is_none());
+    }
+}
@@ -0,0 +1,223 @@
+use std::{collections::HashMap, sync::{mpsc::{Receiver, Sender}, Arc}};
+
+use nalgebra::Vector3;
+
+use crate::{renderer::renderctx::Renderctx, view::{camera::Camera, frustum::Frustum}};
+
+use super::{chunkmanager::ChunkManager, mesher::MesherKind, meshthread::{spawn_chunk_meshing_loop, MeshJob, MeshResult, MeshedSlice}};
+
+//chunk x, chunk z, y slice
+pub type SliceKey = (i32, i32, u32);
+
+//slices outside the view frustum are pushed back as if they were this many times further away
+const HIDDEN_PRIORITY_PENALTY: f32 = 4.0;
+
+//how many jobs each worker gets handed at once. More keeps the workers busy, less lets priorities react quicker
+const JOBS_PER_WORKER: usize = 2;
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MeshSchedulerStats {
+    pub pending: usize,
+    pub in_flight: usize,
+    pub completed: u64,
+    //results that came back after a newer request for the same slice, or for a chunk that was unloaded
+    pub dropped_stale: u64
+}
+
+//lower runs first. Distance to the slice center, with slices the renderer would cull pushed back
+pub fn mesh_priority(camera: &Camera, frustum: &Frustum, key: SliceKey) -> f32 {
+    let center = Vector3::new(key.0 as f32 * 16.0 + 8.0, key.2 as f32 * 16.0 + 8.0, key.1 as f32 * 16.0 + 8.0);
+    let distance = (center - camera.position.coords).norm();
+
+    if frustum.intersects_slice(key.0, key.1, key.2) {distance} else {distance * HIDDEN_PRIORITY_PENALTY}
+}
+
+//which version of each slice is wanted. Requesting a slice again replaces its pending request, and results
+//from any older version are stale
+#[derive(Default)]
+pub struct SliceRequests {
+    //latest version requested for each slice
+    versions: HashMap<SliceKey, u64>,
+    //requested but not handed to a worker yet
+    pending: HashMap<SliceKey, u64>,
+    next_version: u64
+}
+
+impl SliceRequests {
+    pub fn request(&mut self, key: SliceKey) {
+        self.next_version += 1;
+        self.versions.insert(key, self.next_version);
+        self.pending.insert(key, self.next_version);
+    }
+
+    pub fn cancel_chunk(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.pending.retain(|k, _| (k.0, k.1) != (chunk_x, chunk_z));
+        self.versions.retain(|k, _| (k.0, k.1) != (chunk_x, chunk_z));
+    }
+
+    //the pending slices and their versions, they stay pending until taken
+    pub fn pending(&self) -> impl Iterator<Item = (SliceKey, u64)> + '_ {
+        self.pending.iter().map(|(k, v)| (*k, *v))
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn take(&mut self, key: SliceKey) -> Option<u64> {
+        self.pending.remove(&key)
+    }
+
+    //the slice won't be meshed, nothing it sent off is wanted anymore
+    pub fn forget(&mut self, key: SliceKey) {
+        self.pending.remove(&key);
+        self.versions.remove(&key);
+    }
+
+    //true if version is the latest one asked for, which also completes the request
+    pub fn complete(&mut self, key: SliceKey, version: u64) -> bool {
+        if self.versions.get(&key) != Some(&version) {
+            return false;
+        }
+        self.versions.remove(&key);
+        true
+    }
+}
+
+//keeps the requested slices on the main thread and only hands the workers a few at a time, closest and visible first
+pub struct MeshScheduler {
+    sender: Sender<MeshJob>,
+    receiver: Receiver<MeshResult>,
+    renderctx: Arc<Renderctx>,
+    max_in_flight: usize,
+    requests: SliceRequests,
+    in_flight: usize,
+    stats: MeshSchedulerStats
+}
+
+impl MeshScheduler {
+    pub fn new(num_workers: usize, mesher: MesherKind, renderctx: Arc<Renderctx>) -> Self {
+        let (sender, receiver) = spawn_chunk_meshing_loop(num_workers, mesher);
+        Self {
+            sender,
+            receiver,
+            renderctx,
+            max_in_flight: num_workers * JOBS_PER_WORKER,
+            requests: SliceRequests::default(),
+            in_flight: 0,
+            stats: MeshSchedulerStats::default()
+        }
+    }
+
+    pub fn request(&mut self, key: SliceKey) {
+        self.requests.request(key);
+    }
+
+    //forgets every slice of the chunk, anything still in flight for it gets dropped when it lands
+    pub fn cancel_chunk(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.requests.cancel_chunk(chunk_x, chunk_z);
+    }
+
+    //hands the highest priority slices to the workers. The blocks are snapshotted now, so edits made while
+    //a slice was waiting are included. Slices that can't be meshed go back to the state they were queued from
+    pub fn dispatch(&mut self, chunk_manager: &mut ChunkManager, camera: &Camera) {
+        let budget = self.max_in_flight.saturating_sub(self.in_flight);
+        if budget == 0 || self.requests.pending_count() == 0 {return};
+
+        let frustum = camera.frustum();
+        let mut queue = self.requests.pending().map(|(k, v)| (mesh_priority(camera, &frustum, k), k, v)).collect::<Vec<_>>();
+        queue.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut sent = 0;
+        for (_, key, version) in queue {
+            if sent == budget {break};
+
+            let Some(input) = chunk_manager.mesh_input(key.0, key.1, key.2) else {
+                self.requests.forget(key);
+                chunk_manager.unqueue_slice(key);
+                continue;
+            };
+            self.requests.take(key);
+
+            self.in_flight += 1;
+            self.sender.send((input, version, self.renderctx.clone())).unwrap();
+            sent += 1;
+        }
+    }
+
+    //finished slices that are still wanted, in the order they finished
+    pub fn poll(&mut self) -> Vec<(SliceKey, MeshedSlice)> {
+        let mut out = Vec::new();
+
+        while let Ok((x, z, y, version, meshed)) = self.receiver.try_recv() {
+            let key = (x, z, y);
+            self.in_flight -= 1;
+
+            if !self.requests.complete(key, version) {
+                self.stats.dropped_stale += 1;
+                continue;
+            }
+
+            self.stats.completed += 1;
+            out.push((key, meshed));
+        }
+
+        out
+    }
+
+    pub fn stats(&self) -> MeshSchedulerStats {
+        MeshSchedulerStats {
+            pending: self.requests.pending_count(),
+            in_flight: self.in_flight,
+            ..self.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SliceRequests;
+
+    #[test]
+    fn requesting_again_replaces_the_pending_request() {
+        let mut requests = SliceRequests::default();
+        requests.request((0, 0, 3));
+        requests.request((0, 0, 3));
+        requests.request((1, 0, 3));
+
+        assert_eq!(requests.pending_count(), 2);
+        let version = requests.pending().find(|(k, _)| *k == (0, 0, 3)).unwrap().1;
+        assert_eq!(requests.take((0, 0, 3)), Some(version));
+        assert_eq!(requests.take((0, 0, 3)), None);
+        assert!(requests.complete((0, 0, 3), version));
+    }
+
+    #[test]
+    fn older_versions_are_stale() {
+        let mut requests = SliceRequests::default();
+        requests.request((2, -1, 0));
+        let first = requests.take((2, -1, 0)).unwrap();
+
+        //changed again while the first mesh was in flight
+        requests.request((2, -1, 0));
+        let second = requests.take((2, -1, 0)).unwrap();
+
+        assert!(!requests.complete((2, -1, 0), first));
+        assert!(requests.complete((2, -1, 0), second));
+        //landing twice doesn't count
+        assert!(!requests.complete((2, -1, 0), second));
+    }
+
+    #[test]
+    fn cancelled_chunks_drop_their_results() {
+        let mut requests = SliceRequests::default();
+        requests.request((0, 0, 0));
+        requests.request((0, 0, 1));
+        requests.request((0, 1, 0));
+        let in_flight = requests.take((0, 0, 0)).unwrap();
+
+        requests.cancel_chunk(0, 0);
+        assert_eq!(requests.pending().map(|(k, _)| k).collect::<Vec<_>>(), vec![(0, 1, 0)]);
+        assert!(!requests.complete((0, 0, 0), in_flight));
+    }
+}
@@ -1,53 +1,75 @@
-use std::{collections::HashMap, sync::{mpsc::{self, Receiver, Sender}, Arc}, thread};
+use std::{sync::{mpsc::{self, Receiver, Sender}, Arc}, thread};
 
-use shared::world::chunk::Chunk;
-use stopwatch::Stopwatch;
 use wgpu::util::DeviceExt;
 
 use crate::renderer::renderctx::Renderctx;
 
-use super::mesher::mesh_slice_arrayed;
+use super::{depthsort::Quad, mesher::{mesh_slice_arrayed, MesherKind}, meshinput::MeshInput, occlusion::SliceConnectivity};
+
+//the blocks to mesh, the request version (handed back with the result) and the context to create the buffers with
+pub type MeshJob = (MeshInput, u64, Arc<Renderctx>);
+
+//chunk x, chunk z, y slice, request version, buffers
+pub type MeshResult = (i32, i32, u32, u64, MeshedSlice);
+
+//vertex buffer, index buffer, index count
+pub type SliceBuffers = (wgpu::Buffer, wgpu::Buffer, u32);
+
+pub struct MeshedSlice {
+    pub solid: SliceBuffers,
+    pub cutout: SliceBuffers,
+    pub transparent: SliceBuffers,
+    pub fluid: SliceBuffers,
+    pub model: SliceBuffers,
+    pub transparent_quads: Vec<Quad>,
+    pub connectivity: SliceConnectivity
+}
+
+fn create_slice_buffers<V: bytemuck::Pod>(ctx: &Renderctx, label: &str, mesh: &(Vec<V>, Vec<u32>, u32), index_usage: wgpu::BufferUsages) -> SliceBuffers {
+    let vertex_buffer = ctx.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(&format!("Chunk Vertex Buffer {}", label)),
+        contents: bytemuck::cast_slice(&mesh.0),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    let index_buffer = ctx.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(&format!("Chunk Index Buffer {}", label)),
+        contents: bytemuck::cast_slice(&mesh.1),
+        usage: index_usage,
+    });
+
+    (vertex_buffer, index_buffer, mesh.2)
+}
+
+//meshes the slice and uploads it, on whatever thread calls it
+pub fn build_meshed_slice(ctx: &Renderctx, input: &MeshInput, mesher: MesherKind) -> MeshedSlice {
+    let result = mesh_slice_arrayed(input, mesher);
+
+    MeshedSlice {
+        solid: create_slice_buffers(ctx, "Solid", &result.solid, wgpu::BufferUsages::INDEX),
+        cutout: create_slice_buffers(ctx, "Cutout", &result.cutout, wgpu::BufferUsages::INDEX),
+        //rewritten in place when the quads get depth sorted
+        transparent: create_slice_buffers(ctx, "Transparent", &result.transparent, wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST),
+        fluid: create_slice_buffers(ctx, "Fluid", &result.fluid, wgpu::BufferUsages::INDEX),
+        model: create_slice_buffers(ctx, "Model", &result.model, wgpu::BufferUsages::INDEX),
+        transparent_quads: result.transparent_quads,
+        connectivity: result.connectivity
+    }
+}
 
 pub fn spawn_chunk_meshing_worker_thread(
     id: usize,
-    send_back: Sender<(usize, i32, i32, u32, ((wgpu::Buffer, wgpu::Buffer, u32), (wgpu::Buffer, wgpu::Buffer, u32)))>
-) -> Sender<(i32, i32, u32, HashMap<u32, Arc<Chunk>>, Arc<Renderctx>)> {
-    let (send, recv) = mpsc::channel::<(i32, i32, u32, HashMap<u32, Arc<Chunk>>, Arc<Renderctx>)>();
+    mesher: MesherKind,
+    send_back: Sender<(usize, MeshResult)>
+) -> Sender<MeshJob> {
+    let (send, recv) = mpsc::channel::<MeshJob>();
     
 
     thread::spawn(move || {
-        while let Ok((chunk_x, chunk_z, y_slice, mut chunks, ctx)) = recv.recv() {
-            let t = Stopwatch::start_new();
-            let result = mesh_slice_arrayed(chunk_x, chunk_z, y_slice, &chunks);
-
-            let ((vertices, indices, ilen), (vertices_transparent, indices_transparent, ilen_t, quads)) = (result.0, result.1);
-
-            let vertex_buffer = ctx.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("Chunk Vertex Buffer")),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-
-            let index_buffer = ctx.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("Chunk Index Buffer")),
-                contents: bytemuck::cast_slice(&indices),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-
-            let vertex_buffer_t = ctx.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("Chunk Vertex Buffer Transparent")),
-                contents: bytemuck::cast_slice(&vertices_transparent),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-
-            let index_buffer_t = ctx.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("Chunk Index Buffer Transparent")),
-                contents: bytemuck::cast_slice(&indices_transparent),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-
-
-            send_back.send((id, chunk_x, chunk_z, y_slice, ((vertex_buffer, index_buffer, ilen), (vertex_buffer_t, index_buffer_t, ilen_t)))).unwrap();
+        while let Ok((input, version, ctx)) = recv.recv() {
+            let meshed = build_meshed_slice(&ctx, &input, mesher);
+
+            send_back.send((id, (input.chunk_x, input.chunk_z, input.y_slice, version, meshed))).unwrap();
             
         }
         
@@ -57,10 +79,11 @@ pub fn spawn_chunk_meshing_worker_thread(
 }
 
 pub fn spawn_chunk_meshing_loop(
-    num_workers: usize
+    num_workers: usize,
+    mesher: MesherKind
 ) -> (
-    Sender<(i32, i32, u32, HashMap<u32, Arc<Chunk>>, Arc<Renderctx>)>,
-    Receiver<(i32, i32, u32, ((wgpu::Buffer, wgpu::Buffer, u32), (wgpu::Buffer, wgpu::Buffer, u32)))>
+    Sender<MeshJob>,
+    Receiver<MeshResult>
 ) {
     //unapologetically stolen from elttob
     let (frommain, frommainrecv) = mpsc::channel();
@@ -76,16 +99,15 @@ pub fn spawn_chunk_meshing_loop(
     thread::spawn(move || {
         let send_idle_worker = send_idle_worker.clone();
         loop {
-            let data: (usize, i32, i32, u32, ((wgpu::Buffer, wgpu::Buffer, u32), (wgpu::Buffer, wgpu::Buffer, u32))) = worker_recv_finished_chunks.recv().unwrap();
-            let id = data.0.clone();
-            tomain.send((data.1, data.2, data.3, data.4)).unwrap();
+            let (id, result): (usize, MeshResult) = worker_recv_finished_chunks.recv().unwrap();
+            tomain.send(result).unwrap();
             send_idle_worker.send(id).unwrap();
         }
     });
 
     thread::spawn(move || {
         let mut workers = (0..num_workers).map(|id| {
-            spawn_chunk_meshing_worker_thread(id, worker_send_finished_chunks.clone())
+            spawn_chunk_meshing_worker_thread(id, mesher, worker_send_finished_chunks.clone())
         }).collect::<Vec<_>>();
 
         loop {
@@ -4,4 +4,9 @@ pub mod meshthread;
 pub mod mesher;
 pub mod binarymesher;
 pub mod depthsort;
-pub mod fastmesher;
\ No newline at end of file
+pub mod fastmesher;
+pub mod fluidmesher;
+pub mod modelmesher;
+pub mod meshinput;
+pub mod meshscheduler;
+pub mod occlusion;
\ No newline at end of file
@@ -0,0 +1,164 @@
+use std::{collections::HashMap, sync::Arc};
+
+use nalgebra::{Matrix3, Vector3};
+use shared::{loaders::model_loader::{get_block_model, BlockModel}, world::blockrepr::{block_orientation_matrix, get_block_model_alias, has_partial_transparency}};
+
+use super::meshinput::MeshInput;
+
+use crate::renderer::{surfacevertex::calculate_illumination_bytes, vertex::{calculate_tangents_inplace_modelvertex, ModelVertex}};
+
+//turns a block space position around the block's center
+fn orient_position(orientation: &Matrix3<f32>, position: [f32; 3]) -> [f32; 3] {
+    let centered = Vector3::from(position) - Vector3::repeat(0.5);
+    (orientation * centered + Vector3::repeat(0.5)).into()
+}
+
+//meshes every block with a model in the slice, turned by the block's orientation bits. Faces on the block boundary are
+//culled against full (opaque cube) neighbours, everything else is always emitted
+pub fn model_mesh(input: &MeshInput) -> (Vec<ModelVertex>, Vec<u32>, u32) {
+    model_mesh_with(input, get_block_model)
+}
+
+fn model_mesh_with(input: &MeshInput, get_model: impl Fn(&str) -> Option<Arc<BlockModel>>) -> (Vec<ModelVertex>, Vec<u32>, u32) {
+    if input.fullair {
+        return (Vec::new(), Vec::new(), 0);
+    }
+
+    //the registry is behind a mutex, so look each model up once per slice
+    let mut models: HashMap<&'static str, Option<Arc<BlockModel>>> = HashMap::new();
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for x in 0..16 {
+        for z in 0..16 {
+            for y in 0..16 {
+                let Some(block) = input.get(x, y, z) else {continue};
+                let Some(alias) = get_block_model_alias(block) else {continue};
+                let Some(model) = models.entry(alias).or_insert_with(|| get_model(alias)) else {continue};
+
+                let illumination = calculate_illumination_bytes(block);
+                let orientation = block_orientation_matrix(block);
+                let orientation_f32 = orientation.map(|v| v as f32);
+                let normal = |face_normal: [f32; 3]| (orientation_f32 * Vector3::from(face_normal)).into();
+
+                for face in model.faces.iter() {
+                    if let Some(cull) = face.cull {
+                        let normal = orientation * cull.normal();
+                        let neighbor = input.get(x + normal.x, y + normal.y, z + normal.z);
+                        if !neighbor.is_some_and(has_partial_transparency) {
+                            continue;
+                        }
+                    }
+
+                    let base = vertices.len() as u32;
+
+                    for (position, tex_coords) in face.positions.iter().zip(face.tex_coords.iter()) {
+                        vertices.push(ModelVertex {
+                            position: {
+                                let [px, py, pz] = orient_position(&orientation_f32, *position);
+                                [px + x as f32, py + y as f32, pz + z as f32]
+                            },
+                            tex_coords: *tex_coords,
+                            normal: normal(face.normal),
+                            tangent: [0.0; 3],
+                            bitangent: [0.0; 3],
+                            diffuse_texture_index: face.texture,
+                            normal_texture_index: 0,
+                            emissive_texture_index: 0,
+                            illumination
+                        });
+                    }
+
+                    //faces are convex polygons, fan them out from the first corner
+                    for i in 1..face.positions.len() as u32 - 1 {
+                        indices.extend([base, base + i, base + i + 1]);
+                    }
+                }
+            }
+        }
+    }
+
+    if !vertices.is_empty() {
+        calculate_tangents_inplace_modelvertex(&mut vertices, &mut indices);
+    }
+
+    let l = indices.len();
+    (vertices, indices, l as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use nalgebra::Vector2;
+    use shared::{loaders::model_loader::{BlockModel, ModelFace}, world::{block::BlockFace, blockrepr::{set_block_orientation, WorldBlock}, chunk::{local_xyz_to_index, xz_to_index, Chunk}}};
+
+    use crate::{renderer::vertex::ModelVertex, world::meshinput::MeshInput};
+
+    use super::model_mesh_with;
+
+    //a single face on the front (+z) side of the block, hidden by a full block in front of it
+    fn front_face() -> Arc<BlockModel> {
+        Arc::new(BlockModel {
+            faces: vec![ModelFace {
+                positions: vec![[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 0.5, 1.0], [0.0, 0.5, 1.0]],
+                tex_coords: vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.5], [0.0, 0.5]],
+                normal: [0.0, 0.0, 1.0],
+                texture: 1,
+                cull: Some(BlockFace::Front)
+            }]
+        })
+    }
+
+    //a slab at 5, 5, 5 with the blocks around it
+    fn mesh(slab: WorldBlock, blocks: &[([u32; 3], WorldBlock)]) -> Vec<ModelVertex> {
+        let grid = (0..16).map(|y_slice| {
+            let mut slice = vec![WorldBlock::Air(15); 4096];
+            if y_slice == 0 {
+                slice[local_xyz_to_index(5, 5, 5) as usize] = slab;
+                for ([x, y, z], block) in blocks {
+                    slice[local_xyz_to_index(*x, *y, *z) as usize] = *block;
+                }
+            }
+            slice
+        }).collect();
+        let chunks = HashMap::from([(xz_to_index(0, 0), Arc::new(Chunk::from_blocks(Vector2::new(0, 0), grid)))]);
+
+        let (vertices, _, _) = model_mesh_with(&MeshInput::from_chunk_map(0, 0, 0, &chunks).unwrap(), |_| Some(front_face()));
+        vertices
+    }
+
+    #[test]
+    fn unturned_models_stay_put() {
+        let vertices = mesh(WorldBlock::Slab(15), &[]);
+        assert_eq!(vertices.len(), 4);
+        assert!(vertices.iter().all(|v| v.position[2] == 6.0 && v.normal == [0.0, 0.0, 1.0]));
+
+        assert!(mesh(WorldBlock::Slab(15), &[([5, 5, 6], WorldBlock::Stone(0))]).is_empty());
+    }
+
+    #[test]
+    fn models_follow_the_orientation_bits() {
+        //a quarter turn puts the front face on the right
+        let mut turned = WorldBlock::Slab(15);
+        set_block_orientation(&mut turned, 1, false);
+
+        let vertices = mesh(turned, &[]);
+        assert_eq!(vertices.len(), 4);
+        assert!(vertices.iter().all(|v| v.position[0] == 6.0 && v.normal == [1.0, 0.0, 0.0]));
+        assert!(vertices.iter().all(|v| (5.0..=5.5).contains(&v.position[1])));
+
+        //and it's culled by what's there now, not by what's in front
+        assert!(mesh(turned, &[([6, 5, 5], WorldBlock::Stone(0))]).is_empty());
+        assert_eq!(mesh(turned, &[([5, 5, 6], WorldBlock::Stone(0))]).len(), 4);
+
+        //upside down, the bottom half moves to the top and the face to the back
+        let mut flipped = WorldBlock::Slab(15);
+        set_block_orientation(&mut flipped, 0, true);
+
+        let vertices = mesh(flipped, &[]);
+        assert!(vertices.iter().all(|v| v.position[2] == 5.0 && v.normal == [0.0, 0.0, -1.0]));
+        assert!(vertices.iter().all(|v| (5.5..=6.0).contains(&v.position[1])));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{HashSet, VecDeque};
+
+use shared::world::{block::BlockFace, blockrepr::has_partial_transparency};
+
+use super::{meshinput::MeshInput, meshscheduler::SliceKey};
+
+//faces are indexed like BlockFace: top, bottom, right (+x), left (-x), front (+z), back (-z)
+const FACES: [BlockFace; 6] = [BlockFace::Top, BlockFace::Bottom, BlockFace::Right, BlockFace::Left, BlockFace::Front, BlockFace::Back];
+
+fn opposite(face: usize) -> usize {
+    face ^ 1
+}
+
+//which faces of a slice can see each other through non-opaque blocks. Bit a * 6 + b is set when face a connects to face b
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SliceConnectivity(u64);
+
+impl SliceConnectivity {
+    pub const OPEN: Self = Self((1 << 36) - 1);
+    pub const CLOSED: Self = Self(0);
+
+    pub fn connects(&self, a: BlockFace, b: BlockFace) -> bool {
+        self.connects_index(a as usize, b as usize)
+    }
+
+    fn connects_index(&self, a: usize, b: usize) -> bool {
+        self.0 & (1 << (a * 6 + b)) != 0
+    }
+
+    //every face in the mask sees every other one
+    fn connect_all(&mut self, faces: u8) {
+        for a in 0..6 {
+            if faces & (1 << a) == 0 {continue};
+            for b in 0..6 {
+                if faces & (1 << b) != 0 {
+                    self.0 |= 1 << (a * 6 + b);
+                }
+            }
+        }
+    }
+}
+
+//faces of the slice this local position touches
+fn boundary_faces(x: usize, y: usize, z: usize) -> u8 {
+    let mut faces = 0;
+    if y == 15 {faces |= 1 << BlockFace::Top as u8};
+    if y == 0 {faces |= 1 << BlockFace::Bottom as u8};
+    if x == 15 {faces |= 1 << BlockFace::Right as u8};
+    if x == 0 {faces |= 1 << BlockFace::Left as u8};
+    if z == 15 {faces |= 1 << BlockFace::Front as u8};
+    if z == 0 {faces |= 1 << BlockFace::Back as u8};
+    faces
+}
+
+//flood fills every pocket of non-opaque blocks and connects all the faces each pocket reaches
+pub fn compute_connectivity(input: &MeshInput) -> SliceConnectivity {
+    if input.fullair {
+        return SliceConnectivity::OPEN;
+    }
+
+    let index = |x: usize, y: usize, z: usize| (z * 16 + y) * 16 + x;
+
+    let mut open = vec![false; 4096];
+    for x in 0..16 {
+        for y in 0..16 {
+            for z in 0..16 {
+                open[index(x, y, z)] = input.get(x as i32, y as i32, z as i32).map_or(true, has_partial_transparency);
+            }
+        }
+    }
+
+    let mut connectivity = SliceConnectivity::CLOSED;
+    let mut visited = vec![false; 4096];
+    let mut stack = Vec::new();
+
+    for x in 0..16 {
+        for y in 0..16 {
+            for z in 0..16 {
+                //pockets that don't touch the boundary can't connect anything
+                if boundary_faces(x, y, z) == 0 {continue};
+                if !open[index(x, y, z)] || visited[index(x, y, z)] {continue};
+
+                let mut faces = 0;
+                visited[index(x, y, z)] = true;
+                stack.push((x, y, z));
+
+                while let Some((x, y, z)) = stack.pop() {
+                    faces |= boundary_faces(x, y, z);
+
+                    for face in FACES {
+                        let normal = face.normal();
+                        let (nx, ny, nz) = (x as i32 + normal.x, y as i32 + normal.y, z as i32 + normal.z);
+                        if !(0..16).contains(&nx) || !(0..16).contains(&ny) || !(0..16).contains(&nz) {continue};
+
+                        let n = index(nx as usize, ny as usize, nz as usize);
+                        if open[n] && !visited[n] {
+                            visited[n] = true;
+                            stack.push((nx as usize, ny as usize, nz as usize));
+                        }
+                    }
+                }
+
+                connectivity.connect_all(faces);
+                if connectivity == SliceConnectivity::OPEN {
+                    return connectivity;
+                }
+            }
+        }
+    }
+
+    connectivity
+}
+
+//slices that can be seen from the camera's slice, walking the connectivity graph breadth first. A slice is only entered
+//through a face that connects to the face it's left through, and the walk never turns back against a direction it
+//already moved in, which keeps it from creeping around corners into caves behind walls.
+//connectivity returns None for slices that aren't loaded, in_frustum prunes slices off screen.
+//None if the camera's own chunk isn't loaded
+pub fn visible_slices(
+    camera_slice: SliceKey,
+    connectivity: impl Fn(SliceKey) -> Option<SliceConnectivity>,
+    in_frustum: impl Fn(SliceKey) -> bool
+) -> Option<HashSet<SliceKey>> {
+    connectivity(camera_slice)?;
+
+    let mut visible = HashSet::from([camera_slice]);
+    //slice, face it was entered through, directions moved in so far
+    let mut queue = VecDeque::from([(camera_slice, None::<usize>, 0u8)]);
+
+    while let Some((key, entered, directions)) = queue.pop_front() {
+        let Some(current) = connectivity(key) else {continue};
+
+        for (exit, face) in FACES.iter().enumerate() {
+            if directions & (1 << opposite(exit)) != 0 {continue};
+            if entered.is_some_and(|entered| !current.connects_index(entered, exit)) {continue};
+
+            let normal = face.normal();
+            let y = key.2 as i32 + normal.y;
+            if !(0..16).contains(&y) {continue};
+
+            let next = (key.0 + normal.x, key.1 + normal.z, y as u32);
+            if visible.contains(&next) || connectivity(next).is_none() || !in_frustum(next) {continue};
+
+            visible.insert(next);
+            queue.push_back((next, Some(opposite(exit)), directions | (1 << exit)));
+        }
+    }
+
+    Some(visible)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use nalgebra::Vector2;
+    use shared::world::{block::BlockFace, blockrepr::WorldBlock, chunk::{local_xyz_to_index, xz_to_index, Chunk}};
+
+    use crate::world::{meshinput::MeshInput, meshscheduler::SliceKey};
+
+    use super::{compute_connectivity, visible_slices, SliceConnectivity};
+
+    //slice 0 of a chunk filled by the closure, every other slice air
+    fn connectivity_of(fill: impl Fn(u32, u32, u32) -> WorldBlock) -> SliceConnectivity {
+        let grid = (0..16).map(|y_slice| {
+            let mut slice = vec![WorldBlock::Air(0); 4096];
+            if y_slice == 0 {
+                for x in 0..16 {
+                    for y in 0..16 {
+                        for z in 0..16 {
+                            slice[local_xyz_to_index(x, y, z) as usize] = fill(x, y, z);
+                        }
+                    }
+                }
+            }
+            slice
+        }).collect();
+
+        let chunks = HashMap::from([(xz_to_index(0, 0), Arc::new(Chunk::from_blocks(Vector2::new(0, 0), grid)))]);
+        compute_connectivity(&MeshInput::from_chunk_map(0, 0, 0, &chunks).unwrap())
+    }
+
+    #[test]
+    fn air_and_stone_slices() {
+        assert_eq!(connectivity_of(|_, _, _| WorldBlock::Air(0)), SliceConnectivity::OPEN);
+        assert_eq!(connectivity_of(|_, _, _| WorldBlock::Stone(0)), SliceConnectivity::CLOSED);
+        //glass and leaves don't block sight
+        assert_eq!(connectivity_of(|_, _, _| WorldBlock::Glass(0)), SliceConnectivity::OPEN);
+    }
+
+    #[test]
+    fn tunnel_only_connects_its_ends() {
+        let connectivity = connectivity_of(|_, y, z| if y == 8 && z == 8 {WorldBlock::Air(0)} else {WorldBlock::Stone(0)});
+
+        assert!(connectivity.connects(BlockFace::Left, BlockFace::Right));
+        assert!(connectivity.connects(BlockFace::Right, BlockFace::Left));
+        assert!(!connectivity.connects(BlockFace::Left, BlockFace::Top));
+        assert!(!connectivity.connects(BlockFace::Front, BlockFace::Back));
+    }
+
+    #[test]
+    fn wall_splits_the_slice() {
+        //a stone wall at x = 8 with air on both sides
+        let connectivity = connectivity_of(|x, _, _| if x == 8 {WorldBlock::Stone(0)} else {WorldBlock::Air(0)});
+
+        assert!(!connectivity.connects(BlockFace::Left, BlockFace::Right));
+        assert!(connectivity.connects(BlockFace::Left, BlockFace::Top));
+        assert!(connectivity.connects(BlockFace::Right, BlockFace::Front));
+        assert!(connectivity.connects(BlockFace::Top, BlockFace::Bottom));
+    }
+
+    #[test]
+    fn flood_stops_at_closed_slices() {
+        //a row of slices along x at y 0, the one at x = 2 is solid
+        let graph: HashMap<SliceKey, SliceConnectivity> = (-1..6).map(|x| {
+            ((x, 0, 0), if x == 2 {SliceConnectivity::CLOSED} else {SliceConnectivity::OPEN})
+        }).collect();
+
+        let visible = visible_slices((0, 0, 0), |k| graph.get(&k).copied(), |_| true).unwrap();
+
+        assert!(visible.contains(&(1, 0, 0)));
+        assert!(visible.contains(&(-1, 0, 0)));
+        //the solid slice itself is visible, its far side isn't
+        assert!(visible.contains(&(2, 0, 0)));
+        assert!(!visible.contains(&(3, 0, 0)));
+    }
+
+    #[test]
+    fn flood_respects_the_frustum_and_loaded_chunks() {
+        let graph: HashMap<SliceKey, SliceConnectivity> = (-3..=3).map(|x| ((x, 0, 0), SliceConnectivity::OPEN)).collect();
+
+        let visible = visible_slices((0, 0, 0), |k| graph.get(&k).copied(), |k| k.0 >= 0).unwrap();
+        assert!(visible.contains(&(3, 0, 0)));
+        assert!(!visible.contains(&(-1, 0, 0)));
+
+        assert!(visible_slices((10, 0, 0), |k| graph.get(&k).copied(), |_| true).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+//position: slice relative, tex_coords: unscrolled, d1: (16 texture index, 3 face normal)
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) d1: u32,
+    @location(4) illumination: u32
+}
+
+struct ChunkData {
+    @location(3) position_sliced: vec3<i32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(4) diffuse_texture_index: u32,
+    @location(7) illumination: u32,
+    @location(8) worldpos: vec3<f32>
+};
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>
+}
+
+struct Fluid {
+    tint: vec4<f32>,
+    //seconds, scroll speed in blocks per second
+    time_scroll: vec4<f32>
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(2) @binding(0)
+var<uniform> fluid: Fluid;
+
+struct Sky {
+    inv_view_proj: mat4x4<f32>,
+    //xyz towards the sun, w is 1 in daylight and 0 at night
+    sun_direction: vec4<f32>,
+    zenith_color: vec4<f32>,
+    fog_color: vec4<f32>,
+    //start, end
+    fog_range: vec4<f32>
+}
+
+@group(3) @binding(0)
+var<uniform> sky: Sky;
+
+//skylight left at midnight
+const NIGHT_BRIGHTNESS: f32 = 0.25;
+
+//0 up close, 1 at the end of the fog. Chunks load in columns so only the horizontal distance counts
+fn fog_amount(worldpos: vec3<f32>) -> f32 {
+    let distance = length(worldpos.xz - camera.view_pos.xz);
+    return smoothstep(sky.fog_range.x, max(sky.fog_range.y, sky.fog_range.x + 0.001), distance);
+}
+
+@vertex
+fn vs_main(vertex: VertexInput, chunk_data: ChunkData) -> VertexOutput {
+    var out: VertexOutput;
+
+    let normalid = extractBits(vertex.d1, 16u, 3u);
+
+    var normal: vec3<f32>;
+
+    switch normalid {
+        case 0u: {
+            normal = vec3(0f, 1f, 0f);
+        }
+        case 1u: {
+            normal = vec3(0f, -1f, 0f);
+        }
+        case 2u: {
+            normal = vec3(1f, 0f, 0f);
+        }
+        case 3u: {
+            normal = vec3(-1f, 0f, 0f);
+        }
+        case 4u: {
+            normal = vec3(0f, 0f, 1f);
+        }
+        case 5u: {
+            normal = vec3(0f, 0f, -1f);
+        }
+        default: {
+            normal = vec3(0f, 1f, 0f);
+        }
+    }
+
+    let scroll = fluid.time_scroll.x * fluid.time_scroll.y;
+
+    //tops drift diagonally, sides flow downwards
+    if (normalid == 0u || normalid == 1u) {
+        out.tex_coords = vertex.tex_coords + vec2(scroll, scroll * 0.5);
+    } else {
+        out.tex_coords = vertex.tex_coords - vec2(0.0, scroll);
+    }
+
+    out.normal = normal;
+    out.diffuse_texture_index = extractBits(vertex.d1, 0u, 16u);
+    out.illumination = vertex.illumination;
+    out.worldpos = vec3<f32>(chunk_data.position_sliced * 16) + vertex.position;
+    out.clip_position = camera.view_proj * vec4<f32>(out.worldpos, 1.0);
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let diffuse_color = sample_diffuse(
+        in.diffuse_texture_index,
+        fract(in.tex_coords),
+        dpdxCoarse(in.tex_coords),
+        dpdyCoarse(in.tex_coords)
+    );
+
+    let sunlight = f32(extractBits(in.illumination, 24u, 4u));
+
+    let sunlight_factor = 0.1 + 0.9 * sunlight / 15.0 * mix(NIGHT_BRIGHTNESS, 1.0, sky.sun_direction.w);
+    let color = mix(diffuse_color.rgb * fluid.tint.rgb * sunlight_factor, sky.fog_color.rgb, fog_amount(in.worldpos));
+
+    return vec4(color, diffuse_color.a * fluid.tint.a);
+}
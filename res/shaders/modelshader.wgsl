@@ -0,0 +1,96 @@
+//position: slice relative, normal/tangent/bitangent: block space, textures: (diffuse, normal, emissive)
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(4) tangent: vec3<f32>,
+    @location(5) bitangent: vec3<f32>,
+    @location(6) diffuse_texture_index: u32,
+    @location(7) normal_texture_index: u32,
+    @location(8) emissive_texture_index: u32,
+    @location(9) illumination: u32
+}
+
+struct ChunkData {
+    @location(3) position_sliced: vec3<i32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(4) diffuse_texture_index: u32,
+    @location(7) illumination: u32,
+    @location(8) worldpos: vec3<f32>
+};
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+struct Sky {
+    inv_view_proj: mat4x4<f32>,
+    //xyz towards the sun, w is 1 in daylight and 0 at night
+    sun_direction: vec4<f32>,
+    zenith_color: vec4<f32>,
+    fog_color: vec4<f32>,
+    //start, end
+    fog_range: vec4<f32>
+}
+
+@group(2) @binding(0)
+var<uniform> sky: Sky;
+
+//same as the surface cutout pass
+const CUTOUT_ALPHA_THRESHOLD: f32 = 0.5;
+//same as the surface shader, what's left in shadow
+const AMBIENT_LIGHT: f32 = 0.6;
+
+//skylight left at midnight
+const NIGHT_BRIGHTNESS: f32 = 0.25;
+
+//0 up close, 1 at the end of the fog. Chunks load in columns so only the horizontal distance counts
+fn fog_amount(worldpos: vec3<f32>) -> f32 {
+    let distance = length(worldpos.xz - camera.view_pos.xz);
+    return smoothstep(sky.fog_range.x, max(sky.fog_range.y, sky.fog_range.x + 0.001), distance);
+}
+
+@vertex
+fn vs_main(vertex: VertexInput, chunk_data: ChunkData) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.tex_coords = vertex.tex_coords;
+    out.normal = vertex.normal;
+    out.diffuse_texture_index = vertex.diffuse_texture_index;
+    out.illumination = vertex.illumination;
+    out.worldpos = vec3<f32>(chunk_data.position_sliced * 16) + vertex.position;
+    out.clip_position = camera.view_proj * vec4<f32>(out.worldpos, 1.0);
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let diffuse_color = sample_diffuse(
+        in.diffuse_texture_index,
+        in.tex_coords,
+        dpdx(in.tex_coords),
+        dpdy(in.tex_coords)
+    );
+
+    if (diffuse_color.a < CUTOUT_ALPHA_THRESHOLD) {
+        discard;
+    }
+
+    let sunlight = f32(extractBits(in.illumination, 24u, 4u));
+
+    let sunlight_factor = 0.1 + 0.9 * sunlight / 15.0 * mix(NIGHT_BRIGHTNESS, 1.0, sky.sun_direction.w)
+        * mix(AMBIENT_LIGHT, 1.0, shadow_factor(in.worldpos, in.normal));
+
+    return vec4(mix(diffuse_color.rgb * sunlight_factor, sky.fog_color.rgb, fog_amount(in.worldpos)), 1.0);
+}
@@ -0,0 +1,42 @@
+//the 12 edges of the targeted block as a line list, the corners come from the vertex index
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct Outline {
+    //xyz is the block's minimum corner
+    position: vec4<f32>
+}
+
+@group(1) @binding(0)
+var<uniform> outline: Outline;
+
+//pushed out a little so the lines aren't hidden by the block's own faces
+const OUTLINE_INFLATE: f32 = 0.002;
+const OUTLINE_COLOR: vec3<f32> = vec3(0.02, 0.02, 0.02);
+
+//pairs of corners, each corner is a bitmask of x, y and z
+const EDGES: array<u32, 24> = array(
+    0u, 1u, 2u, 3u, 4u, 5u, 6u, 7u,
+    0u, 2u, 1u, 3u, 4u, 6u, 5u, 7u,
+    0u, 4u, 1u, 5u, 2u, 6u, 3u, 7u
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    var edges = EDGES;
+    let corner = edges[vertex_index];
+    let unit = vec3(f32(corner & 1u), f32((corner >> 1u) & 1u), f32((corner >> 2u) & 1u));
+    let position = outline.position.xyz + unit * (1.0 + 2.0 * OUTLINE_INFLATE) - vec3(OUTLINE_INFLATE);
+    return camera.view_proj * vec4(position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4(OUTLINE_COLOR, 1.0);
+}
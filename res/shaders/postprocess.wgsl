@@ -0,0 +1,106 @@
+//appended to blit.wgsl, which supplies vs_main and the r_color input of every pass
+
+@group(0) @binding(2)
+var bloom_color: texture_2d<f32>;
+
+struct PostSettings {
+    exposure: f32,
+    bloom_threshold: f32,
+    bloom_intensity: f32,
+    gamma: f32,
+    //PostDebugView
+    debug_view: u32,
+    tonemapping: u32,
+    bloom: u32,
+    //the target encodes srgb itself
+    srgb_target: u32
+}
+
+@group(0) @binding(3)
+var<uniform> settings: PostSettings;
+
+//what srgb targets are assumed to encode with
+const DISPLAY_GAMMA: f32 = 2.2;
+
+//9 tap gaussian, the center and one side
+const BLUR_WEIGHTS: array<f32, 5> = array(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
+
+//Krzysztof Narkowicz's fit of the ACES filmic curve
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3(0.0), vec3(1.0));
+}
+
+//keeps only what's brighter than the threshold, which in practice is the sun and emissive blocks
+@fragment
+fn fs_bright(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(r_color, r_sampler, vertex.tex_coords).rgb * settings.exposure;
+    let brightness = luminance(color);
+    let contribution = max(brightness - settings.bloom_threshold, 0.0) / max(brightness, 0.0001);
+    return vec4(color * contribution, 1.0);
+}
+
+fn blur(tex_coords: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let step = direction / vec2<f32>(textureDimensions(r_color));
+    //const arrays can only be indexed by constants
+    var weights = BLUR_WEIGHTS;
+    var color = textureSample(r_color, r_sampler, tex_coords).rgb * weights[0];
+    for (var i = 1; i < 5; i++) {
+        color += textureSample(r_color, r_sampler, tex_coords + step * f32(i)).rgb * weights[i];
+        color += textureSample(r_color, r_sampler, tex_coords - step * f32(i)).rgb * weights[i];
+    }
+    return vec4(color, 1.0);
+}
+
+@fragment
+fn fs_blur_horizontal(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(vertex.tex_coords, vec2(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_vertical(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(vertex.tex_coords, vec2(0.0, 1.0));
+}
+
+@fragment
+fn fs_composite(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(r_color, r_sampler, vertex.tex_coords).rgb * settings.exposure;
+    let bloom = textureSample(bloom_color, r_sampler, vertex.tex_coords).rgb;
+
+    var color: vec3<f32>;
+    switch settings.debug_view {
+        //the hdr scene alone, clipped instead of tonemapped
+        case 1u: {
+            color = min(scene, vec3(1.0));
+        }
+        case 2u: {
+            color = bloom;
+        }
+        case 3u: {
+            color = vec3(luminance(scene));
+        }
+        default: {
+            color = scene;
+            if (settings.bloom != 0u) {
+                color += bloom * settings.bloom_intensity;
+            }
+            if (settings.tonemapping != 0u) {
+                color = aces(color);
+            } else {
+                color = min(color, vec3(1.0));
+            }
+        }
+    }
+
+    //srgb targets apply DISPLAY_GAMMA on their own, only the difference to it is left to do
+    if (settings.srgb_target != 0u) {
+        color = pow(color, vec3(DISPLAY_GAMMA / settings.gamma));
+    } else {
+        color = pow(color, vec3(1.0 / settings.gamma));
+    }
+
+    return vec4(color, 1.0);
+}
@@ -0,0 +1,44 @@
+//shadows from the cascaded shadow map, see renderer/shadows.rs. Prepended to the shaders that receive shadows
+
+struct Shadows {
+    cascades: array<mat4x4<f32>, 3>,
+    //enabled, texel size
+    settings: vec4<f32>
+}
+
+@group(3) @binding(0)
+var<uniform> shadows: Shadows;
+@group(3) @binding(1)
+var shadow_map: texture_depth_2d_array;
+@group(3) @binding(2)
+var shadow_sampler: sampler_comparison;
+
+const CASCADE_COUNT: u32 = 3u;
+//the lookup moves off the surface along its normal, so faces at grazing angles don't shadow themselves
+const SHADOW_NORMAL_OFFSET: f32 = 0.1;
+
+//1 in sunlight, 0 in shadow. Uses the first cascade the position is in, 3x3 pcf softens the edges
+fn shadow_factor(worldpos: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if (shadows.settings.x == 0.0) {
+        return 1.0;
+    }
+
+    let position = vec4(worldpos + normal * SHADOW_NORMAL_OFFSET, 1.0);
+    for (var i = 0u; i < CASCADE_COUNT; i++) {
+        let clip = shadows.cascades[i] * position;
+        let uv = clip.xy * vec2(0.5, -0.5) + 0.5;
+        if (all(uv > vec2(0.0)) && all(uv < vec2(1.0)) && clip.z <= 1.0) {
+            var lit = 0.0;
+            for (var x = -1; x <= 1; x++) {
+                for (var y = -1; y <= 1; y++) {
+                    let offset = vec2(f32(x), f32(y)) * shadows.settings.y;
+                    lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, i, clip.z);
+                }
+            }
+            return lit / 9.0;
+        }
+    }
+
+    //past the last cascade
+    return 1.0;
+}
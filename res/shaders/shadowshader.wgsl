@@ -0,0 +1,21 @@
+//depth only, the surface geometry seen from the sun for one cascade
+
+struct VertexInput {
+    @location(0) d0: u32,
+}
+
+struct ChunkData {
+    @location(3) position_sliced: vec3<i32>,
+}
+
+@group(0) @binding(0)
+var<uniform> light_view_proj: mat4x4<f32>;
+
+@vertex
+fn vs_main(vertex: VertexInput, chunk_data: ChunkData) -> @builtin(position) vec4<f32> {
+    let x = i32(extractBits(vertex.d0, 0u, 5u));
+    let y = i32(extractBits(vertex.d0, 5u, 5u));
+    let z = i32(extractBits(vertex.d0, 10u, 5u));
+
+    return light_view_proj * vec4<f32>(vec3<f32>(chunk_data.position_sliced * 16 + vec3(x, y, z)), 1.0);
+}
@@ -0,0 +1,76 @@
+//drawn first as one triangle over the screen, the terrain covers it
+
+struct Sky {
+    inv_view_proj: mat4x4<f32>,
+    //xyz towards the sun, w is 1 in daylight and 0 at night
+    sun_direction: vec4<f32>,
+    zenith_color: vec4<f32>,
+    //also the colour of the horizon
+    fog_color: vec4<f32>,
+    //start, end
+    fog_range: vec4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> sky: Sky;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+//half the width of the sprites, in units of the distance to them
+const SUN_SIZE: f32 = 0.08;
+const MOON_SIZE: f32 = 0.06;
+const SUN_COLOR: vec3<f32> = vec3(1.0, 0.95, 0.8);
+const MOON_COLOR: vec3<f32> = vec3(0.8, 0.85, 0.95);
+const SUNSET_COLOR: vec3<f32> = vec3(1.0, 0.45, 0.2);
+//the sun is far brighter than the hdr target's white, that's what makes it bloom
+const SUN_INTENSITY: f32 = 4.0;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let ndc = vec2(f32(i32(vertex_index) / 2) * 4.0 - 1.0, f32(i32(vertex_index) & 1) * 4.0 - 1.0);
+    out.clip_position = vec4(ndc, 0.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+//1 inside a square sprite facing the viewer at direction, 0 outside
+fn sprite(view: vec3<f32>, direction: vec3<f32>, size: f32) -> f32 {
+    let facing = dot(view, direction);
+    if (facing <= 0.0) {
+        return 0.0;
+    }
+    //the sun moves in a plane tilted towards +z, so z is never parallel to it
+    let side = normalize(cross(direction, vec3(0.0, 0.0, 1.0)));
+    let up = cross(side, direction);
+    let projected = view / facing;
+    let offset = vec2(dot(projected, side), dot(projected, up));
+    return select(0.0, 1.0, max(abs(offset.x), abs(offset.y)) < size);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let near = sky.inv_view_proj * vec4(in.ndc, 0.0, 1.0);
+    let far = sky.inv_view_proj * vec4(in.ndc, 1.0, 1.0);
+    let view = normalize(far.xyz / far.w - near.xyz / near.w);
+
+    let sun = sky.sun_direction.xyz;
+    let daylight = sky.sun_direction.w;
+    let height = clamp(view.y, 0.0, 1.0);
+
+    var color = mix(sky.fog_color.rgb, sky.zenith_color.rgb, sqrt(height));
+
+    //the horizon glows around the sun while it rises and sets
+    let sunset = (1.0 - smoothstep(0.0, 0.35, abs(sun.y))) * pow(max(dot(view, sun), 0.0), 4.0) * (1.0 - height);
+    color = mix(color, SUNSET_COLOR, sunset * 0.6);
+
+    //below the horizon the sprites would be in the ground
+    let above = smoothstep(-0.02, 0.02, view.y);
+    color = mix(color, SUN_COLOR * SUN_INTENSITY, sprite(view, sun, SUN_SIZE) * above);
+    color = mix(color, MOON_COLOR, sprite(view, -sun, MOON_SIZE) * above * (1.0 - daylight * 0.7));
+
+    return vec4(color, 1.0);
+}
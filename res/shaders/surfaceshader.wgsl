@@ -1,5 +1,5 @@
 //chunk_position_orientation: u32 (4 posx, 4 posy, 4 posz, 3 face_normal, 2 uv_index)
-//textures: (8 diffuse, 8 normal, 8 emissive)
+//textures: (12 diffuse, 10 normal, 10 emissive)
 //separate binding for chunk position!
 
 struct VertexInput {
@@ -29,10 +29,9 @@ fn vs_main(vertex: VertexInput, chunk_data: ChunkData) -> VertexOutput {
 
     var normalid = extractBits(vertex.d0, 15u, 3u);
     var uvi = extractBits(vertex.d0, 18u, 2u);
-    var diffuse_texure_index = extractBits(vertex.d1, 0u, 16u);
-
-    var normal_texure_index = 0u;
-    var emissive_texure_index = 0u;
+    var diffuse_texure_index = extractBits(vertex.d1, 0u, 12u);
+    var normal_texure_index = extractBits(vertex.d1, 12u, 10u);
+    var emissive_texure_index = extractBits(vertex.d1, 22u, 10u);
 
     var uv = vec2(select(0.0, 1.0, uvi == 2 || uvi == 0), select(0.0, 1.0, uvi == 1 || uvi == 0));
 
@@ -95,24 +94,6 @@ struct VertexOutput {
     @location(8) worldpos: vec3<f32>
 };
 
-@group(0) @binding(0)
-var diffuse_texture_array: binding_array<texture_2d<f32>>;
-
-@group(0) @binding(1)
-var diffuse_sampler_array: binding_array<sampler>;
-
-@group(0) @binding(2)
-var normal_texture_array: binding_array<texture_2d<f32>>;
-
-@group(0) @binding(3)
-var normal_sampler_array: binding_array<sampler>;
-
-@group(0) @binding(4)
-var emissive_texture_array: binding_array<texture_2d<f32>>;
-
-@group(0) @binding(5)
-var emissive_sampler_array: binding_array<sampler>;
-
 struct Camera {
     view_pos: vec4<f32>,
     view_proj: mat4x4<f32>
@@ -121,29 +102,95 @@ struct Camera {
 @group(1) @binding(0)
 var<uniform> camera: Camera;
 
+struct Sky {
+    inv_view_proj: mat4x4<f32>,
+    //xyz towards the sun, w is 1 in daylight and 0 at night
+    sun_direction: vec4<f32>,
+    zenith_color: vec4<f32>,
+    fog_color: vec4<f32>,
+    //start, end
+    fog_range: vec4<f32>
+}
+
+@group(2) @binding(0)
+var<uniform> sky: Sky;
+
+//cutout fragments with less alpha than this are discarded
+const CUTOUT_ALPHA_THRESHOLD: f32 = 0.5;
+
+//share of the light that doesn't depend on the surface facing the sun
+const AMBIENT_LIGHT: f32 = 0.6;
+
+//skylight left at midnight
+const NIGHT_BRIGHTNESS: f32 = 0.25;
+//emission goes past 1 so the post processing picks it up as bloom
+const EMISSIVE_STRENGTH: f32 = 3.0;
+
+//0 up close, 1 at the end of the fog. Chunks load in columns so only the horizontal distance counts
+fn fog_amount(worldpos: vec3<f32>) -> f32 {
+    let distance = length(worldpos.xz - camera.view_pos.xz);
+    return smoothstep(sky.fog_range.x, max(sky.fog_range.y, sky.fog_range.x + 0.001), distance);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade_surface(in);
+}
+
+@fragment
+fn fs_cutout(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = shade_surface(in);
+    if (color.a < CUTOUT_ALPHA_THRESHOLD) {
+        discard;
+    }
+    return vec4(color.rgb, 1.0);
+}
+
+fn shade_surface(in: VertexOutput) -> vec4<f32> {
     var tileUV: vec2<f32>;
     let absNormal = abs(in.normal);
     var texCoord: vec2<f32>;
+    //world space directions u and v grow in, for the normal map
+    var tangent: vec3<f32>;
+    var bitangent: vec3<f32>;
 
     //check which axis it is
     if (absNormal.x > absNormal.y && absNormal.x > absNormal.z) {
         tileUV = vec2(1 - in.worldpos.z, 1 - in.worldpos.y);
-        texCoord = fract(tileUV);
+        tangent = vec3(0.0, 0.0, -1.0);
+        bitangent = vec3(0.0, -1.0, 0.0);
     } else if (absNormal.y > absNormal.x && absNormal.y > absNormal.z) {
         tileUV = vec2(in.worldpos.x, in.worldpos.z);
-        texCoord = fract(tileUV);
+        tangent = vec3(1.0, 0.0, 0.0);
+        bitangent = vec3(0.0, 0.0, 1.0);
     } else {
         tileUV = vec2(1 - in.worldpos.x, 1 - in.worldpos.y);
-        texCoord = fract(tileUV);
+        tangent = vec3(-1.0, 0.0, 0.0);
+        bitangent = vec3(0.0, -1.0, 0.0);
     }
+    texCoord = fract(tileUV);
 
-    let diffuse_color = textureSampleGrad(diffuse_texture_array[in.diffuse_texture_index], diffuse_sampler_array[in.diffuse_texture_index], texCoord, dpdxCoarse(tileUV), dpdyCoarse(tileUV)).rgba;
+    let ddx = dpdxCoarse(tileUV);
+    let ddy = dpdyCoarse(tileUV);
+
+    let diffuse_color = sample_diffuse(in.diffuse_texture_index, texCoord, ddx, ddy);
+    let normal_sample = sample_normal(in.normal_texture_index, texCoord, ddx, ddy).xyz;
+    let emissive_color = sample_emissive(in.emissive_texture_index, texCoord, ddx, ddy);
+
+    //green points up the image, which is against v
+    let tangent_normal = normal_sample * 2.0 - 1.0;
+    let normal = normalize(tangent * tangent_normal.x - bitangent * tangent_normal.y + in.normal * tangent_normal.z);
 
     let sunlight = f32(extractBits(in.illumination, 24u, 4u));
 
-    let sunlight_factor = 0.1 + 0.9 * sunlight / 15.0;
+    let sunlight_factor = 0.1 + 0.9 * sunlight / 15.0 * mix(NIGHT_BRIGHTNESS, 1.0, sky.sun_direction.w);
+    //the normal maps are lit by the sun, or the moon opposite it at night
+    let light_direction = select(-sky.sun_direction.xyz, sky.sun_direction.xyz, sky.sun_direction.y >= 0.0);
+    let facing = AMBIENT_LIGHT + (1.0 - AMBIENT_LIGHT) * max(dot(normal, light_direction), 0.0) * shadow_factor(in.worldpos, in.normal);
+
+    //emissive maps mask the diffuse color, which then glows regardless of the light around it. One mask can serve every ore
+    let emission = diffuse_color.rgb * emissive_color.rgb * emissive_color.a * EMISSIVE_STRENGTH;
+    let lit = diffuse_color.rgb * sunlight_factor * facing + emission;
 
-    return vec4(diffuse_color.rgb * sunlight_factor * 1.0, diffuse_color.a);
+    return vec4(mix(lit, sky.fog_color.rgb, fog_amount(in.worldpos)), diffuse_color.a);
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+//texture backend: every texture of a type is a layer of one texture_2d_array. Works everywhere. Each layer is
+//magnified with the filter from the texture manifest, the filter buffers say which layers are linear.
+//prepended to the block shaders, which only go through the sample_* functions
+
+@group(0) @binding(0)
+var diffuse_textures: texture_2d_array<f32>;
+
+@group(0) @binding(1)
+var texture_sampler: sampler;
+
+@group(0) @binding(2)
+var normal_textures: texture_2d_array<f32>;
+
+@group(0) @binding(3)
+var emissive_textures: texture_2d_array<f32>;
+
+//one entry per layer, 1 if it's linear. See texture_loader::layer_filter_flags
+@group(0) @binding(4)
+var<storage, read> diffuse_filters: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read> normal_filters: array<u32>;
+
+@group(0) @binding(6)
+var<storage, read> emissive_filters: array<u32>;
+
+//the sampler magnifies nearest, linear layers blend the four closest texels here instead.
+//textureSampleGrad doesn't need uniform control flow, so the branch is fine
+fn sample_layer(textures: texture_2d_array<f32>, linear: bool, index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    if !linear {
+        return textureSampleGrad(textures, texture_sampler, uv, index, ddx, ddy);
+    }
+
+    let size = vec2<f32>(textureDimensions(textures));
+    let texel = uv * size - 0.5;
+    let weight = fract(texel);
+    let base = (floor(texel) + 0.5) / size;
+    let step = 1.0 / size;
+
+    let a = textureSampleGrad(textures, texture_sampler, base, index, ddx, ddy);
+    let b = textureSampleGrad(textures, texture_sampler, base + vec2<f32>(step.x, 0.0), index, ddx, ddy);
+    let c = textureSampleGrad(textures, texture_sampler, base + vec2<f32>(0.0, step.y), index, ddx, ddy);
+    let d = textureSampleGrad(textures, texture_sampler, base + step, index, ddx, ddy);
+    return mix(mix(a, b, weight.x), mix(c, d, weight.x), weight.y);
+}
+fn sample_diffuse(index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    return sample_layer(diffuse_textures, diffuse_filters[index] == 1u, index, uv, ddx, ddy);
+}
+
+fn sample_normal(index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    return sample_layer(normal_textures, normal_filters[index] == 1u, index, uv, ddx, ddy);
+}
+
+fn sample_emissive(index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    return sample_layer(emissive_textures, emissive_filters[index] == 1u, index, uv, ddx, ddy);
+}
@@ -0,0 +1,32 @@
+//texture backend: one binding array entry (texture + sampler) per texture. Needs TEXTURE_BINDING_ARRAY and non-uniform indexing.
+//prepended to the block shaders, which only go through the sample_* functions
+
+@group(0) @binding(0)
+var diffuse_texture_array: binding_array<texture_2d<f32>>;
+
+@group(0) @binding(1)
+var diffuse_sampler_array: binding_array<sampler>;
+
+@group(0) @binding(2)
+var normal_texture_array: binding_array<texture_2d<f32>>;
+
+@group(0) @binding(3)
+var normal_sampler_array: binding_array<sampler>;
+
+@group(0) @binding(4)
+var emissive_texture_array: binding_array<texture_2d<f32>>;
+
+@group(0) @binding(5)
+var emissive_sampler_array: binding_array<sampler>;
+
+fn sample_diffuse(index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    return textureSampleGrad(diffuse_texture_array[index], diffuse_sampler_array[index], uv, ddx, ddy);
+}
+
+fn sample_normal(index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    return textureSampleGrad(normal_texture_array[index], normal_sampler_array[index], uv, ddx, ddy);
+}
+
+fn sample_emissive(index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    return textureSampleGrad(emissive_texture_array[index], emissive_sampler_array[index], uv, ddx, ddy);
+}
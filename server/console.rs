@@ -0,0 +1,64 @@
+use std::{io::stdin, sync::mpsc::{channel, Receiver}, thread};
+
+use nalgebra::Vector2;
+
+use crate::world::{chunktickets::{ChunkLevel, Ticket, TicketKind}, serverchunkmanager::ServerChunkManager};
+
+//lines typed into the server's terminal, read on their own thread so the tick loop never waits on them
+pub fn spawn_console() -> Receiver<String> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for line in stdin().lines() {
+            let Ok(line) = line else {break};
+            if sender.send(line).is_err() {break};
+        }
+    });
+    receiver
+}
+
+//ctrl+c, so the tick loop can save before the server exits
+pub fn spawn_ctrl_c() -> Receiver<()> {
+    let (sender, receiver) = channel();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let _ = sender.send(());
+        }
+    });
+    receiver
+}
+
+pub fn run_command(line: &str, chunk_manager: &mut ServerChunkManager) {
+    let args = line.split_whitespace().collect::<Vec<_>>();
+    let chunk_arg = || -> Option<Vector2<i32>> {
+        Some(Vector2::new(args.get(1)?.parse().ok()?, args.get(2)?.parse().ok()?))
+    };
+
+    match args.first().copied() {
+        //keeps a chunk loaded and ticking with nobody near it
+        Some("forceload") => {
+            let Some(chunk) = chunk_arg() else {
+                println!("usage: forceload <chunk x> <chunk z>");
+                return;
+            };
+            chunk_manager.tickets.add(TicketKind::Forced(chunk), Ticket {center: chunk, radius: 0, level: ChunkLevel::EntityTicking});
+            println!("forceloading {:?}", chunk);
+        },
+        Some("unforceload") => {
+            let Some(chunk) = chunk_arg() else {
+                println!("usage: unforceload <chunk x> <chunk z>");
+                return;
+            };
+            chunk_manager.tickets.remove(TicketKind::Forced(chunk));
+            println!("no longer forceloading {:?}", chunk);
+        },
+        Some("save") => chunk_manager.save_all(),
+        Some("chunks") => {
+            println!("{} chunks in memory, {} block ticking, {} entity ticking",
+                chunk_manager.chunks.len(),
+                chunk_manager.chunks_at_level(ChunkLevel::BlockTicking).len(),
+                chunk_manager.chunks_at_level(ChunkLevel::EntityTicking).len());
+        },
+        Some(command) => println!("unknown command {}", command),
+        None => {}
+    }
+}
@@ -1,29 +1,50 @@
-use std::{thread::sleep, time::Duration};
+use std::{path::PathBuf, thread::sleep, time::Duration};
 
+use console::{run_command, spawn_console, spawn_ctrl_c};
 use nalgebra::Vector2;
-use network::{server_event_handler::{self, ServerEventHandler}, servernet::ServerNetwork};
-use world::serverchunkmanager::ServerChunkManager;
+use network::{auth::authenticator_from_env, chunkstreamer::stream_chunks, server_event_handler::{self, broadcast_block_changes, ServerEventHandler}, servernet::ServerNetwork};
+use world::{chunkstorage::DEFAULT_SAVE_DIR, chunktickets::{ChunkLevel, Ticket, TicketKind}, serverchunkmanager::ServerChunkManager};
 use shared::network::containers::ServerToClientMessage;
 mod world;
 mod network;
+mod console;
+
+//chunks around 0, 0 at full level, loaded out to SPAWN_RADIUS + 2
+const SPAWN_RADIUS: u32 = 4;
 
 #[tokio::main]
 pub async fn main() {
-    let mut servernetwork = ServerNetwork::new();
+    let mut servernetwork = ServerNetwork::new(authenticator_from_env());
 
     // for i in 0..10 {
     //     sleep(Duration::from_secs(1));
     //     servernetwork.recv();
     // }
-    let mut chunkmanager = ServerChunkManager::new();
+    let mut chunkmanager = ServerChunkManager::new(Some(PathBuf::from(DEFAULT_SAVE_DIR)));
+
+    //spawn stays loaded with nobody online, and is ready before anyone joins
+    chunkmanager.tickets.add(TicketKind::Spawn, Ticket {center: Vector2::new(0, 0), radius: SPAWN_RADIUS, level: ChunkLevel::EntityTicking});
+    chunkmanager.load_ticketed_chunks(usize::MAX);
 
-    chunkmanager.generate_range_inclusive(-10, -10, 10, 10);
+    let console = spawn_console();
+    let ctrl_c = spawn_ctrl_c();
     
-    loop {
+    while ctrl_c.try_recv().is_err() {
         sleep(Duration::from_millis(33)); //1000ms/30ticks ~= 33
+        for line in console.try_iter() {
+            run_command(&line, &mut chunkmanager);
+        }
         let events = servernetwork.recv().await;
         ServerEventHandler::handle_network_messages(events, &mut chunkmanager, &mut servernetwork);
+        let changes = chunkmanager.tick();
+        broadcast_block_changes(&servernetwork, changes);
+        stream_chunks(&mut servernetwork, &chunkmanager);
     }
+
+    //changes only reach the disk when a chunk is evicted, the rest are saved here
+    chunkmanager.save_all();
+    //the listener thread is joined when the network drops, it has to stop first
+    servernetwork.handler.stop();
     
     // for (_, chunk) in &chunkmanager.chunks {
     //     let pos = chunk.position;
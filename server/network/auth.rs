@@ -0,0 +1,157 @@
+use std::{env, future::{ready, Future}, net::{IpAddr, SocketAddr, ToSocketAddrs}, pin::Pin};
+
+use reqwest::{StatusCode, Url};
+use shared::network::auth::{random_token, verify_challenge_answer, SRST};
+
+pub const DEFAULT_AUTH_HOST: &str = "http://localhost:8000";
+//where the auth service reaches this server to confirm users, unless STARFORGED_AUTH_CALLBACK says otherwise
+pub const DEFAULT_AUTH_CALLBACK: &str = "127.0.0.1:3043";
+
+pub enum JoinDecision {
+    //let them in, the server makes up their session token
+    Accept,
+    //the auth service made this session token. The client takes it to the service, which confirms the user with AuthConfirmedUser
+    AwaitService(String),
+    //the client has to answer this to get in
+    Challenge(String),
+    Reject(String)
+}
+
+pub type JoinFuture<'a> = Pin<Box<dyn Future<Output = JoinDecision> + 'a>>;
+
+//decides who gets to join
+pub trait Authenticator {
+    fn request_join<'a>(&'a self, username: &'a str) -> JoinFuture<'a>;
+
+    //the client's answer to a Challenge
+    fn check_answer(&self, _challenge: &str, _answer: &str) -> bool {
+        false
+    }
+
+    //whether an AuthConfirmedUser from this address is the auth service speaking
+    fn trusts_confirmation_from(&self, _address: SocketAddr) -> bool {
+        false
+    }
+}
+
+//the auth service vouches for users, they log in to it with their account
+pub struct HttpAuthenticator {
+    host: Url,
+    //the address this server tells the service to send confirmations to
+    callback: String
+}
+
+impl HttpAuthenticator {
+    pub fn new(host: &str, callback: String) -> Self {
+        Self {
+            host: Url::parse(host).unwrap(),
+            callback
+        }
+    }
+
+    fn host_ips(&self) -> Vec<IpAddr> {
+        let (Some(host), Some(port)) = (self.host.host_str(), self.host.port_or_known_default()) else {return Vec::new()};
+        (host, port).to_socket_addrs().map(|addrs| addrs.map(|a| a.ip()).collect()).unwrap_or_default()
+    }
+}
+
+impl Authenticator for HttpAuthenticator {
+    fn request_join<'a>(&'a self, username: &'a str) -> JoinFuture<'a> {
+        Box::pin(async move {
+            let req = reqwest::Client::new().get(self.host.join("servergetsessiontoken").unwrap())
+                .body(serde_json::to_string(&SRST {
+                    expected_username: username.to_string(),
+                    callback: self.callback.clone()
+                }).unwrap()).send().await;
+
+            match req {
+                Ok(res) if res.status() == StatusCode::OK => match res.text().await {
+                    Ok(token) => JoinDecision::AwaitService(token),
+                    Err(_) => JoinDecision::Reject("the authentication server sent a broken response".to_string())
+                },
+                Ok(res) => JoinDecision::Reject(format!("the authentication server refused ({})", res.status())),
+                Err(_) => {
+                    println!("AUTHENTICATION SERVER OFFLINE");
+                    JoinDecision::Reject("the authentication server is offline".to_string())
+                }
+            }
+        })
+    }
+
+    fn trusts_confirmation_from(&self, address: SocketAddr) -> bool {
+        self.host_ips().contains(&address.ip())
+    }
+}
+
+//anyone can join as anyone, for playing on a trusted lan or without internet
+pub struct OfflineAuthenticator;
+
+impl Authenticator for OfflineAuthenticator {
+    fn request_join<'a>(&'a self, _username: &'a str) -> JoinFuture<'a> {
+        Box::pin(ready(JoinDecision::Accept))
+    }
+}
+
+//anyone who knows the secret can join as anyone. The secret is never sent, clients answer a random challenge with it
+pub struct SharedSecretAuthenticator {
+    secret: String
+}
+
+impl SharedSecretAuthenticator {
+    pub fn new(secret: String) -> Self {
+        Self {
+            secret
+        }
+    }
+}
+
+impl Authenticator for SharedSecretAuthenticator {
+    fn request_join<'a>(&'a self, _username: &'a str) -> JoinFuture<'a> {
+        Box::pin(ready(JoinDecision::Challenge(random_token())))
+    }
+
+    fn check_answer(&self, challenge: &str, answer: &str) -> bool {
+        verify_challenge_answer(&self.secret, challenge, answer)
+    }
+}
+
+//STARFORGED_AUTH picks the mode: http (default, the service at STARFORGED_AUTH_HOST, which reaches this server at
+//STARFORGED_AUTH_CALLBACK), offline, or secret (STARFORGED_AUTH_SECRET)
+pub fn authenticator_from_env() -> Box<dyn Authenticator> {
+    let mode = env::var("STARFORGED_AUTH").unwrap_or_else(|_| "http".to_string());
+
+    match mode.as_str() {
+        "offline" => {
+            println!("offline mode, anyone can join as anyone");
+            Box::new(OfflineAuthenticator)
+        },
+        "secret" => {
+            let secret = env::var("STARFORGED_AUTH_SECRET").expect("STARFORGED_AUTH_SECRET has to be set in secret mode");
+            Box::new(SharedSecretAuthenticator::new(secret))
+        },
+        "http" => Box::new(HttpAuthenticator::new(
+            &env::var("STARFORGED_AUTH_HOST").unwrap_or_else(|_| DEFAULT_AUTH_HOST.to_string()),
+            env::var("STARFORGED_AUTH_CALLBACK").unwrap_or_else(|_| DEFAULT_AUTH_CALLBACK.to_string())
+        )),
+        _ => panic!("unknown STARFORGED_AUTH mode {}, expected http, offline or secret", mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+    use shared::network::auth::challenge_answer;
+
+    use super::{Authenticator, JoinDecision, SharedSecretAuthenticator};
+
+    #[test]
+    fn shared_secret_needs_the_right_answer() {
+        let authenticator = SharedSecretAuthenticator::new("hunter2".to_string());
+        let JoinDecision::Challenge(challenge) = authenticator.request_join("phxie").block_on() else {panic!("expected a challenge")};
+
+        assert!(authenticator.check_answer(&challenge, &challenge_answer("hunter2", &challenge)));
+        assert!(!authenticator.check_answer(&challenge, &challenge_answer("hunter3", &challenge)));
+        //answers don't carry over to another challenge
+        assert!(!authenticator.check_answer("something else", &challenge_answer("hunter2", &challenge)));
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use nalgebra::{Point3, Vector2};
+use shared::{network::{codec::encode, containers::{send_packet, NetworkMessage, Packet, ServerToClientMessage}, handshake::FEATURE_DEFLATE}, world::{chunk::xz_to_index, chunkcompress::compress_chunk}};
+
+use crate::world::{chunktickets::{ChunkLevel, Ticket}, serverchunkmanager::ServerChunkManager};
+
+use super::servernet::ServerNetwork;
+
+//chunks in each direction a player gets until it asks for something else
+pub const DEFAULT_VIEW_DISTANCE: u32 = 8;
+pub const MAX_VIEW_DISTANCE: u32 = 16;
+
+//chunk bytes put on the wire for each player per tick. The first chunk of a tick always goes, however big
+pub const BYTES_PER_TICK: usize = 128 * 1024;
+
+//every offset within radius chunks (square), walking outwards ring by ring so the nearest come first
+pub fn spiral_offsets(radius: i32) -> Vec<Vector2<i32>> {
+    let mut offsets = vec![Vector2::new(0, 0)];
+
+    for ring in 1..=radius {
+        //starts in a corner and goes around clockwise, each side stops one short of the next corner
+        let mut position = Vector2::new(-ring, -ring);
+        for direction in [Vector2::new(1, 0), Vector2::new(0, 1), Vector2::new(-1, 0), Vector2::new(0, -1)] {
+            for _ in 0..ring * 2 {
+                offsets.push(position);
+                position += direction;
+            }
+        }
+    }
+
+    offsets
+}
+
+//what one player has and still needs
+pub struct PlayerView {
+    pub center: Vector2<i32>,
+    //where the player's eye last was, None until it says
+    pub position: Option<Point3<f32>>,
+    pub view_distance: u32,
+    pub loaded: HashSet<Vector2<i32>>,
+    //in range and not sent yet, nearest first
+    pending: VecDeque<Vector2<i32>>,
+    //the client meshes what it has once the first fill is done
+    awaiting_initial: bool
+}
+
+impl PlayerView {
+    pub fn new() -> Self {
+        Self {
+            center: Vector2::new(0, 0),
+            position: None,
+            view_distance: DEFAULT_VIEW_DISTANCE,
+            loaded: HashSet::new(),
+            pending: VecDeque::new(),
+            awaiting_initial: false
+        }
+    }
+
+    pub fn in_range(&self, chunk: Vector2<i32>) -> bool {
+        let offset = chunk - self.center;
+        offset.x.abs().max(offset.y.abs()) <= self.view_distance as i32
+    }
+
+    //moves the view, returns the loaded chunks that are now out of range. They're forgotten right away
+    pub fn update(&mut self, center: Vector2<i32>, view_distance: u32) -> Vec<Vector2<i32>> {
+        self.center = center;
+        self.view_distance = view_distance.min(MAX_VIEW_DISTANCE);
+
+        let out_of_range = self.loaded.iter().copied().filter(|chunk| !self.in_range(*chunk)).collect::<Vec<_>>();
+        for chunk in &out_of_range {
+            self.loaded.remove(chunk);
+        }
+
+        self.pending = spiral_offsets(self.view_distance as i32).into_iter()
+            .map(|offset| center + offset)
+            .filter(|chunk| !self.loaded.contains(chunk))
+            .collect();
+
+        out_of_range
+    }
+
+    //a chunk the client asked for goes to the front, if the player can see it
+    pub fn request(&mut self, chunk: Vector2<i32>) {
+        if !self.in_range(chunk) || self.loaded.contains(&chunk) {return};
+        self.pending.retain(|pending| *pending != chunk);
+        self.pending.push_front(chunk);
+    }
+
+    //holds everything the player can see in memory, ticking all but the outer two rings
+    pub fn ticket(&self) -> Ticket {
+        Ticket {center: self.center, radius: self.view_distance.saturating_sub(2), level: ChunkLevel::EntityTicking}
+    }
+
+    pub fn begin_initial(&mut self) {
+        self.awaiting_initial = true;
+    }
+}
+
+//sends every player the nearest chunks it's missing, up to the budget
+pub fn stream_chunks(network: &mut ServerNetwork, chunk_manager: &ServerChunkManager) {
+    let handler = network.handler.clone();
+    let deflate = network.views.keys().map(|endpoint| (*endpoint, network.has_feature(*endpoint, FEATURE_DEFLATE))).collect::<HashMap<_, _>>();
+
+    for (endpoint, view) in network.views.iter_mut() {
+        let mut sent = 0;
+
+        while sent < BYTES_PER_TICK {
+            let Some(position) = view.pending.pop_front() else {break};
+            //still being generated, the player's ticket has it on the way
+            let Some(chunk) = chunk_manager.chunks.get(&xz_to_index(position.x, position.y)) else {
+                view.pending.push_front(position);
+                break;
+            };
+
+            let message = NetworkMessage::ServerToClient(ServerToClientMessage::ChunkProvided((position, compress_chunk(chunk))));
+            let frame = match encode(&Packet::new(message, None), deflate[endpoint]) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    println!("couldn't send chunk {:?}: {}", position, e);
+                    continue;
+                }
+            };
+            sent += frame.len();
+
+            handler.network().send(*endpoint, &frame);
+            view.loaded.insert(position);
+        }
+
+        if view.awaiting_initial && view.pending.is_empty() {
+            view.awaiting_initial = false;
+            send_packet(handler.network(), *endpoint, NetworkMessage::ServerToClient(ServerToClientMessage::ConcludeReceiveInitialChunks), None, deflate[endpoint]);
+            println!("Initial chunks sent");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use nalgebra::Vector2;
+
+    use super::{spiral_offsets, PlayerView};
+
+    #[test]
+    fn spiral_covers_the_square_nearest_first() {
+        let offsets = spiral_offsets(3);
+
+        assert_eq!(offsets.len(), 7 * 7);
+        assert_eq!(offsets.iter().collect::<HashSet<_>>().len(), offsets.len());
+        assert_eq!(offsets[0], Vector2::new(0, 0));
+
+        let rings = offsets.iter().map(|o| o.x.abs().max(o.y.abs())).collect::<Vec<_>>();
+        assert!(rings.windows(2).all(|w| w[0] <= w[1]));
+
+        //each step is to a neighbour within a ring
+        for pair in offsets[1..9].windows(2) {
+            assert_eq!((pair[1] - pair[0]).abs().sum(), 1);
+        }
+    }
+
+    #[test]
+    fn moving_unloads_what_left_the_view() {
+        let mut view = PlayerView::new();
+        view.update(Vector2::new(0, 0), 2);
+        view.loaded.extend(view.pending.drain(..));
+
+        let mut unloaded = view.update(Vector2::new(1, 0), 2);
+        unloaded.sort_by_key(|c| c.y);
+
+        assert_eq!(unloaded, (-2..=2).map(|z| Vector2::new(-2, z)).collect::<Vec<_>>());
+        //only the new column is left to send
+        assert_eq!(view.pending.len(), 5);
+        assert!(view.pending.iter().all(|c| c.x == 3));
+    }
+}
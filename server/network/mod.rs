@@ -1,2 +1,4 @@
 pub mod servernet;
-pub mod server_event_handler;
\ No newline at end of file
+pub mod server_event_handler;
+pub mod chunkstreamer;
+pub mod auth;
\ No newline at end of file
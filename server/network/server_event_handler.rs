@@ -1,8 +1,9 @@
-use message_io::network::NetworkController;
-use miniz_oxide::deflate::compress_to_vec;
-use shared::{network::containers::{ClientToServerMessage, NetworkMessage, ServerToClientMessage}, world::chunkcompress::compress_chunk};
+use std::collections::HashMap;
 
-use crate::world::serverchunkmanager::ServerChunkManager;
+use nalgebra::{Point3, Vector2, Vector3};
+use shared::{network::containers::{ClientToServerMessage, NetworkMessage, ServerToClientMessage}, world::{blockrepr::{is_placeable, WorldBlock}, chunk::chunk_of}};
+
+use crate::world::{chunktickets::TicketKind, serverchunkmanager::ServerChunkManager};
 
 use super::servernet::{Client, ServerNetwork, ServerNetworkMessage};
 
@@ -10,34 +11,120 @@ pub struct ServerEventHandler {
 
 }
 
-pub fn send_network_message(network: &NetworkController, client: &Client, data: &NetworkMessage) {
-    network.send(client.endpoint, &bincode::serialize(data).unwrap());
+//every client that has a chunk gets the changes inside it, batched into one message per chunk
+pub fn broadcast_block_changes(network: &ServerNetwork, changes: Vec<(Vector3<i32>, WorldBlock)>) {
+    let mut per_chunk: HashMap<Vector2<i32>, Vec<(Vector3<i32>, WorldBlock)>> = HashMap::new();
+    for (position, block) in changes {
+        per_chunk.entry(Vector2::new(position.x.div_euclid(16), position.z.div_euclid(16))).or_default().push((position, block));
+    }
+
+    for (chunk_position, changes) in per_chunk {
+        let message = if let [(position, block)] = changes[..] {
+            ServerToClientMessage::BlockChanged(position, block)
+        }
+        else {
+            ServerToClientMessage::MultiBlockChange(chunk_position, changes.iter().map(|(position, block)| {
+                (position.x.rem_euclid(16) as u8, position.y as u8, position.z.rem_euclid(16) as u8, *block)
+            }).collect())
+        };
+
+        for endpoint in network.chunk_watchers(chunk_position) {
+            network.send(endpoint, NetworkMessage::ServerToClient(message.clone()));
+        }
+    }
+}
+
+//the client drops what it can't see anymore, and the player's ticket follows it so the new chunks get loaded
+fn move_view(network: &mut ServerNetwork, chunk_manager: &mut ServerChunkManager, client: &Client, center: Vector2<i32>, view_distance: u32) {
+    let view = network.view_of(client);
+    let unloaded = view.update(center, view_distance);
+    chunk_manager.tickets.add(TicketKind::Player(client.endpoint), view.ticket());
+
+    for chunk in unloaded {
+        network.send(client.endpoint, NetworkMessage::ServerToClient(ServerToClientMessage::UnloadChunk(chunk)));
+    }
+}
+
+//how far the eye an edit was aimed from may be from where the player last said it was. The client sends its position
+//right before every edit, so this only has to cover float noise
+pub const MAX_EYE_OFFSET: f32 = 0.5;
+
+//the raycast is only repeated from where the player actually is, otherwise any eye could reach any block
+fn eye_is_plausible(network: &mut ServerNetwork, client: &Client, eye: Point3<f32>) -> bool {
+    network.view_of(client).position.is_some_and(|position| (position - eye).norm() <= MAX_EYE_OFFSET)
+}
+
+//a rejected change was already shown by the client that asked for it, this puts the block back
+fn revert_block_change(network: &ServerNetwork, client: &Client, chunk_manager: &ServerChunkManager, position: Vector3<i32>) {
+    if let Some(block) = chunk_manager.get_block(position) {
+        network.send(client.endpoint, NetworkMessage::ServerToClient(ServerToClientMessage::BlockChanged(position, block)));
+    }
 }
 
 impl ServerEventHandler {
     pub fn handle_network_messages(msgs: Vec<ServerNetworkMessage>, chunk_manager: &mut ServerChunkManager, network: &mut ServerNetwork) {
         for msg in msgs {
             match msg {
+                ServerNetworkMessage::Disconnected(endpoint) => {
+                    chunk_manager.tickets.remove(TicketKind::Player(endpoint));
+                },
                 ServerNetworkMessage::ClientToServer((client, msg)) => {
                     match msg { 
                         ClientToServerMessage::RequestInitialChunks => {
                             println!("Request Chunks");
-                            for (i, v) in &chunk_manager.chunks {
-                                
-                                let compressed = compress_chunk(v);
-                                let encoded = bincode::serialize(&compressed).unwrap();
-        
-                                let cmp = compress_to_vec(&encoded, 6);
-                                
-                                send_network_message(network.handler.network(), &client, &NetworkMessage::ServerToClient(ServerToClientMessage::ChunkProvided((v.position, cmp))));
+                            let view = network.view_of(&client);
+                            view.begin_initial();
+                            let (center, view_distance) = (view.center, view.view_distance);
+                            move_view(network, chunk_manager, &client, center, view_distance);
+                        },
+                        ClientToServerMessage::RequestChunk(position) => {
+                            network.view_of(&client).request(position);
+                        },
+                        ClientToServerMessage::PlayerPosition(position) => {
+                            let view = network.view_of(&client);
+                            view.position = Some(position);
+                            let (center, view_distance) = (chunk_of(position), view.view_distance);
+                            if center != view.center {
+                                move_view(network, chunk_manager, &client, center, view_distance);
                             }
-                            send_network_message(network.handler.network(), &client, &NetworkMessage::ServerToClient(ServerToClientMessage::ConcludeReceiveInitialChunks));
-                            println!("Chunks All Sent!");
                         },
-                        ClientToServerMessage::BreakBlock(pos) => {
-                            chunk_manager.break_block(pos.x, pos.z, pos.y as u32);
+                        ClientToServerMessage::SetViewDistance(view_distance) => {
+                            let center = network.view_of(&client).center;
+                            move_view(network, chunk_manager, &client, center, view_distance);
+                        },
+                        //the client's raycast has to agree with the server's from where the player is, which also keeps
+                        //the block within reach
+                        ClientToServerMessage::BreakBlock(pos, eye, direction) => {
+                            if eye_is_plausible(network, &client, eye) && chunk_manager.raycast(eye, direction).is_some_and(|hit| hit.block == pos) {
+                                let changes = chunk_manager.break_block(pos.x, pos.z, pos.y as u32);
+                                //nothing changed, e.g. the block is unbreakable, so the client's guess is undone too
+                                if changes.is_empty() {
+                                    revert_block_change(network, &client, chunk_manager, pos);
+                                }
+                                broadcast_block_changes(network, changes);
+                            }
+                            else {
+                                println!("{} can't reach {:?} to break it", client.username, pos);
+                                revert_block_change(network, &client, chunk_manager, pos);
+                            }
+                        },
+                        ClientToServerMessage::SetBlock(pos, block, eye, direction) => {
+                            if !is_placeable(&block) {
+                                println!("{} can't place {:?}", client.username, block);
+                                revert_block_change(network, &client, chunk_manager, pos);
+                            }
+                            else if eye_is_plausible(network, &client, eye) && chunk_manager.raycast(eye, direction).is_some_and(|hit| hit.face != Vector3::zeros() && hit.block + hit.face == pos) {
+                                let changes = chunk_manager.place_block(pos.x, pos.z, pos.y as u32, block);
+                                if changes.is_empty() {
+                                    revert_block_change(network, &client, chunk_manager, pos);
+                                }
+                                broadcast_block_changes(network, changes);
+                            }
+                            else {
+                                println!("{} can't reach {:?} to place a block", client.username, pos);
+                                revert_block_change(network, &client, chunk_manager, pos);
+                            }
                         },
-                        _ => {}
                     }
                 }
             }
@@ -1,22 +1,11 @@
-use std::{collections::HashMap, io::Write, net::{Ipv4Addr, SocketAddrV4}, str::FromStr, sync::{mpsc::{channel, Receiver}, Arc, RwLock}, thread};
+use std::{collections::HashMap, fmt::{self, Display}, net::{Ipv4Addr, SocketAddrV4}, str::FromStr, sync::{mpsc::{channel, Receiver}, Arc, RwLock}, thread};
 
-use bincode::{deserialize, serialize};
-use flate2::{write::ZlibEncoder, Compression};
 use message_io::{network::{Endpoint, NetEvent, ResourceId, Transport}, node::{self, NodeHandler, NodeListener, NodeTask}};
-use miniz_oxide::deflate::compress_to_vec;
+use nalgebra::Vector2;
 use pollster::FutureExt;
-use reqwest::{StatusCode, Url};
-use serde::Serialize;
-use shared::{network::containers::{AuthMessages, AuthenticatedPacket, ClientToServerMessage, NetworkMessage, Packet, ServerToClientMessage, UnauthenticatedPacket}, world::chunkcompress::compress_chunk};
+use shared::{network::{auth::random_token, codec::{CodecError, FrameReader}, containers::{send_packet, AuthMessages, ClientToServerMessage, NetworkMessage, Packet, ServerToClientMessage, UnauthenticatedPacket}, handshake::{negotiate, HandshakeMessage, FEATURE_DEFLATE, SUPPORTED_FEATURES}}, world::chunkcompress::compress_chunk};
 
-const SERVER_HOST: &str = "http://localhost:8000";
-const SERVER_HOST_SRST: &str = "http://localhost:8000/servergetsessiontoken";
-
-#[derive(Serialize)]
-pub struct SRST {
-    pub expected_username: String,
-    pub callback: String
-}
+use super::{auth::{Authenticator, JoinDecision}, chunkstreamer::PlayerView};
 
 #[derive(Clone)]
 pub struct Client {
@@ -25,171 +14,295 @@ pub struct Client {
 }
 
 pub enum ServerNetworkMessage {
-    ClientToServer((Client, ClientToServerMessage))
+    ClientToServer((Client, ClientToServerMessage)),
+    //the connection closed or was kicked, nothing about it is kept past this
+    Disconnected(Endpoint)
+}
+
+//what comes out of the listener thread for each connection
+enum ConnectionEvent {
+    Packet(Packet),
+    Malformed(CodecError),
+    Disconnected
+}
+
+//what a client did wrong. It's kicked with this as the reason
+#[derive(Debug)]
+pub enum ClientError {
+    Malformed(CodecError),
+    //an authenticated packet with a token that isn't this connection's
+    InvalidToken,
+    //a game message from a connection that hasn't joined
+    NotJoined,
+    //a message only the server or the auth service sends
+    Unexpected(String)
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Malformed(e) => write!(f, "sent a malformed packet ({})", e),
+            ClientError::InvalidToken => write!(f, "sent a session token that isn't theirs"),
+            ClientError::NotJoined => write!(f, "sent a game message before joining"),
+            ClientError::Unexpected(message) => write!(f, "sent {}, which clients don't send", message)
+        }
+    }
 }
 
+impl std::error::Error for ClientError {}
+
 pub struct ServerNetwork {
     pub handler: Arc<NodeHandler<()>>,
-    connected: HashMap<String, Endpoint>,
     task: NodeTask,
     id: ResourceId,
-    join_receiver: Receiver<(Endpoint, Packet)>,
-    queued_auth_responses: HashMap<String, (String, Endpoint)>,
+    join_receiver: Receiver<(Endpoint, ConnectionEvent)>,
+    authenticator: Box<dyn Authenticator>,
+    //username -> (session token or challenge, endpoint) for joins that are waiting on the auth service or an answer
+    pending_joins: HashMap<String, (String, Endpoint)>,
     valid_tokens: HashMap<String, Client>,
+    //where each client is and the chunks it was sent, changes inside them are sent on to it
+    pub views: HashMap<Endpoint, PlayerView>,
+    //the features agreed on with every connection that said hello
+    pub features: HashMap<Endpoint, Vec<String>>
 }
 
 impl ServerNetwork {
-    pub fn new() -> Self {
+    pub fn new(authenticator: Box<dyn Authenticator>) -> Self {
         let (handlerf, listener) = node::split::<()>();
 
         let (id, _) = handlerf.network().listen(Transport::Tcp, "0.0.0.0:3043").unwrap();
 
         let handlerarc = Arc::new(handlerf);
 
-        let (send, recv) = channel::<(Endpoint, Packet)>();
+        let (send, recv) = channel::<(Endpoint, ConnectionEvent)>();
 
         let handler = handlerarc.clone();
+        //a read can hold part of a packet or several, each connection puts its own back together
+        let mut readers: HashMap<Endpoint, FrameReader> = HashMap::new();
         let task = listener.for_each_async(move |event| match event.network() {
             NetEvent::Connected(_, _) => unreachable!(), // Used for explicit connections.
             NetEvent::Accepted(_endpoint, _listener) => println!("Client connected"), // Tcp or Ws
             NetEvent::Message(endpoint, data) => {
                 //handler.network().send(endpoint, data);
-                let msg = bincode::deserialize::<Packet>(data);
+                let reader = readers.entry(endpoint).or_default();
+                reader.push(data);
 
-                if let Ok(message) = msg {
-                    send.send((endpoint, message)).unwrap();
-                }
-                else {
-                    println!("Failed to deserialize message");
+                //the rest of the read isn't worth looking at after a bad frame, the client gets kicked for it.
+                //Sending only fails once the server is shutting down
+                loop {
+                    match reader.next_message::<Packet>() {
+                        Ok(Some(message)) => {let _ = send.send((endpoint, ConnectionEvent::Packet(message)));},
+                        Ok(None) => break,
+                        Err(e) => {
+                            readers.remove(&endpoint);
+                            let _ = send.send((endpoint, ConnectionEvent::Malformed(e)));
+                            break;
+                        }
+                    }
                 }
-                
-                
             },
-            NetEvent::Disconnected(_endpoint) => println!("Client disconnected"), //Tcp or Ws
+            NetEvent::Disconnected(endpoint) => {
+                readers.remove(&endpoint);
+                let _ = send.send((endpoint, ConnectionEvent::Disconnected));
+                println!("Client disconnected")
+            }, //Tcp or Ws
         });
         
         
         Self {
             handler: handlerarc,
-            connected: HashMap::new(),
             task,
             id,
             join_receiver: recv,
-            queued_auth_responses: HashMap::new(),
-            valid_tokens: HashMap::new()
+            authenticator,
+            pending_joins: HashMap::new(),
+            valid_tokens: HashMap::new(),
+            views: HashMap::new(),
+            features: HashMap::new()
         }
     }
 
-    pub fn validate_packet(&self, packet: &AuthenticatedPacket) -> Option<Client> {
-        if let Some(user) = self.valid_tokens.get(&packet.token) {
-            return Some(user.clone())
+    pub fn has_feature(&self, endpoint: Endpoint, feature: &str) -> bool {
+        self.features.get(&endpoint).is_some_and(|features| features.iter().any(|f| f == feature))
+    }
+
+    pub fn send(&self, endpoint: Endpoint, message: NetworkMessage) {
+        send_packet(self.handler.network(), endpoint, message, None, self.has_feature(endpoint, FEATURE_DEFLATE));
+    }
+
+    pub fn view_of(&mut self, client: &Client) -> &mut PlayerView {
+        self.views.entry(client.endpoint).or_insert_with(PlayerView::new)
+    }
+
+    //every client that has the chunk
+    pub fn chunk_watchers(&self, chunk_position: Vector2<i32>) -> Vec<Endpoint> {
+        self.views.iter()
+            .filter(|(_, view)| view.loaded.contains(&chunk_position))
+            .map(|(endpoint, _)| *endpoint)
+            .collect()
+    }
+
+    //a token only counts on the connection it was handed to
+    pub fn validate_packet(&self, endpoint: Endpoint, token: &str) -> Option<Client> {
+        self.valid_tokens.get(token).filter(|user| user.endpoint == endpoint).cloned()
+    }
+
+    fn username_of(&self, endpoint: Endpoint) -> Option<&str> {
+        self.valid_tokens.values().find(|client| client.endpoint == endpoint).map(|client| client.username.as_str())
+    }
+
+    //drops everything kept for a connection once it's gone
+    fn forget(&mut self, endpoint: Endpoint) -> ServerNetworkMessage {
+        if let Some(username) = self.username_of(endpoint) {
+            println!("{} left", username);
         }
-        None
+        self.valid_tokens.retain(|_, client| client.endpoint != endpoint);
+        self.pending_joins.retain(|_, (_, pending)| *pending != endpoint);
+        self.views.remove(&endpoint);
+        self.features.remove(&endpoint);
+        ServerNetworkMessage::Disconnected(endpoint)
     }
 
-    async fn handle_auth_message(&mut self, endpoint: Endpoint, msg: AuthMessages) -> Option<ServerNetworkMessage> {
+    //tells the client why and closes the connection. None if it's already closed
+    pub fn kick(&mut self, endpoint: Endpoint, reason: ClientError) -> Option<ServerNetworkMessage> {
+        self.handler.network().is_ready(endpoint.resource_id())?;
+
+        println!("kicked {}: {}", self.username_of(endpoint).map(String::from).unwrap_or_else(|| endpoint.addr().to_string()), reason);
+        self.send(endpoint, NetworkMessage::ServerToClient(ServerToClientMessage::Kicked(reason.to_string())));
+        self.handler.network().remove(endpoint.resource_id());
+        Some(self.forget(endpoint))
+    }
+
+    fn send_auth_message(&self, endpoint: Endpoint, msg: AuthMessages) {
+        self.send(endpoint, NetworkMessage::Auth(msg));
+    }
+
+    //the session token is theirs from now on
+    fn accept_join(&mut self, username: String, endpoint: Endpoint, token: String) {
+        println!("{} joined", username);
+        self.send_auth_message(endpoint, AuthMessages::JoinConfirmed(token.clone()));
+        self.valid_tokens.insert(token, Client {
+            username,
+            endpoint
+        });
+    }
+
+    fn reject_join(&self, username: &str, endpoint: Endpoint, reason: String) {
+        println!("{} can't join: {}", username, reason);
+        self.send_auth_message(endpoint, AuthMessages::JoinRejected(reason));
+    }
+
+    fn handle_handshake_message(&mut self, endpoint: Endpoint, msg: HandshakeMessage) -> Result<(), ClientError> {
+        let hello = match msg {
+            HandshakeMessage::Hello(hello) => hello,
+            HandshakeMessage::Accepted(_) | HandshakeMessage::Rejected(_) => return Err(ClientError::Unexpected(msg.to_string()))
+        };
+
+        let response = match negotiate(&hello, SUPPORTED_FEATURES) {
+            Ok(features) => {
+                self.features.insert(endpoint, features.clone());
+                HandshakeMessage::Accepted(features)
+            },
+            Err(reason) => {
+                println!("turned away a client on {} (protocol {}): {}", hello.game_version, hello.protocol_version, reason);
+                HandshakeMessage::Rejected(reason)
+            }
+        };
+        self.send(endpoint, NetworkMessage::Handshake(response));
+        Ok(())
+    }
+
+    async fn handle_auth_message(&mut self, endpoint: Endpoint, msg: AuthMessages) -> Result<(), ClientError> {
         match msg {
-            AuthMessages::AuthRequestUserCredentials(_) => None,
+            AuthMessages::ClientRequestJoin(username) => {
+                if !self.features.contains_key(&endpoint) {
+                    self.reject_join(&username, endpoint, "the client didn't say which version it is, it's probably out of date".to_string());
+                    return Ok(());
+                }
+                match self.authenticator.request_join(&username).await {
+                    JoinDecision::Accept => self.accept_join(username, endpoint, random_token()),
+                    JoinDecision::AwaitService(token) => {
+                        self.send_auth_message(endpoint, AuthMessages::AuthRequestUserCredentials(token.clone()));
+                        self.pending_joins.insert(username, (token, endpoint));
+                    },
+                    JoinDecision::Challenge(challenge) => {
+                        self.send_auth_message(endpoint, AuthMessages::AuthChallenge(challenge.clone()));
+                        self.pending_joins.insert(username, (challenge, endpoint));
+                    },
+                    JoinDecision::Reject(reason) => self.reject_join(&username, endpoint, reason)
+                }
+            },
             AuthMessages::AuthConfirmedUser(username) => {
-                //if &endpoint.addr().ip().to_string() != SERVER_HOST {return};
-                //TODO: READD FOR PROD^^
-                
-                let qar = self.queued_auth_responses.remove(&username).unwrap();
-                self.valid_tokens.insert(qar.0, Client {
-                    username: username.clone(),
-                    endpoint: qar.1.clone()
-                });
-                self.handler.network().send(qar.1, &bincode::serialize(&NetworkMessage::Auth(AuthMessages::JoinConfirmed)).unwrap());
-                None
+                //anyone else claiming to be the auth service is up to no good
+                if !self.authenticator.trusts_confirmation_from(endpoint.addr()) {
+                    return Err(ClientError::Unexpected(format!("a confirmation for {}", username)));
+                }
+                //they may have left while the service was confirming them
+                let Some((token, client_endpoint)) = self.pending_joins.remove(&username) else {return Ok(())};
+                self.accept_join(username, client_endpoint, token);
             },
-            AuthMessages::ClientRequestJoin(username) => {
-                let client = reqwest::Client::new();
-                let req = client.get(Url::parse(SERVER_HOST_SRST).unwrap())
-                    .body(serde_json::to_string(&SRST {
-                        expected_username: username.clone(),
-                        callback: "127.0.0.1:3043".to_string()
-                    }).unwrap()).send().await;
-
-                if let Ok(res) = req {
-                    if res.status() == StatusCode::OK {
-                        let code = res.text().await.unwrap();
-                        self.queued_auth_responses.insert(username, (code.clone(), endpoint));
-                        println!("WILL GIVE");
-                        self.handler.network().send(endpoint, &bincode::serialize(&NetworkMessage::Auth(AuthMessages::AuthRequestUserCredentials(code.clone()))).unwrap());
-                        self.handler.network().send(endpoint, &bincode::serialize(&NetworkMessage::Auth(AuthMessages::GiveUserSessionToken(code))).unwrap());
-                    }
-                    else {
-                        println!("BAD STATUS");
-                    }
+            AuthMessages::AuthChallengeResponse(username, answer) => {
+                //only the connection that was challenged can answer
+                let Some((challenge, challenged)) = self.pending_joins.get(&username).cloned() else {return Ok(())};
+                if challenged != endpoint {return Ok(())};
+                self.pending_joins.remove(&username);
+
+                if self.authenticator.check_answer(&challenge, &answer) {
+                    self.accept_join(username, endpoint, random_token());
                 }
                 else {
-                    println!("AUTHENTICATION SERVER OFFLINE");
+                    self.reject_join(&username, endpoint, "wrong server secret".to_string());
                 }
-                None
             },
-            AuthMessages::GiveUserSessionToken(_) => None,
-            AuthMessages::JoinConfirmed => None,
+            AuthMessages::AuthRequestUserCredentials(_) | AuthMessages::JoinConfirmed(_) |
+            AuthMessages::AuthChallenge(_) | AuthMessages::JoinRejected(_) => return Err(ClientError::Unexpected(msg.to_string()))
         }
+        Ok(())
     }
 
     pub async fn handle_client_to_server_message(&self, client: Client, msg: ClientToServerMessage) -> Option<ServerNetworkMessage> {
         Some(ServerNetworkMessage::ClientToServer((client, msg)))
     }
 
+    async fn handle_packet(&mut self, endpoint: Endpoint, packet: Packet) -> Result<Option<ServerNetworkMessage>, ClientError> {
+        let (msg, client) = match packet {
+            Packet::Authenticated(p) => {
+                let client = self.validate_packet(endpoint, &p.token).ok_or(ClientError::InvalidToken)?;
+                (p.data, Some(client))
+            },
+            Packet::Unauthenticated(p) => (p.data, None)
+        };
+
+        match msg {
+            NetworkMessage::Handshake(msg) => self.handle_handshake_message(endpoint, msg)?,
+            NetworkMessage::Auth(msg) => self.handle_auth_message(endpoint, msg).await?,
+            NetworkMessage::ServerToClient(msg) => return Err(ClientError::Unexpected(msg.to_string())),
+            NetworkMessage::ClientToServer(msg) => {
+                let client = client.ok_or(ClientError::NotJoined)?;
+                return Ok(self.handle_client_to_server_message(client, msg).await);
+            }
+        }
+        Ok(None)
+    }
+
     pub async fn recv(&mut self) -> Vec<ServerNetworkMessage> {
         let mut msgs: Vec<ServerNetworkMessage> = Vec::new();
-        loop {
-            if let Ok((endpoint, packet)) = self.join_receiver.try_recv() {
-                let message: (Option<NetworkMessage>, Option<Client>) = match packet {
-                    Packet::Authenticated(p) => {
-                        if let Some(client) = self.validate_packet(&p) {
-                            (Some(p.data), Some(client))
-                        }
-                        else {
-                            (None, None)
-                        }
-                    }
-                    Packet::Unauthenticated(p) => (Some(p.data), None)
-                };
-                if message.0.is_none() {continue}; //unauthenticated message.
-                
-                let msg = message.0.unwrap();
-
-                match msg {
-                    NetworkMessage::ServerToClient(msg) => {
-                        if message.1.is_none() {continue;}
-                    },
-                    NetworkMessage::Auth(msg) => {
-                        self.handle_auth_message(endpoint, msg).await;
-                    },
-                    NetworkMessage::ClientToServer(msg) => {
-                        if message.1.is_none() {continue;}
-                        println!("RC SV {}", msg);
-                        if let Some(res) = self.handle_client_to_server_message(message.1.unwrap(), msg).await {
-                            msgs.push(res);
-                        }
-                    },
-                }
-            }
-            else {
-                break;
+        while let Ok((endpoint, event)) = self.join_receiver.try_recv() {
+            //what a closed connection sent before it went would only bring its state back
+            let closed = self.handler.network().is_ready(endpoint.resource_id()).is_none();
+
+            let result = match event {
+                ConnectionEvent::Packet(_) | ConnectionEvent::Malformed(_) if closed => continue,
+                ConnectionEvent::Packet(packet) => self.handle_packet(endpoint, packet).await,
+                ConnectionEvent::Malformed(e) => Err(ClientError::Malformed(e)),
+                ConnectionEvent::Disconnected => Ok(Some(self.forget(endpoint)))
+            };
+
+            match result {
+                Ok(msg) => msgs.extend(msg),
+                Err(reason) => msgs.extend(self.kick(endpoint, reason))
             }
         }
         msgs
     }
-    //199480(new) 7240448(old) 133944(newer) 14595(newest!!! zlib!!!)
-    pub fn send_message_to(&self, userid: String, message: ServerToClientMessage) {
-        match message {
-            ServerToClientMessage::ChunkProvided(c) => {
-                let compressed = c.1;
-                let encoded = bincode::serialize(&compressed).unwrap();
-                
-                let b = compress_to_vec(&encoded, 6);
-                println!("LEN {}, {}", b.len(), encoded.len());
-                let u = self.handler.network().send(*self.connected.get("X").unwrap(), &b);
-                println!("{:?} {}", u, *self.connected.get("X").unwrap());
-            },
-            _ => {}
-        }
-    }
-}
\ No newline at end of file
+}
@@ -0,0 +1,31 @@
+use std::{fs, io, path::{Path, PathBuf}, sync::Arc};
+
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec};
+use nalgebra::Vector2;
+use shared::world::{chunk::Chunk, chunkcompress::{compress_chunk, decompress_chunk, CompressedChunk}};
+
+pub const DEFAULT_SAVE_DIR: &str = "world/chunks";
+
+//one file per chunk, stored the same way chunks are sent to clients
+fn chunk_path(dir: &Path, position: Vector2<i32>) -> PathBuf {
+    dir.join(format!("{}_{}.chunk", position.x, position.y))
+}
+
+pub fn save_chunk(dir: &Path, chunk: &Chunk) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let encoded = bincode::serialize(&compress_chunk(chunk)).unwrap();
+    fs::write(chunk_path(dir, chunk.position), compress_to_vec(&encoded, 6))
+}
+
+//None if the chunk was never saved. A file that doesn't decode is reported and treated the same
+pub fn load_chunk(dir: &Path, position: Vector2<i32>) -> Option<Chunk> {
+    let data = fs::read(chunk_path(dir, position)).ok()?;
+
+    let decoded = decompress_to_vec(&data).ok().and_then(|encoded| bincode::deserialize::<CompressedChunk>(&encoded).ok());
+    let Some(compressed) = decoded else {
+        println!("chunk file for {:?} is corrupt, generating it again", position);
+        return None;
+    };
+
+    Some(Arc::unwrap_or_clone(decompress_chunk(compressed)))
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use message_io::network::Endpoint;
+use nalgebra::Vector2;
+
+//how much of the game runs in a chunk, each level includes the ones below it
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ChunkLevel {
+    //kept in memory and sent to players, nothing runs
+    Loaded = 1,
+    BlockTicking = 2,
+    EntityTicking = 3
+}
+
+impl ChunkLevel {
+    fn from_i32(level: i32) -> Option<Self> {
+        match level {
+            1 => Some(ChunkLevel::Loaded),
+            2 => Some(ChunkLevel::BlockTicking),
+            3 => Some(ChunkLevel::EntityTicking),
+            _ => None
+        }
+    }
+}
+
+//who holds a ticket, holding a new one replaces the old
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TicketKind {
+    Player(Endpoint),
+    Spawn,
+    //from the forceload console command, one per chunk
+    Forced(Vector2<i32>)
+}
+
+//chunks within radius of the center get the level, then it falls by one for every ring past that until nothing is left
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ticket {
+    pub center: Vector2<i32>,
+    pub radius: u32,
+    pub level: ChunkLevel
+}
+
+impl Ticket {
+    //in rings around the center, so the corners are as far as the sides
+    pub fn distance(&self, chunk: Vector2<i32>) -> i32 {
+        let offset = chunk - self.center;
+        offset.x.abs().max(offset.y.abs())
+    }
+
+    pub fn level_at(&self, chunk: Vector2<i32>) -> Option<ChunkLevel> {
+        ChunkLevel::from_i32(self.level as i32 - (self.distance(chunk) - self.radius as i32).max(0))
+    }
+
+    //how far from the center the ticket reaches at all
+    pub fn reach(&self) -> i32 {
+        self.radius as i32 + self.level as i32 - 1
+    }
+}
+
+//the highest level any ticket gives each chunk, worked out again whenever the tickets change
+pub struct ChunkTickets {
+    tickets: HashMap<TicketKind, Ticket>,
+    levels: HashMap<Vector2<i32>, ChunkLevel>
+}
+
+impl ChunkTickets {
+    pub fn new() -> Self {
+        Self {
+            tickets: HashMap::new(),
+            levels: HashMap::new()
+        }
+    }
+
+    pub fn add(&mut self, kind: TicketKind, ticket: Ticket) {
+        if self.tickets.insert(kind, ticket) != Some(ticket) {
+            self.recompute();
+        }
+    }
+
+    pub fn remove(&mut self, kind: TicketKind) {
+        if self.tickets.remove(&kind).is_some() {
+            self.recompute();
+        }
+    }
+
+    //None if no ticket reaches the chunk, it's on its way out
+    pub fn level(&self, chunk: Vector2<i32>) -> Option<ChunkLevel> {
+        self.levels.get(&chunk).copied()
+    }
+
+    //how many rings the chunk is from the closest ticket's center, None without any tickets
+    pub fn nearest_center_distance(&self, chunk: Vector2<i32>) -> Option<i32> {
+        self.tickets.values().map(|ticket| ticket.distance(chunk)).min()
+    }
+
+    //every chunk that should be in memory
+    pub fn ticketed(&self) -> impl Iterator<Item = (Vector2<i32>, ChunkLevel)> + '_ {
+        self.levels.iter().map(|(chunk, level)| (*chunk, *level))
+    }
+
+    fn recompute(&mut self) {
+        self.levels.clear();
+
+        for ticket in self.tickets.values() {
+            let reach = ticket.reach();
+            for x in -reach..=reach {
+                for z in -reach..=reach {
+                    let chunk = ticket.center + Vector2::new(x, z);
+                    let Some(level) = ticket.level_at(chunk) else {continue};
+
+                    let entry = self.levels.entry(chunk).or_insert(level);
+                    *entry = (*entry).max(level);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::{ChunkLevel, ChunkTickets, Ticket, TicketKind};
+
+    #[test]
+    fn levels_fall_off_past_the_radius_and_overlaps_take_the_highest() {
+        let mut tickets = ChunkTickets::new();
+        tickets.add(TicketKind::Spawn, Ticket {center: Vector2::new(0, 0), radius: 1, level: ChunkLevel::EntityTicking});
+
+        assert_eq!(tickets.level(Vector2::new(1, -1)), Some(ChunkLevel::EntityTicking));
+        assert_eq!(tickets.level(Vector2::new(2, 0)), Some(ChunkLevel::BlockTicking));
+        assert_eq!(tickets.level(Vector2::new(-3, 3)), Some(ChunkLevel::Loaded));
+        assert_eq!(tickets.level(Vector2::new(4, 0)), None);
+        assert_eq!(tickets.ticketed().count(), 7 * 7);
+
+        tickets.add(TicketKind::Forced(Vector2::new(4, 0)), Ticket {center: Vector2::new(4, 0), radius: 0, level: ChunkLevel::BlockTicking});
+        assert_eq!(tickets.level(Vector2::new(3, 0)), Some(ChunkLevel::Loaded));
+        assert_eq!(tickets.level(Vector2::new(4, 0)), Some(ChunkLevel::BlockTicking));
+
+        tickets.remove(TicketKind::Spawn);
+        assert_eq!(tickets.level(Vector2::new(0, 0)), None);
+        assert_eq!(tickets.ticketed().count(), 3 * 3);
+    }
+
+    #[test]
+    fn distance_is_to_the_closest_center() {
+        let mut tickets = ChunkTickets::new();
+        assert_eq!(tickets.nearest_center_distance(Vector2::new(0, 0)), None);
+
+        tickets.add(TicketKind::Spawn, Ticket {center: Vector2::new(0, 0), radius: 4, level: ChunkLevel::Loaded});
+        tickets.add(TicketKind::Forced(Vector2::new(10, 0)), Ticket {center: Vector2::new(10, 0), radius: 0, level: ChunkLevel::Loaded});
+        assert_eq!(tickets.nearest_center_distance(Vector2::new(-3, 3)), Some(3));
+        assert_eq!(tickets.nearest_center_distance(Vector2::new(8, -1)), Some(2));
+    }
+}
@@ -1 +1,3 @@
-pub mod serverchunkmanager;
\ No newline at end of file
+pub mod serverchunkmanager;
+pub mod chunktickets;
+pub mod chunkstorage;
\ No newline at end of file
@@ -1,59 +1,281 @@
-use std::{collections::{HashMap, VecDeque}, hash::Hash, sync::Arc, thread::sleep, time::Duration};
+use std::{collections::{HashMap, HashSet, VecDeque}, hash::Hash, path::PathBuf, sync::Arc, thread::sleep, time::Duration};
 
-use nalgebra::{Vector2, Vector3};
+use nalgebra::{Point3, Vector2, Vector3};
 use noise::{OpenSimplex, Perlin};
-use shared::world::{blockrepr::{get_block_light, has_partial_transparency, is_unbreakable, set_block_light, WorldBlock}, chunk::{get_block_at_absolute, local_xyz_to_index, xz_to_index, Chunk}};
+use shared::world::{blockrepr::{get_block_light, has_gravity, has_partial_transparency, is_replaceable, is_unbreakable, set_block_light, WorldBlock}, chunk::{get_block_at_absolute, local_xyz_to_index, xz_to_index, Chunk}, raycast::{raycast, RaycastHit, REACH}};
+
+use super::{chunkstorage::{load_chunk, save_chunk}, chunktickets::{ChunkLevel, ChunkTickets}};
+
+//light dims by one per block from 15, a block change can't reach further than this
+const LIGHT_UPDATE_RADIUS: i32 = 15;
+
+//chunks loaded or generated per tick, the ones closest to a ticket's center first
+pub const LOADS_PER_TICK: usize = 4;
+//how long a chunk stays in memory after losing its last ticket, so walking back and forth doesn't reload it (30 seconds)
+pub const EVICT_AFTER_TICKS: u64 = 30 * 30;
+//block updates run per tick, the rest wait for the next one
+const BLOCK_UPDATES_PER_TICK: usize = 64;
 
 pub struct ServerChunkManager {
-    pub chunks: HashMap<u32, Chunk>
+    pub chunks: HashMap<u32, Chunk>,
+    pub tickets: ChunkTickets,
+    noisegen: OpenSimplex,
+    //evicted chunks are saved here, with None they're only ever generated
+    save_dir: Option<PathBuf>,
+    //chunks that changed since they were loaded. Untouched ones generate the same again and aren't saved
+    dirty: HashSet<u32>,
+    //the tick each chunk lost its last ticket on
+    unticketed_since: HashMap<u32, u64>,
+    //changed blocks and the ones above them, updated once their chunk is at least BlockTicking
+    pending_updates: VecDeque<Vector3<i32>>,
+    current_tick: u64
 }
 
 impl ServerChunkManager {
-    pub fn new() -> Self {
+    pub fn new(save_dir: Option<PathBuf>) -> Self {
         Self {
-            chunks: HashMap::new()
+            chunks: HashMap::new(),
+            tickets: ChunkTickets::new(),
+            noisegen: OpenSimplex::new(52223),
+            save_dir,
+            dirty: HashSet::new(),
+            unticketed_since: HashMap::new(),
+            pending_updates: VecDeque::new(),
+            current_tick: 0
+        }
+    }
+
+    //returns the blocks the block updates changed
+    pub fn tick(&mut self) -> Vec<(Vector3<i32>, WorldBlock)> {
+        self.current_tick += 1;
+        self.load_ticketed_chunks(LOADS_PER_TICK);
+        let changes = self.update_blocks(BLOCK_UPDATES_PER_TICK);
+        self.evict_unticketed_chunks();
+        changes
+    }
+
+    //runs up to budget of the pending updates. Ones in chunks below BlockTicking stay queued, ones in unloaded chunks are dropped
+    fn update_blocks(&mut self, budget: usize) -> Vec<(Vector3<i32>, WorldBlock)> {
+        let mut changes = Vec::new();
+        let mut waiting = VecDeque::new();
+        let mut ran = 0;
+
+        while ran < budget {
+            let Some(position) = self.pending_updates.pop_front() else {break};
+            let chunk = Vector2::new(position.x.div_euclid(16), position.z.div_euclid(16));
+            if !self.chunks.contains_key(&xz_to_index(chunk.x, chunk.y)) {continue};
+
+            if self.tickets.level(chunk).is_some_and(|level| level >= ChunkLevel::BlockTicking) {
+                changes.extend(self.update_block(position));
+                ran += 1;
+            }
+            else {
+                waiting.push_back(position);
+            }
+        }
+
+        self.pending_updates.extend(waiting);
+        changes
+    }
+
+    fn update_block(&mut self, position: Vector3<i32>) -> Vec<(Vector3<i32>, WorldBlock)> {
+        let (Some(block), Some(below)) = (self.get_block(position), self.get_block(position - Vector3::y())) else {return Vec::new()};
+
+        //falls a block per update, the change queues the next one
+        if has_gravity(&block) && is_replaceable(&below) {
+            let mut changes = self.change_block(position, below);
+            changes.extend(self.change_block(position - Vector3::y(), block));
+            return changes;
         }
+        Vec::new()
     }
 
-    pub fn generate_range_inclusive(&mut self, start_x: i32, start_z: i32, end_x: i32, end_z: i32) {
-        let noisegen = OpenSimplex::new(52223);
-        for x in start_x..=end_x {
-            for z in start_z..=end_z {
-                let chunk = Chunk::new(Vector2::new(x, z), noisegen, &mut HashMap::new());
-                self.chunks.insert(xz_to_index(x, z), chunk);
+    //loads or generates up to budget of the missing chunks that hold a ticket, returns how many are still missing.
+    //nearest to a ticket's center first, so players see the chunks around them before the edge of their view
+    pub fn load_ticketed_chunks(&mut self, budget: usize) -> usize {
+        let mut missing = self.tickets.ticketed()
+            .filter(|(position, _)| !self.chunks.contains_key(&xz_to_index(position.x, position.y)))
+            .collect::<Vec<_>>();
+        missing.sort_by_key(|(position, level)| (self.tickets.nearest_center_distance(*position), std::cmp::Reverse(*level), position.x, position.y));
+
+        let mut generated = Vec::new();
+        for (position, _) in missing.iter().take(budget) {
+            let saved = self.save_dir.as_ref().and_then(|dir| load_chunk(dir, *position));
+            let chunk = saved.unwrap_or_else(|| {
+                generated.push(*position);
+                Chunk::new(*position, self.noisegen, &mut HashMap::new())
+            });
+            self.chunks.insert(xz_to_index(position.x, position.y), chunk);
+        }
+
+        //saved chunks keep their light, new ones are lit once they're all in so light crosses between them
+        for position in generated {
+            self.calculate_initial_lighting(position);
+        }
+
+        missing.len().saturating_sub(budget)
+    }
+
+    //saves and drops every chunk that's had no ticket for EVICT_AFTER_TICKS
+    fn evict_unticketed_chunks(&mut self) {
+        let mut evict = Vec::new();
+        for (index, chunk) in &self.chunks {
+            if self.tickets.level(chunk.position).is_some() {
+                self.unticketed_since.remove(index);
+                continue;
+            }
+
+            let since = *self.unticketed_since.entry(*index).or_insert(self.current_tick);
+            if self.current_tick - since >= EVICT_AFTER_TICKS {
+                evict.push(*index);
             }
         }
 
-        for x in start_x..=end_x {
-            for z in start_z..=end_z {
-                self.calculate_initial_lighting(Vector2::new(x, z));
+        for index in evict {
+            //changes that failed to save keep the chunk around, it's tried again after another EVICT_AFTER_TICKS
+            if self.save_dir.is_some() && !self.save_if_dirty(index) {
+                self.unticketed_since.insert(index, self.current_tick);
+                continue;
             }
+            //without a save dir the changes are gone with the chunk
+            self.dirty.remove(&index);
+            self.chunks.remove(&index);
+            self.unticketed_since.remove(&index);
         }
     }
 
-    pub fn break_block(&mut self, x: i32, z: i32, y: u32) {
-        let xr = x.rem_euclid(16) as u32;
-        let zr = z.rem_euclid(16) as u32;
-        let chunk = self.chunks.get_mut(&xz_to_index(x.div_euclid(16), z.div_euclid(16))).unwrap();
-        let block = chunk.get_block_at(xr as u32, y, zr as u32);
+    //false if the chunk still has changes that aren't on disk
+    fn save_if_dirty(&mut self, index: u32) -> bool {
+        if !self.dirty.contains(&index) {return true};
+        let (Some(dir), Some(chunk)) = (&self.save_dir, self.chunks.get(&index)) else {return false};
 
-        if is_unbreakable(block) {
-            return;
+        match save_chunk(dir, chunk) {
+            Ok(()) => {self.dirty.remove(&index); true},
+            Err(e) => {
+                println!("failed to save chunk {:?}: {}", chunk.position, e);
+                false
+            }
+        }
+    }
+
+    //saves every changed chunk without dropping anything
+    pub fn save_all(&mut self) {
+        let dirty = self.dirty.iter().copied().collect::<Vec<_>>();
+        for index in &dirty {
+            self.save_if_dirty(*index);
         }
+        println!("saved {} chunks", dirty.len() - self.dirty.len());
+    }
+
+    //loaded chunks at or above the level, where block and entity updates are allowed to run
+    pub fn chunks_at_level(&self, level: ChunkLevel) -> Vec<Vector2<i32>> {
+        self.chunks.values()
+            .map(|chunk| chunk.position)
+            .filter(|position| self.tickets.level(*position).is_some_and(|l| l >= level))
+            .collect()
+    }
 
-        chunk.grid[y as usize / 16][local_xyz_to_index(xr, y % 16, zr) as usize] = WorldBlock::Air(0);
+    //None outside the world or in chunks that aren't generated
+    pub fn get_block(&self, position: Vector3<i32>) -> Option<WorldBlock> {
+        if !(0..=255).contains(&position.y) {return None};
+        self.chunks.get(&xz_to_index(position.x.div_euclid(16), position.z.div_euclid(16)))
+            .map(|chunk| *chunk.get_block_at(position.x.rem_euclid(16) as u32, position.y as u32, position.z.rem_euclid(16) as u32))
     }
-    pub fn place_block(&mut self, x: i32, z: i32, y: u32, toplace: WorldBlock) {
-        let xr = x.rem_euclid(16) as u32;
-        let zr = z.rem_euclid(16) as u32;
-        let chunk = self.chunks.get_mut(&xz_to_index(x.div_euclid(16), z.div_euclid(16))).unwrap();
-        let block = chunk.get_block_at(xr as u32, y, zr as u32);
 
-        if is_unbreakable(block) {
-            return;
+    //the block a player looking along direction from eye would target
+    pub fn raycast(&self, eye: Point3<f32>, direction: Vector3<f32>) -> Option<RaycastHit> {
+        raycast(eye, direction, REACH, |position| self.get_block(position))
+    }
+
+    //both return every block that changed, including the ones whose light changed, and nothing if the change isn't allowed
+    pub fn break_block(&mut self, x: i32, z: i32, y: u32) -> Vec<(Vector3<i32>, WorldBlock)> {
+        let position = Vector3::new(x, y as i32, z);
+        let Some(block) = self.get_block(position) else {return Vec::new()};
+
+        if is_unbreakable(&block) {
+            return Vec::new();
         }
 
-        chunk.grid[y as usize / 16][local_xyz_to_index(xr, y % 16, zr) as usize] = toplace;
+        self.change_block(position, WorldBlock::Air(0))
+    }
+    pub fn place_block(&mut self, x: i32, z: i32, y: u32, toplace: WorldBlock) -> Vec<(Vector3<i32>, WorldBlock)> {
+        let position = Vector3::new(x, y as i32, z);
+        let Some(block) = self.get_block(position) else {return Vec::new()};
+
+        //only air and fluids make room for a block
+        if !is_replaceable(&block) {
+            return Vec::new();
+        }
+
+        self.change_block(position, toplace)
+    }
+
+    //sets the block and relights the columns around it. Returns it and every block whose light changed
+    fn change_block(&mut self, position: Vector3<i32>, block: WorldBlock) -> Vec<(Vector3<i32>, WorldBlock)> {
+        let chunk = self.chunks.get_mut(&xz_to_index(position.x.div_euclid(16), position.z.div_euclid(16))).unwrap();
+        chunk.set_block_at(position.x.rem_euclid(16) as u32, position.y as u32, position.z.rem_euclid(16) as u32, block);
+
+        let mut changed = self.relight_around(position);
+        if !changed.contains(&position) {
+            changed.push(position);
+        }
+
+        let changes = changed.into_iter()
+            .map(|position| (position, self.get_block(position).unwrap()))
+            .collect::<Vec<_>>();
+        for (position, _) in &changes {
+            self.dirty.insert(xz_to_index(position.x.div_euclid(16), position.z.div_euclid(16)));
+        }
+        self.pending_updates.push_back(position);
+        self.pending_updates.push_back(position + Vector3::y());
+        changes
+    }
+
+    //light spreads less than LIGHT_UPDATE_RADIUS blocks, so only columns that close to position can change. They're
+    //cleared and lit again from the sky, then whatever light reaches them from the columns around flows back in.
+    //Returns the blocks that ended up with different light
+    fn relight_around(&mut self, position: Vector3<i32>) -> Vec<Vector3<i32>> {
+        let mut queue: VecDeque<Vector3<i32>> = VecDeque::new();
+        let mut before = HashMap::new();
+        let range = -LIGHT_UPDATE_RADIUS - 1..=LIGHT_UPDATE_RADIUS + 1;
+
+        for dx in range.clone() {
+            for dz in range.clone() {
+                let (x, z) = (position.x + dx, position.z + dz);
+                let Some(chunk) = self.chunks.get_mut(&xz_to_index(x.div_euclid(16), z.div_euclid(16))) else {continue};
+                let (xr, zr) = (x.rem_euclid(16) as u32, z.rem_euclid(16) as u32);
+                let border = dx.abs() > LIGHT_UPDATE_RADIUS || dz.abs() > LIGHT_UPDATE_RADIUS;
+
+                //the same sky pass as calculate_initial_lighting down to the first solid block, which starts a flood fill.
+                //Below it the border keeps its light and passes it on again, everything else starts dark
+                let mut sky = true;
+                for y in (0..=255).rev() {
+                    let block = chunk.get_block_at_mut(xr, y, zr);
+                    let block_position = Vector3::new(x, y as i32, z);
+                    if sky {
+                        record_light(&mut before, block_position, block, 15);
+                        if !has_partial_transparency(block) {
+                            queue.push_back(block_position);
+                            sky = false;
+                        }
+                    }
+                    else if !border {
+                        record_light(&mut before, block_position, block, 0);
+                    }
+                    else if has_partial_transparency(block) {
+                        queue.push_back(block_position);
+                    }
+                }
+            }
+        }
+
+        self.flood_light(queue, Some(&mut before));
+
+        //a block cleared and lit back to the same level didn't change
+        before.into_iter()
+            .filter(|(position, light)| get_block_light(&self.get_block(*position).unwrap()) != *light)
+            .map(|(position, _)| position)
+            .collect()
     }
 
     pub fn calculate_initial_lighting(&mut self, chunk_pos: Vector2<i32>) {
@@ -78,52 +300,260 @@ impl ServerChunkManager {
 
                     queue.push_back(Vector3::new(abs_x, abs_y, abs_z));
 
-                    while queue.len() > 0 {
-                        let current_pos = queue.pop_front().unwrap();
+                    self.flood_light(queue, None);
 
-                        let chunk = self.chunks.get_mut(&xz_to_index(current_pos.x.div_euclid(16), current_pos.z.div_euclid(16))).unwrap();
+                    break;
+                }
+            }
+        }
+    }
 
-                        let localspace = current_pos.map(|v| v.rem_euclid(16) as u32);
+    //spreads light from every queued block, each step dims it by one. Light only passes on through transparent blocks.
+    //before gets the light of every block it raises, from before its first change
+    fn flood_light(&mut self, mut queue: VecDeque<Vector3<i32>>, mut before: Option<&mut HashMap<Vector3<i32>, u8>>) {
+        while queue.len() > 0 {
+            let current_pos = queue.pop_front().unwrap();
 
-                        let block = chunk.get_block_at_mut(localspace.x, localspace.y, localspace.z);
+            let chunk = self.chunks.get_mut(&xz_to_index(current_pos.x.div_euclid(16), current_pos.z.div_euclid(16))).unwrap();
 
-                        let light_intensity = get_block_light(block);
+            //y stays absolute, the chunk picks the slice from it
+            let localspace = Vector3::new(current_pos.x.rem_euclid(16), current_pos.y, current_pos.z.rem_euclid(16)).map(|v| v as u32);
 
-                        if light_intensity <= 1 {
-                            continue;
-                        }
+            let block = chunk.get_block_at_mut(localspace.x, localspace.y, localspace.z);
 
-                        //check all the neighbors, and add to queue if required
-                        [
-                            current_pos + Vector3::new(1, 0, 0),
-                            current_pos + Vector3::new(-1, 0, 0),
-                            current_pos + Vector3::new(0, 1, 0),
-                            current_pos + Vector3::new(0, -1, 0),
-                            current_pos + Vector3::new(0, 0, 1),
-                            current_pos + Vector3::new(0, 0, -1)
-                        ].map(|pos| {
-                            if pos.y < 0 || pos.y > 255 || !self.chunks.contains_key(&xz_to_index(pos.x.div_euclid(16), pos.z.div_euclid(16))) {
-                                return;
-                            }
-                            let chunk = self.chunks.get_mut(&xz_to_index(pos.x.div_euclid(16), pos.z.div_euclid(16))).unwrap();
-                            let localspace = pos.map(|v| v.rem_euclid(16) as u32);
-                            let block = chunk.get_block_at_mut(localspace.x, localspace.y, localspace.z);
+            let light_intensity = get_block_light(block);
 
-                            let block_intensity = get_block_light(block);
-                            
+            if light_intensity <= 1 {
+                continue;
+            }
+
+            //check all the neighbors, and add to queue if required
+            [
+                current_pos + Vector3::new(1, 0, 0),
+                current_pos + Vector3::new(-1, 0, 0),
+                current_pos + Vector3::new(0, 1, 0),
+                current_pos + Vector3::new(0, -1, 0),
+                current_pos + Vector3::new(0, 0, 1),
+                current_pos + Vector3::new(0, 0, -1)
+            ].map(|pos| {
+                if pos.y < 0 || pos.y > 255 || !self.chunks.contains_key(&xz_to_index(pos.x.div_euclid(16), pos.z.div_euclid(16))) {
+                    return;
+                }
+                let chunk = self.chunks.get_mut(&xz_to_index(pos.x.div_euclid(16), pos.z.div_euclid(16))).unwrap();
+                let localspace = Vector3::new(pos.x.rem_euclid(16), pos.y, pos.z.rem_euclid(16)).map(|v| v as u32);
+                let block = chunk.get_block_at_mut(localspace.x, localspace.y, localspace.z);
+
+                let block_intensity = get_block_light(block);
+                
+
+                if block_intensity < light_intensity - 1 {
+                    match before.as_deref_mut() {
+                        Some(before) => record_light(before, pos, block, light_intensity - 1),
+                        None => set_block_light(block, light_intensity - 1)
+                    }
+                    if has_partial_transparency(block) {
+                        queue.push_back(pos);
+                    }
+                }
+            });
+        }
+    }
+}
+
+//sets the light, keeping what it was in before the first time the block's light changes
+fn record_light(before: &mut HashMap<Vector3<i32>, u8>, position: Vector3<i32>, block: &mut WorldBlock, light: u8) {
+    let old = get_block_light(block);
+    if old != light {
+        before.entry(position).or_insert(old);
+        set_block_light(block, light);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Vector2, Vector3};
+    use shared::world::{blockrepr::{get_block_light, WorldBlock}, chunk::{local_xyz_to_index, xz_to_index, Chunk}};
+
+    use crate::world::chunktickets::{ChunkLevel, Ticket, TicketKind};
 
-                            if block_intensity < light_intensity - 1 {
-                                set_block_light(block, light_intensity - 1);
-                                if has_partial_transparency(block) {
-                                    queue.push_back(pos);
+    use super::{ServerChunkManager, EVICT_AFTER_TICKS};
+
+    //high enough that the light crosses y slices
+    const FLOOR_HEIGHT: u32 = 20;
+
+    //a 3x3 chunk stone floor, lit like freshly generated chunks. extra is set before the lighting
+    fn floor_world(extra: Option<Vector3<i32>>) -> ServerChunkManager {
+        let mut chunk_manager = ServerChunkManager::new(None);
+        for cx in -1..=1 {
+            for cz in -1..=1 {
+                let grid = (0..16).map(|y_slice| {
+                    let mut slice = vec![WorldBlock::Air(0); 4096];
+                    for x in 0..16 {
+                        for z in 0..16 {
+                            for y in 0..16 {
+                                if y_slice * 16 + y < FLOOR_HEIGHT {
+                                    slice[local_xyz_to_index(x, y, z) as usize] = WorldBlock::Stone(0);
                                 }
                             }
-                        });
+                        }
                     }
+                    slice
+                }).collect();
+                chunk_manager.chunks.insert(xz_to_index(cx, cz), Chunk::from_blocks(Vector2::new(cx, cz), grid));
+            }
+        }
 
-                    break;
+        if let Some(position) = extra {
+            let chunk = chunk_manager.chunks.get_mut(&xz_to_index(position.x.div_euclid(16), position.z.div_euclid(16))).unwrap();
+            chunk.set_block_at(position.x.rem_euclid(16) as u32, position.y as u32, position.z.rem_euclid(16) as u32, WorldBlock::Stone(0));
+        }
+
+        for cx in -1..=1 {
+            for cz in -1..=1 {
+                chunk_manager.calculate_initial_lighting(Vector2::new(cx, cz));
+            }
+        }
+        chunk_manager
+    }
+
+    fn assert_same_blocks(a: &ServerChunkManager, b: &ServerChunkManager) {
+        for (index, chunk) in &a.chunks {
+            assert!(chunk.grid == b.chunks[index].grid, "chunk {:?} differs", chunk.position);
+        }
+    }
+
+    #[test]
+    fn changes_relight_like_a_fresh_world() {
+        let position = Vector3::new(3, FLOOR_HEIGHT as i32 + 2, -2);
+        let mut chunk_manager = floor_world(None);
+
+        let changes = chunk_manager.place_block(position.x, position.z, position.y as u32, WorldBlock::Stone(0));
+        assert_same_blocks(&chunk_manager, &floor_world(Some(position)));
+
+        //the block itself and the shadow it casts on the floor are both sent
+        assert!(changes.iter().any(|(p, block)| *p == position && matches!(block, WorldBlock::Stone(_))));
+        let floor_below = Vector3::new(position.x, FLOOR_HEIGHT as i32 - 1, position.z);
+        assert!(changes.iter().any(|(p, block)| *p == floor_below && get_block_light(block) < 15));
+        //right under it the light still spreads from the neighbours
+        let below = chunk_manager.get_block(position - Vector3::y()).unwrap();
+        assert_eq!(get_block_light(&below), 14);
+
+        chunk_manager.break_block(position.x, position.z, position.y as u32);
+        assert_same_blocks(&chunk_manager, &floor_world(None));
+    }
+
+    #[test]
+    fn changes_are_the_blocks_that_differ() {
+        let position = Vector3::new(-7, FLOOR_HEIGHT as i32 + 1, 12);
+        let (before, after) = (floor_world(None), floor_world(Some(position)));
+
+        let mut differing = Vec::new();
+        for chunk in before.chunks.values() {
+            for y in 0..256 {
+                for x in 0..16 {
+                    for z in 0..16 {
+                        let absolute = Vector3::new(chunk.position.x * 16 + x, y, chunk.position.y * 16 + z);
+                        if before.get_block(absolute) != after.get_block(absolute) {
+                            differing.push(absolute);
+                        }
+                    }
                 }
             }
         }
+
+        let mut chunk_manager = floor_world(None);
+        let mut changes = chunk_manager.place_block(position.x, position.z, position.y as u32, WorldBlock::Stone(0))
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect::<Vec<_>>();
+        let key = |p: &Vector3<i32>| (p.x, p.y, p.z);
+        changes.sort_by_key(key);
+        differing.sort_by_key(key);
+        assert_eq!(changes, differing);
+    }
+
+    #[test]
+    fn refuses_changes_that_arent_allowed() {
+        let mut chunk_manager = floor_world(None);
+
+        assert!(chunk_manager.break_block(0, 0, FLOOR_HEIGHT + 5).is_empty());
+        assert!(chunk_manager.place_block(0, 0, FLOOR_HEIGHT - 1, WorldBlock::Sand(0)).is_empty());
+        //outside the generated chunks
+        assert!(chunk_manager.place_block(100, 0, 10, WorldBlock::Sand(0)).is_empty());
+    }
+
+    #[test]
+    fn evicted_chunks_keep_their_changes() {
+        let dir = std::env::temp_dir().join(format!("starforged-eviction-{}", std::process::id()));
+        let mut chunk_manager = ServerChunkManager::new(Some(dir.clone()));
+        let kind = TicketKind::Forced(Vector2::new(0, 0));
+        let ticket = Ticket {center: Vector2::new(0, 0), radius: 0, level: ChunkLevel::Loaded};
+
+        chunk_manager.tickets.add(kind, ticket);
+        assert_eq!(chunk_manager.load_ticketed_chunks(usize::MAX), 0);
+        let position = Vector3::new(1, 250, 1);
+        assert!(!chunk_manager.place_block(position.x, position.z, position.y as u32, WorldBlock::Stone(0)).is_empty());
+
+        //held a while longer once the ticket goes
+        chunk_manager.tickets.remove(kind);
+        chunk_manager.tick();
+        assert_eq!(chunk_manager.chunks.len(), 1);
+        for _ in 0..EVICT_AFTER_TICKS {
+            chunk_manager.tick();
+        }
+        assert!(chunk_manager.chunks.is_empty());
+
+        chunk_manager.tickets.add(kind, ticket);
+        chunk_manager.load_ticketed_chunks(usize::MAX);
+        assert!(matches!(chunk_manager.get_block(position), Some(WorldBlock::Stone(_))));
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn chunks_near_the_center_load_first() {
+        let mut chunk_manager = ServerChunkManager::new(None);
+        chunk_manager.tickets.add(TicketKind::Spawn, Ticket {center: Vector2::new(0, 0), radius: 3, level: ChunkLevel::Loaded});
+
+        assert_eq!(chunk_manager.load_ticketed_chunks(9), 7 * 7 - 9);
+        for x in -1..=1 {
+            for z in -1..=1 {
+                assert!(chunk_manager.chunks.contains_key(&xz_to_index(x, z)));
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_only_update_in_block_ticking_chunks() {
+        let mut chunk_manager = floor_world(None);
+        let position = Vector3::new(3, FLOOR_HEIGHT as i32 + 3, 3);
+        chunk_manager.place_block(position.x, position.z, position.y as u32, WorldBlock::Sand(0));
+
+        //no ticket, the sand waits in the air
+        for _ in 0..5 {
+            assert!(chunk_manager.tick().is_empty());
+        }
+        assert!(matches!(chunk_manager.get_block(position), Some(WorldBlock::Sand(_))));
+
+        chunk_manager.tickets.add(TicketKind::Spawn, Ticket {center: Vector2::new(0, 0), radius: 1, level: ChunkLevel::BlockTicking});
+        for _ in 0..5 {
+            chunk_manager.tick();
+        }
+        assert!(matches!(chunk_manager.get_block(position), Some(WorldBlock::Air(_))));
+        let landed = Vector3::new(position.x, FLOOR_HEIGHT as i32, position.z);
+        assert!(matches!(chunk_manager.get_block(landed), Some(WorldBlock::Sand(_))));
+    }
+
+    #[test]
+    fn evicting_without_a_save_dir_forgets_the_changes() {
+        let mut chunk_manager = floor_world(None);
+        chunk_manager.place_block(0, 0, FLOOR_HEIGHT, WorldBlock::Stone(0));
+        assert!(!chunk_manager.dirty.is_empty());
+
+        for _ in 0..=EVICT_AFTER_TICKS {
+            chunk_manager.tick();
+        }
+        assert!(chunk_manager.chunks.is_empty());
+        assert!(chunk_manager.dirty.is_empty());
+    }
+}
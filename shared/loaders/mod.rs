@@ -1,2 +1,3 @@
 pub mod texture_loader;
-pub mod texture;
\ No newline at end of file
+pub mod texture;
+pub mod model_loader;
@@ -0,0 +1,235 @@
+use std::{collections::HashMap, env, fs::File, io::BufReader, path::Path, sync::{Arc, Mutex}};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::world::block::BlockFace;
+
+use super::texture_loader::get_indices_from_texture;
+
+//a single polygon of a block model, positions are in block space (0..1 on every axis)
+pub struct ModelFace {
+    pub positions: Vec<[f32; 3]>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub normal: [f32; 3],
+    pub texture: u32,
+    //faces lying flat on the block boundary are hidden by a full neighbour on that side
+    pub cull: Option<BlockFace>
+}
+
+pub struct BlockModel {
+    pub faces: Vec<ModelFace>
+}
+
+#[derive(Deserialize)]
+struct ModelLoadData {
+    #[serde(rename(deserialize = "path"))]
+    model_path: String,
+    alias: String,
+    //obj files don't carry our texture aliases, json models name theirs per face
+    texture: Option<String>
+}
+
+#[derive(Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum SerBlockFace {
+    Top,
+    Bottom,
+    Right,
+    Left,
+    Front,
+    Back
+}
+
+impl From<SerBlockFace> for BlockFace {
+    fn from(value: SerBlockFace) -> Self {
+        match value {
+            SerBlockFace::Top => BlockFace::Top,
+            SerBlockFace::Bottom => BlockFace::Bottom,
+            SerBlockFace::Right => BlockFace::Right,
+            SerBlockFace::Left => BlockFace::Left,
+            SerBlockFace::Front => BlockFace::Front,
+            SerBlockFace::Back => BlockFace::Back,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonModelFace {
+    texture: String,
+    //u0, v0, u1, v1 in pixels (0..16), defaults to the element's extent on that face
+    uv: Option<[f32; 4]>
+}
+
+#[derive(Deserialize)]
+struct JsonModelElement {
+    //in pixels, 16 is a full block
+    from: [f32; 3],
+    to: [f32; 3],
+    faces: HashMap<SerBlockFace, JsonModelFace>
+}
+
+#[derive(Deserialize)]
+struct JsonModel {
+    elements: Vec<JsonModelElement>
+}
+
+pub static LOADED_MODELS: Lazy<Mutex<HashMap<String, Arc<BlockModel>>>> = Lazy::new(|| {
+    let m = HashMap::new();
+    Mutex::new(m)
+});
+
+pub fn get_block_model(alias: &str) -> Option<Arc<BlockModel>> {
+    LOADED_MODELS.lock().unwrap().get(alias).cloned()
+}
+
+//the face is cullable when it lies on the boundary plane its normal points through
+fn boundary_face(positions: &[[f32; 3]], normal: [f32; 3]) -> Option<BlockFace> {
+    const EPSILON: f32 = 0.001;
+    let on_plane = |axis: usize, value: f32| positions.iter().all(|p| (p[axis] - value).abs() < EPSILON);
+
+    let face = if normal[1] > 1.0 - EPSILON {BlockFace::Top} else if normal[1] < -1.0 + EPSILON {BlockFace::Bottom}
+        else if normal[0] > 1.0 - EPSILON {BlockFace::Right} else if normal[0] < -1.0 + EPSILON {BlockFace::Left}
+        else if normal[2] > 1.0 - EPSILON {BlockFace::Front} else if normal[2] < -1.0 + EPSILON {BlockFace::Back}
+        else {return None};
+
+    let on_boundary = match face {
+        BlockFace::Top => on_plane(1, 1.0),
+        BlockFace::Bottom => on_plane(1, 0.0),
+        BlockFace::Right => on_plane(0, 1.0),
+        BlockFace::Left => on_plane(0, 0.0),
+        BlockFace::Front => on_plane(2, 1.0),
+        BlockFace::Back => on_plane(2, 0.0),
+    };
+
+    if on_boundary {Some(face)} else {None}
+}
+
+//corners are counter clockwise seen from outside
+fn cuboid_face(from: [f32; 3], to: [f32; 3], face: BlockFace) -> [[f32; 3]; 4] {
+    let [x0, y0, z0] = from;
+    let [x1, y1, z1] = to;
+    match face {
+        BlockFace::Top => [[x0, y1, z0], [x0, y1, z1], [x1, y1, z1], [x1, y1, z0]],
+        BlockFace::Bottom => [[x0, y0, z0], [x1, y0, z0], [x1, y0, z1], [x0, y0, z1]],
+        BlockFace::Right => [[x1, y0, z1], [x1, y0, z0], [x1, y1, z0], [x1, y1, z1]],
+        BlockFace::Left => [[x0, y0, z0], [x0, y0, z1], [x0, y1, z1], [x0, y1, z0]],
+        BlockFace::Front => [[x0, y0, z1], [x1, y0, z1], [x1, y1, z1], [x0, y1, z1]],
+        BlockFace::Back => [[x1, y0, z0], [x0, y0, z0], [x0, y1, z0], [x1, y1, z0]],
+    }
+}
+
+//same projection the surface shader uses for cube faces, so a full element lines up with the neighbouring blocks
+fn face_projection(face: BlockFace, p: [f32; 3]) -> [f32; 2] {
+    match face {
+        BlockFace::Top | BlockFace::Bottom => [p[0], p[2]],
+        BlockFace::Right | BlockFace::Left => [1.0 - p[2], 1.0 - p[1]],
+        BlockFace::Front | BlockFace::Back => [1.0 - p[0], 1.0 - p[1]],
+    }
+}
+
+fn load_json_model(path: &Path) -> BlockModel {
+    let file = File::open(path).expect("Unable to load block model file");
+    let reader = BufReader::new(file);
+    let data: JsonModel = serde_json::from_reader(reader).expect("Invalid block model data");
+
+    let mut faces = Vec::new();
+
+    for element in data.elements {
+        let from = element.from.map(|v| v / 16.0);
+        let to = element.to.map(|v| v / 16.0);
+
+        for (face, definition) in element.faces {
+            let face: BlockFace = face.into();
+            let positions = cuboid_face(from, to, face);
+            let normal = face.normal().map(|v| v as f32).into();
+
+            //the element's own extent is remapped onto the given uv rectangle
+            let [e0, e1] = [face_projection(face, from), face_projection(face, to)];
+            let tex_coords = positions.iter().map(|p| {
+                let [u, v] = face_projection(face, *p);
+                match definition.uv {
+                    None => [u, v],
+                    Some([u0, v0, u1, v1]) => {
+                        let remap = |t: f32, a: f32, b: f32, c0: f32, c1: f32| if (b - a).abs() < 0.0001 {c0} else {c0 + (t - a) / (b - a) * (c1 - c0)};
+                        [remap(u, e0[0], e1[0], u0 / 16.0, u1 / 16.0), remap(v, e0[1], e1[1], v0 / 16.0, v1 / 16.0)]
+                    }
+                }
+            }).collect();
+
+            faces.push(ModelFace {
+                cull: boundary_face(&positions, normal),
+                positions: positions.to_vec(),
+                tex_coords,
+                normal,
+                texture: get_indices_from_texture(&definition.texture) as u32
+            });
+        }
+    }
+
+    BlockModel { faces }
+}
+
+fn load_obj_model(path: &Path, texture: Option<&str>) -> BlockModel {
+    let (models, _) = tobj::load_obj(path, &tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    }).expect("Unable to load obj block model");
+
+    let texture = get_indices_from_texture(texture.unwrap_or("default-diffuse")) as u32;
+    let mut faces = Vec::new();
+
+    for model in models {
+        let mesh = model.mesh;
+        let position = |i: u32| -> [f32; 3] {
+            let i = i as usize * 3;
+            [mesh.positions[i], mesh.positions[i + 1], mesh.positions[i + 2]]
+        };
+        //obj has v going up, the textures are sampled with v going down
+        let tex_coord = |i: u32| -> [f32; 2] {
+            let i = i as usize * 2;
+            if mesh.texcoords.len() > i + 1 {[mesh.texcoords[i], 1.0 - mesh.texcoords[i + 1]]} else {[0.0, 0.0]}
+        };
+
+        for triangle in mesh.indices.chunks(3) {
+            let positions: Vec<[f32; 3]> = triangle.iter().map(|i| position(*i)).collect();
+            let edge0 = nalgebra::Vector3::from(positions[1]) - nalgebra::Vector3::from(positions[0]);
+            let edge1 = nalgebra::Vector3::from(positions[2]) - nalgebra::Vector3::from(positions[0]);
+            let normal: [f32; 3] = edge0.cross(&edge1).normalize().into();
+
+            faces.push(ModelFace {
+                cull: boundary_face(&positions, normal),
+                tex_coords: triangle.iter().map(|i| tex_coord(*i)).collect(),
+                positions,
+                normal,
+                texture
+            });
+        }
+    }
+
+    BlockModel { faces }
+}
+
+//texture indices are resolved while loading, so this has to run after initialize_load_textures
+pub fn preload_models() {
+    let mut manifest = env::current_dir().unwrap();
+    manifest.push("res/data/model_manifest.json");
+
+    let file = File::open(manifest).expect("Unable to load model_manifest file");
+    let reader = BufReader::new(file);
+    let data: Vec<ModelLoadData> = serde_json::from_reader(reader).expect("Invalid model_manifest data");
+
+    let mut lock = LOADED_MODELS.lock().unwrap();
+
+    for definition in data {
+        let mut path = env::current_dir().unwrap();
+        path.push(Path::new(&format!("res/{}", definition.model_path)));
+
+        let model = match path.extension().and_then(|e| e.to_str()) {
+            Some("obj") => load_obj_model(&path, definition.texture.as_deref()),
+            _ => load_json_model(&path)
+        };
+
+        lock.insert(definition.alias, Arc::new(model));
+    }
+}
@@ -11,6 +11,11 @@ pub fn load_binary_sync(file_name: &str) -> std::io::Result<Vec<u8>> {
     Ok(data)
 }
 
+//a full chain, halving the longest side down to 1x1
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    width.max(height).max(1).ilog2() + 1
+}
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub sampler: wgpu::Sampler,
@@ -44,17 +49,22 @@ impl Texture {
             view_formats: &[]
         });
 
-        let view = texture.create_view(&TextureViewDescriptor::default());
+        //an array even with a single light, the shaders always index a layer
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
 
+        //comparison sampler, linear filtering blends the results of the neighbouring depth tests
         let sampler = device.create_sampler(
             &wgpu::SamplerDescriptor {
                 address_mode_u: wgpu::AddressMode::ClampToEdge,
                 address_mode_v: wgpu::AddressMode::ClampToEdge,
                 address_mode_w: wgpu::AddressMode::ClampToEdge,
-                mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Linear,
+                mag_filter: filter,
+                min_filter: filter,
                 mipmap_filter: wgpu::FilterMode::Nearest,
-                compare: None, //TODO: JUST IN CASE, THIS WAS A CHANGE!
+                compare: Some(wgpu::CompareFunction::LessEqual),
                 ..Default::default()
             }
         );
@@ -86,7 +96,9 @@ impl Texture {
             sample_count: 4,
             dimension: wgpu::TextureDimension::D2,
             format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            //attachment only, on gl a sampleable multisampled depth texture can't share a framebuffer with the
+            //msaa color target
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             view_formats: &[]
         });
 
@@ -175,8 +187,18 @@ impl Texture {
         bytes: &[u8],
         filter: wgpu::FilterMode
     ) -> Self {
-        let rgba = image::load_from_memory(bytes).unwrap();
+        Self::from_image(label, device, queue, format, &image::load_from_memory(bytes).unwrap().to_rgba8(), filter)
+    }
 
+    //a full mip chain, only the first level is filled. generate_mipmaps does the rest
+    pub fn from_image(
+        label: &str,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: TextureFormat,
+        rgba: &image::RgbaImage,
+        filter: wgpu::FilterMode
+    ) -> Self {
         let size = wgpu::Extent3d {
             width: rgba.width(),
             height: rgba.height(),
@@ -186,11 +208,12 @@ impl Texture {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some(label),
             size,
-            mip_level_count: 5, // log2(16) + 1
+            mip_level_count: mip_level_count(size.width, size.height),
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            //copied out of when the textures are packed into an array
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[]
         });
 
@@ -201,7 +224,7 @@ impl Texture {
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
-            &rgba.to_rgba8(),
+            rgba,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * size.width),
@@ -234,7 +257,6 @@ impl Texture {
 
 pub fn generate_mipmaps(device: &Arc<wgpu::Device>, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, format: &wgpu::TextureFormat) {
     let shader = device.create_shader_module(wgpu::include_wgsl!("../../res/shaders/blit.wgsl"));
-    const MIP_COUNT: u32 = 5; //log2(16) + 1
 
     let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("blit"),
@@ -274,7 +296,7 @@ pub fn generate_mipmaps(device: &Arc<wgpu::Device>, encoder: &mut wgpu::CommandE
         ..Default::default()
     });
 
-    let views = (0..MIP_COUNT)
+    let views = (0..texture.mip_level_count())
         .map(|mip| {
             texture.create_view(&wgpu::TextureViewDescriptor {
                 label: Some("mip"),
@@ -289,7 +311,7 @@ pub fn generate_mipmaps(device: &Arc<wgpu::Device>, encoder: &mut wgpu::CommandE
         })
         .collect::<Vec<_>>();
 
-    for target_mip in 1..MIP_COUNT as usize {
+    for target_mip in 1..views.len() {
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &bind_group_layout,
             entries: &[
@@ -1,9 +1,9 @@
 use std::{collections::HashMap, env, fs::File, io::BufReader, num::NonZeroU32, path::Path, sync::{Arc, Mutex}};
 use once_cell::sync::Lazy;
 use serde::Deserialize;
-use wgpu::{FilterMode, Sampler, TextureView};
+use wgpu::{util::DeviceExt, FilterMode, Sampler, TextureView};
 
-use super::texture::{generate_mipmaps, load_binary_sync, Texture};
+use super::texture::{generate_mipmaps, load_binary_sync, mip_level_count, Texture};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -18,16 +18,19 @@ static TEXTURE_INDICES: Lazy<Mutex<HashMap<String, usize>>> = Lazy::new(|| {
     Mutex::new(m)
 });
 
+//unknown aliases (or textures that haven't been loaded yet) fall back to the default texture at index 0
 pub fn get_indices_from_texture(texture: &str) -> usize {
-    *TEXTURE_INDICES.lock().unwrap().get(&texture.to_owned()).unwrap()
+    *TEXTURE_INDICES.lock().unwrap().get(texture).unwrap_or(&0)
 }
 
-#[derive(Deserialize)]
-struct ModelLoadData {
-    #[serde(rename(deserialize = "path"))]
-    model_path: String,
-    alias: String,
+//normal maps hold vectors, not colors, so they skip the srgb decode
+fn format_for_texture_type(texture_type: &TextureType, format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+    match texture_type {
+        TextureType::Normal => format.remove_srgb_suffix(),
+        _ => format
+    }
 }
+
 #[derive(Deserialize)]
 #[serde(rename_all = "snake_case")]
 enum SerFilterMode {
@@ -58,6 +61,8 @@ pub struct LoadedTextureData {
     pub path: String,
     pub alias: String,
     pub texture_type: TextureType,
+    //how it's magnified, see SerFilterMode
+    pub filter: FilterMode,
     pub texture: Arc<Texture>
 }
 
@@ -87,12 +92,15 @@ pub fn preload_textures(
     for definition in data {
         let mut path = env::current_dir().unwrap();
         path.push(Path::new(&format!("res/{}", definition.texture_path)));
-        let texture = Arc::new(Texture::from_bytes(&definition.alias, device, queue, format, &load_binary_sync(path.to_str().unwrap()).unwrap(), definition.filter.into()));
+        let format = format_for_texture_type(&definition.texture_type, format);
+        let filter = definition.filter.into();
+        let texture = Arc::new(Texture::from_bytes(&definition.alias, device, queue, format, &load_binary_sync(path.to_str().unwrap()).unwrap(), filter));
         generate_mipmaps(device, &mut encoder, &texture.texture, &format);
         lock.insert(definition.alias.clone(), LoadedTextureData {
             path: definition.texture_path,
             alias: definition.alias,
             texture_type: definition.texture_type,
+            filter,
             texture
         });
     }
@@ -108,7 +116,8 @@ pub fn initialize_load_textures(device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Qu
     let mut emissive_texture_map: Vec<Arc<Texture>> = Vec::new();
 
     let diff = Arc::new(Texture::from_color("default-diffuse", device, queue, format, [0, 255, 255, 0], 1, 1, wgpu::FilterMode::Nearest));
-    let norm = Arc::new(Texture::from_color("default-normal", device, queue, format, [255 / 3, 255 / 3, 255 / 3, 255], 1, 1, wgpu::FilterMode::Nearest));
+    //flat, straight out of the face
+    let norm = Arc::new(Texture::from_color("default-normal", device, queue, format_for_texture_type(&TextureType::Normal, format), [128, 128, 255, 255], 1, 1, wgpu::FilterMode::Nearest));
     let emi = Arc::new(Texture::from_color("default-emissive", device, queue, format, [0, 0, 0, 0], 1, 1, wgpu::FilterMode::Nearest));
 
     let mut n_diffuse: u32 = 1;
@@ -249,4 +258,280 @@ pub fn initialize_load_textures(device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Qu
     });
 
     (texture_bind_group, surface_texture_bind_group_layout)
-}
\ No newline at end of file
+}
+
+//the texture array backend has one nearest sampler, the shader filters the layers flagged here linearly.
+//One entry per layer, 1 if it's magnified linearly. Layer 0, the default, never is
+pub fn layer_filter_flags(filters: &[FilterMode]) -> Vec<u32> {
+    std::iter::once(0)
+        .chain(filters.iter().map(|filter| (*filter == FilterMode::Linear) as u32))
+        .collect()
+}
+
+//the largest texture, so none of them lose detail
+pub fn array_layer_size(sizes: impl Iterator<Item = (u32, u32)>) -> u32 {
+    sizes.map(|(width, height)| width.max(height)).max().unwrap_or(1)
+}
+
+//a texture that isn't size x size, loaded again from its file and scaled to fit a layer
+fn resized_texture(device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Queue>, encoder: &mut wgpu::CommandEncoder, texturedata: &LoadedTextureData, format: wgpu::TextureFormat, size: u32) -> Arc<Texture> {
+    let mut path = env::current_dir().unwrap();
+    path.push(Path::new(&format!("res/{}", texturedata.path)));
+    let image = image::load_from_memory(&load_binary_sync(path.to_str().unwrap()).unwrap()).unwrap().to_rgba8();
+
+    //nearest keeps pixel art crisp, linear textures are smooth anyway
+    let resize_filter = match texturedata.filter {
+        FilterMode::Nearest => image::imageops::FilterType::Nearest,
+        FilterMode::Linear => image::imageops::FilterType::Triangle
+    };
+    let resized = image::imageops::resize(&image, size, size, resize_filter);
+
+    let format = format_for_texture_type(&texturedata.texture_type, format);
+    let texture = Texture::from_image(&texturedata.alias, device, queue, format, &resized, texturedata.filter);
+    generate_mipmaps(device, encoder, &texture.texture, &format);
+    Arc::new(texture)
+}
+
+//one layer per texture of a type, with layer 0 as the default
+fn build_texture_array(
+    device: &Arc<wgpu::Device>,
+    queue: &Arc<wgpu::Queue>,
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    format: wgpu::TextureFormat,
+    default_color: [u8; 4],
+    size: u32,
+    layers: &[Arc<Texture>]
+) -> wgpu::TextureView {
+    //the same chain every layer texture has, so the copies below line up
+    let mip_level_count = mip_level_count(size, size);
+    let array = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {width: size, height: size, depth_or_array_layers: layers.len() as u32 + 1},
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[]
+    });
+
+    //a flat color doesn't need filtering, every mip of the default layer is the same
+    for mip in 0..mip_level_count {
+        let mip_size = (size >> mip).max(1);
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &array,
+                mip_level: mip,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All
+            },
+            &default_color.repeat((mip_size * mip_size) as usize),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * mip_size),
+                rows_per_image: Some(mip_size)
+            },
+            wgpu::Extent3d {width: mip_size, height: mip_size, depth_or_array_layers: 1}
+        );
+    }
+
+    //the loaded textures already have their mip chains, copy every level across
+    for (i, texture) in layers.iter().enumerate() {
+        for mip in 0..mip_level_count {
+            let mip_size = (size >> mip).max(1);
+            encoder.copy_texture_to_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture.texture,
+                    mip_level: mip,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All
+                },
+                wgpu::ImageCopyTexture {
+                    texture: &array,
+                    mip_level: mip,
+                    origin: wgpu::Origin3d {x: 0, y: 0, z: i as u32 + 1},
+                    aspect: wgpu::TextureAspect::All
+                },
+                wgpu::Extent3d {width: mip_size, height: mip_size, depth_or_array_layers: 1}
+            );
+        }
+    }
+
+    array.create_view(&wgpu::TextureViewDescriptor {
+        label: Some(label),
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        ..Default::default()
+    })
+}
+
+//the same textures as initialize_load_textures, packed into one texture_2d_array per type. Doesn't need binding arrays,
+//but every layer is the same square size, textures that aren't get resized. Texture indices become array layers
+pub fn initialize_texture_array_textures(device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Queue>, format: wgpu::TextureFormat) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {
+    let mut texture_indices = TEXTURE_INDICES.lock().unwrap();
+    let loaded_textures = LOADED_TEXTURES.lock().unwrap();
+
+    //sorted so the layers don't move around between runs
+    let mut aliases = loaded_textures.keys().collect::<Vec<_>>();
+    aliases.sort();
+
+    let size = array_layer_size(loaded_textures.values().map(|data| (data.texture.texture.width(), data.texture.texture.height())));
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Texture Array Encoder")
+    });
+
+    let mut diffuse_layers = Vec::new();
+    let mut normal_layers = Vec::new();
+    let mut emissive_layers = Vec::new();
+
+    for alias in aliases {
+        let texturedata = &loaded_textures[alias];
+        let (width, height) = (texturedata.texture.texture.width(), texturedata.texture.texture.height());
+
+        let texture = if width != size || height != size {
+            println!("texture {} is {}x{}, resized to {}x{} for the texture array", alias, width, height, size, size);
+            resized_texture(device, queue, &mut encoder, texturedata, format, size)
+        }
+        else {
+            texturedata.texture.clone()
+        };
+
+        let layers = match texturedata.texture_type {
+            TextureType::Diffuse => &mut diffuse_layers,
+            TextureType::Normal => &mut normal_layers,
+            TextureType::Emissive => &mut emissive_layers
+        };
+        layers.push((texture, texturedata.filter));
+        texture_indices.insert(alias.clone(), layers.len());
+    }
+
+    texture_indices.insert("default-diffuse".to_owned(), 0);
+    texture_indices.insert("default-normal".to_owned(), 0);
+    texture_indices.insert("default-emissive".to_owned(), 0);
+
+    let textures = |layers: &[(Arc<Texture>, FilterMode)]| layers.iter().map(|(texture, _)| texture.clone()).collect::<Vec<_>>();
+    let filters = |layers: &[(Arc<Texture>, FilterMode)]| layer_filter_flags(&layers.iter().map(|(_, filter)| *filter).collect::<Vec<_>>());
+
+    let diffuse = build_texture_array(device, queue, &mut encoder, "diffuse texture array", format, [0, 255, 255, 0], size, &textures(&diffuse_layers));
+    let normal = build_texture_array(device, queue, &mut encoder, "normal texture array", format_for_texture_type(&TextureType::Normal, format), [128, 128, 255, 255], size, &textures(&normal_layers));
+    let emissive = build_texture_array(device, queue, &mut encoder, "emissive texture array", format, [0, 0, 0, 0], size, &textures(&emissive_layers));
+
+    queue.submit(std::iter::once(encoder.finish()));
+
+    //magnifies nearest, the shader filters the linear layers itself. GL can't sample one texture with two samplers
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("texture array sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: FilterMode::Nearest,
+        min_filter: FilterMode::Linear,
+        mipmap_filter: FilterMode::Linear,
+        ..Default::default()
+    });
+
+    //sized to the layer count, so a type can have as many layers as the adapter allows
+    let filter_buffer = |label: &str, layers: &[(Arc<Texture>, FilterMode)]| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::cast_slice(&filters(layers)),
+        usage: wgpu::BufferUsages::STORAGE
+    });
+    let diffuse_filters = filter_buffer("diffuse texture array filters", &diffuse_layers);
+    let normal_filters = filter_buffer("normal texture array filters", &normal_layers);
+    let emissive_filters = filter_buffer("emissive texture array filters", &emissive_layers);
+
+    let array_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2Array,
+            multisampled: false,
+        },
+        count: None,
+    };
+
+    let filter_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            array_entry(0),
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            array_entry(2),
+            array_entry(3),
+            filter_entry(4),
+            filter_entry(5),
+            filter_entry(6),
+        ],
+        label: Some("Surface texture array bind group layout")
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Surface texture array bind group"),
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry {binding: 0, resource: wgpu::BindingResource::TextureView(&diffuse)},
+            wgpu::BindGroupEntry {binding: 1, resource: wgpu::BindingResource::Sampler(&sampler)},
+            wgpu::BindGroupEntry {binding: 2, resource: wgpu::BindingResource::TextureView(&normal)},
+            wgpu::BindGroupEntry {binding: 3, resource: wgpu::BindingResource::TextureView(&emissive)},
+            wgpu::BindGroupEntry {binding: 4, resource: diffuse_filters.as_entire_binding()},
+            wgpu::BindGroupEntry {binding: 5, resource: normal_filters.as_entire_binding()},
+            wgpu::BindGroupEntry {binding: 6, resource: emissive_filters.as_entire_binding()},
+        ]
+    });
+
+    (bind_group, layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::FilterMode;
+
+    use super::{array_layer_size, layer_filter_flags, mip_level_count};
+
+    #[test]
+    fn layers_fit_the_largest_texture() {
+        assert_eq!(array_layer_size([(16, 16), (32, 32), (16, 16)].into_iter()), 32);
+        //not square, the longer side decides
+        assert_eq!(array_layer_size([(16, 16), (8, 64)].into_iter()), 64);
+        assert_eq!(array_layer_size(std::iter::empty()), 1);
+    }
+
+    #[test]
+    fn mip_chains_go_down_to_one_pixel() {
+        assert_eq!(mip_level_count(16, 16), 5);
+        assert_eq!(mip_level_count(8, 8), 4);
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(8, 64), 7);
+        assert_eq!(mip_level_count(24, 24), 5);
+    }
+
+    #[test]
+    fn linear_layers_are_flagged_after_the_default() {
+        assert_eq!(layer_filter_flags(&[]), [0]);
+        assert_eq!(layer_filter_flags(&[FilterMode::Linear, FilterMode::Nearest, FilterMode::Linear]), [0, 1, 0, 1]);
+
+        //no cap on the layer count
+        let mut filters = vec![FilterMode::Nearest; 300];
+        filters[299] = FilterMode::Linear;
+        let flags = layer_filter_flags(&filters);
+        assert_eq!(flags.len(), 301);
+        assert_eq!(flags.iter().sum::<u32>(), 1);
+        assert_eq!(flags[300], 1);
+    }
+}
@@ -0,0 +1,94 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+//game server -> auth service, asks for a session token the named user has to confirm
+#[derive(Serialize, Deserialize)]
+pub struct SRST {
+    pub expected_username: String,
+    //where the service sends AuthConfirmedUser once the user confirms
+    pub callback: String
+}
+
+//client -> auth service, proves who the user is for a session token the game server handed out
+#[derive(Serialize, Deserialize)]
+pub struct CAST {
+    pub jwt: String,
+    //the game server the client thinks it's joining, the service checks it against the callback
+    pub expected_host: String,
+    pub auth_secret: String
+}
+
+#[derive(Serialize, Deserialize)]
+struct JwtClaims {
+    username: String
+}
+
+//session tokens and challenges
+pub fn random_token() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect()
+}
+
+fn challenge_mac(secret: &str, challenge: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(challenge.as_bytes());
+    mac
+}
+
+//hex hmac-sha256 of the challenge, proves both sides know the secret without sending it
+pub fn challenge_answer(secret: &str, challenge: &str) -> String {
+    challenge_mac(secret, challenge).finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+//compares in constant time, so how long it takes says nothing about how much of the answer was right
+pub fn verify_challenge_answer(secret: &str, challenge: &str, answer: &str) -> bool {
+    let Some(answer) = decode_hex(answer) else {return false};
+    challenge_mac(secret, challenge).verify_slice(&answer).is_ok()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {return None};
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+//the username claim, without checking the signature
+pub fn jwt_username(jwt: &str) -> Option<String> {
+    let payload = URL_SAFE_NO_PAD.decode(jwt.split('.').nth(1)?).ok()?;
+    serde_json::from_slice::<JwtClaims>(&payload).ok().map(|claims| claims.username)
+}
+
+//a token nobody signed. The real auth service rejects it, the local stand-in only reads the username
+pub fn unsigned_jwt(username: &str) -> String {
+    let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"none","typ":"JWT"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&JwtClaims {username: username.to_string()}).unwrap());
+    format!("{}.{}.", header, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{challenge_answer, jwt_username, unsigned_jwt, verify_challenge_answer};
+
+    #[test]
+    fn jwt_usernames_round_trip() {
+        assert_eq!(jwt_username(&unsigned_jwt("phxie")).as_deref(), Some("phxie"));
+        assert_eq!(jwt_username("not a jwt"), None);
+    }
+
+    #[test]
+    fn challenge_answers_depend_on_the_secret() {
+        assert_eq!(challenge_answer("secret", "abc"), challenge_answer("secret", "abc"));
+        assert_ne!(challenge_answer("secret", "abc"), challenge_answer("other", "abc"));
+        assert_eq!(challenge_answer("secret", "abc").len(), 64);
+    }
+
+    #[test]
+    fn challenge_answers_verify() {
+        assert!(verify_challenge_answer("secret", "abc", &challenge_answer("secret", "abc")));
+        assert!(!verify_challenge_answer("secret", "abc", &challenge_answer("other", "abc")));
+        assert!(!verify_challenge_answer("secret", "abc", "not hex"));
+        assert!(!verify_challenge_answer("secret", "abc", &challenge_answer("secret", "abc")[1..]));
+        assert!(!verify_challenge_answer("secret", "abc", ""));
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt::{self, Display};
+
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec_with_limit};
+use serde::{de::DeserializeOwned, Serialize};
+
+//every message on the wire is one frame: the body length (u32 le), flags, then the bincode body, deflated if the flags
+//say so. tcp hands over whatever arrived, so frames are put back together by FrameReader
+pub const HEADER_SIZE: usize = 5;
+//bodies bigger than this are refused before they're read, inflated or not
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+//smaller bodies aren't worth deflating
+pub const COMPRESSION_THRESHOLD: usize = 512;
+
+const FLAG_DEFLATED: u8 = 1;
+const COMPRESSION_LEVEL: u8 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodecError {
+    TooLarge(usize),
+    UnknownFlags(u8),
+    Inflate,
+    Encode(String),
+    Decode(String)
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::TooLarge(size) => write!(f, "frame of {} bytes is over the {} byte limit", size, MAX_FRAME_SIZE),
+            CodecError::UnknownFlags(flags) => write!(f, "unknown frame flags {:#04x}", flags),
+            CodecError::Inflate => write!(f, "frame didn't inflate"),
+            CodecError::Encode(e) => write!(f, "couldn't encode message: {}", e),
+            CodecError::Decode(e) => write!(f, "couldn't decode message: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+//deflate is only used when the other side agreed to FEATURE_DEFLATE, and only for bodies over the threshold
+pub fn encode<T: Serialize>(message: &T, deflate: bool) -> Result<Vec<u8>, CodecError> {
+    let body = bincode::serialize(message).map_err(|e| CodecError::Encode(e.to_string()))?;
+
+    let (flags, body) = if deflate && body.len() > COMPRESSION_THRESHOLD {
+        (FLAG_DEFLATED, compress_to_vec(&body, COMPRESSION_LEVEL))
+    }
+    else {
+        (0, body)
+    };
+    if body.len() > MAX_FRAME_SIZE {
+        return Err(CodecError::TooLarge(body.len()));
+    }
+
+    let mut frame = Vec::with_capacity(HEADER_SIZE + body.len());
+    frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    frame.push(flags);
+    frame.extend_from_slice(&body);
+    Ok(frame)
+}
+
+fn inflate_body(flags: u8, body: Vec<u8>) -> Result<Vec<u8>, CodecError> {
+    match flags {
+        0 => Ok(body),
+        FLAG_DEFLATED => decompress_to_vec_with_limit(&body, MAX_FRAME_SIZE).map_err(|_| CodecError::Inflate),
+        _ => Err(CodecError::UnknownFlags(flags))
+    }
+}
+
+fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, CodecError> {
+    bincode::deserialize(body).map_err(|e| CodecError::Decode(e.to_string()))
+}
+
+//turns the bytes of one connection back into messages. A read can end partway through a frame or hold several
+#[derive(Default)]
+pub struct FrameReader {
+    buffer: Vec<u8>
+}
+
+impl FrameReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    //the next whole message, None until all of it has arrived. The frame is used up even if it doesn't decode, but after
+    //TooLarge the stream can't be followed anymore
+    pub fn next_message<T: DeserializeOwned>(&mut self) -> Result<Option<T>, CodecError> {
+        if self.buffer.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let size = u32::from_le_bytes(self.buffer[..4].try_into().unwrap()) as usize;
+        if size > MAX_FRAME_SIZE {
+            self.buffer.clear();
+            return Err(CodecError::TooLarge(size));
+        }
+        if self.buffer.len() < HEADER_SIZE + size {
+            return Ok(None);
+        }
+
+        let flags = self.buffer[4];
+        let body = self.buffer.drain(..HEADER_SIZE + size).skip(HEADER_SIZE).collect::<Vec<_>>();
+        decode(&inflate_body(flags, body)?).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, Vector2, Vector3};
+    use serde::Serialize;
+
+    use crate::{network::{containers::{AuthMessages, AuthenticatedPacket, ClientToServerMessage, NetworkMessage, Packet, ServerToClientMessage, UnauthenticatedPacket}, handshake::{HandshakeMessage, Hello}}, world::{blockrepr::WorldBlock, chunk::Chunk, chunkcompress::compress_chunk}};
+
+    use super::{encode, CodecError, FrameReader, COMPRESSION_THRESHOLD, HEADER_SIZE, MAX_FRAME_SIZE};
+
+    //one block per slice, the palette is a HashMap and more than one entry could encode in another order
+    fn chunk() -> Chunk {
+        let grid = (0..16).map(|y_slice| vec![if y_slice < 4 {WorldBlock::Stone(0)} else {WorldBlock::Air(15)}; 4096]).collect();
+        Chunk::from_blocks(Vector2::new(-2, 5), grid)
+    }
+
+    //one of every message. The match below stops compiling when a variant is added, so it gets a sample here too
+    fn every_message() -> Vec<NetworkMessage> {
+        let position = Vector3::new(-17, 64, 3);
+        let (eye, direction) = (Point3::new(0.5, 70.2, -3.0), Vector3::new(0.0, -0.5, 1.0));
+
+        let messages = vec![
+            NetworkMessage::Handshake(HandshakeMessage::Hello(Hello::current())),
+            NetworkMessage::Handshake(HandshakeMessage::Accepted(vec!["deflate".to_string()])),
+            NetworkMessage::Handshake(HandshakeMessage::Rejected("too old".to_string())),
+            NetworkMessage::Auth(AuthMessages::AuthRequestUserCredentials("secret".to_string())),
+            NetworkMessage::Auth(AuthMessages::AuthConfirmedUser("phxie".to_string())),
+            NetworkMessage::Auth(AuthMessages::ClientRequestJoin("phxie".to_string())),
+            NetworkMessage::Auth(AuthMessages::JoinConfirmed("token".to_string())),
+            NetworkMessage::Auth(AuthMessages::AuthChallenge("challenge".to_string())),
+            NetworkMessage::Auth(AuthMessages::AuthChallengeResponse("phxie".to_string(), "answer".to_string())),
+            NetworkMessage::Auth(AuthMessages::JoinRejected("no".to_string())),
+            NetworkMessage::ServerToClient(ServerToClientMessage::ChunkProvided((Vector2::new(-2, 5), compress_chunk(&chunk())))),
+            NetworkMessage::ServerToClient(ServerToClientMessage::ConcludeReceiveInitialChunks),
+            NetworkMessage::ServerToClient(ServerToClientMessage::BlockChanged(position, WorldBlock::Sand(12))),
+            NetworkMessage::ServerToClient(ServerToClientMessage::MultiBlockChange(Vector2::new(3, -1), vec![(1, 2, 3, WorldBlock::Stone(4)), (15, 255, 0, WorldBlock::Air(15))])),
+            NetworkMessage::ServerToClient(ServerToClientMessage::UnloadChunk(Vector2::new(7, -7))),
+            NetworkMessage::ServerToClient(ServerToClientMessage::Kicked("sent a malformed packet".to_string())),
+            NetworkMessage::ClientToServer(ClientToServerMessage::RequestInitialChunks),
+            NetworkMessage::ClientToServer(ClientToServerMessage::RequestChunk(Vector2::new(0, 1))),
+            NetworkMessage::ClientToServer(ClientToServerMessage::SetBlock(position, WorldBlock::Dirt(0), eye, direction)),
+            NetworkMessage::ClientToServer(ClientToServerMessage::BreakBlock(position, eye, direction)),
+            NetworkMessage::ClientToServer(ClientToServerMessage::PlayerPosition(eye)),
+            NetworkMessage::ClientToServer(ClientToServerMessage::SetViewDistance(12))
+        ];
+
+        for message in &messages {
+            match message {
+                NetworkMessage::Handshake(HandshakeMessage::Hello(_) | HandshakeMessage::Accepted(_) | HandshakeMessage::Rejected(_)) => {},
+                NetworkMessage::Auth(
+                    AuthMessages::AuthRequestUserCredentials(_) | AuthMessages::AuthConfirmedUser(_) | AuthMessages::ClientRequestJoin(_) |
+                    AuthMessages::JoinConfirmed(_) | AuthMessages::AuthChallenge(_) | AuthMessages::AuthChallengeResponse(..) | AuthMessages::JoinRejected(_)
+                ) => {},
+                NetworkMessage::ServerToClient(
+                    ServerToClientMessage::ChunkProvided(_) | ServerToClientMessage::ConcludeReceiveInitialChunks | ServerToClientMessage::BlockChanged(..) |
+                    ServerToClientMessage::MultiBlockChange(..) | ServerToClientMessage::UnloadChunk(_) | ServerToClientMessage::Kicked(_)
+                ) => {},
+                NetworkMessage::ClientToServer(
+                    ClientToServerMessage::RequestInitialChunks | ClientToServerMessage::RequestChunk(_) | ClientToServerMessage::SetBlock(..) |
+                    ClientToServerMessage::BreakBlock(..) | ClientToServerMessage::PlayerPosition(_) | ClientToServerMessage::SetViewDistance(_)
+                ) => {}
+            }
+        }
+        messages
+    }
+
+    //the messages don't implement PartialEq, the same bytes is the same message
+    fn assert_round_trips<T: Serialize + serde::de::DeserializeOwned>(message: &T, deflate: bool) {
+        let mut reader = FrameReader::new();
+        reader.push(&encode(message, deflate).unwrap());
+
+        let decoded = reader.next_message::<T>().unwrap().unwrap();
+        assert_eq!(bincode::serialize(&decoded).unwrap(), bincode::serialize(message).unwrap());
+        assert!(matches!(reader.next_message::<T>(), Ok(None)));
+    }
+
+    #[test]
+    fn every_message_round_trips() {
+        for message in every_message() {
+            assert_round_trips(&message, false);
+            assert_round_trips(&message, true);
+        }
+    }
+
+    #[test]
+    fn every_packet_round_trips() {
+        for message in every_message() {
+            assert_round_trips(&Packet::Unauthenticated(UnauthenticatedPacket {data: message}), true);
+        }
+        for message in every_message() {
+            assert_round_trips(&Packet::Authenticated(AuthenticatedPacket {data: message, token: "token".to_string()}), true);
+        }
+    }
+
+    #[test]
+    fn only_big_bodies_are_deflated() {
+        let small = NetworkMessage::ServerToClient(ServerToClientMessage::UnloadChunk(Vector2::new(1, 2)));
+        assert_eq!(encode(&small, true).unwrap()[4], 0);
+
+        let chunk = NetworkMessage::ServerToClient(ServerToClientMessage::ChunkProvided((Vector2::new(-2, 5), compress_chunk(&chunk()))));
+        let plain = encode(&chunk, false).unwrap();
+        let deflated = encode(&chunk, true).unwrap();
+        assert!(plain.len() > COMPRESSION_THRESHOLD);
+        assert_eq!((plain[4], deflated[4]), (0, 1));
+        assert!(deflated.len() < plain.len());
+    }
+
+    #[test]
+    fn frames_split_and_joined_by_tcp_come_apart() {
+        let messages = every_message();
+        let stream = messages.iter().flat_map(|m| encode(m, true).unwrap()).collect::<Vec<_>>();
+
+        let mut reader = FrameReader::new();
+        let mut decoded = Vec::new();
+        for piece in stream.chunks(7) {
+            reader.push(piece);
+            while let Some(message) = reader.next_message::<NetworkMessage>().unwrap() {
+                decoded.push(bincode::serialize(&message).unwrap());
+            }
+        }
+
+        assert_eq!(decoded, messages.iter().map(|m| bincode::serialize(m).unwrap()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn bad_frames_are_errors() {
+        let mut reader = FrameReader::new();
+
+        //refused from the header alone
+        reader.push(&((MAX_FRAME_SIZE + 1) as u32).to_le_bytes());
+        reader.push(&[0]);
+        assert_eq!(reader.next_message::<NetworkMessage>().err(), Some(CodecError::TooLarge(MAX_FRAME_SIZE + 1)));
+
+        reader.push(&[1, 0, 0, 0, 0x80, 0]);
+        assert_eq!(reader.next_message::<NetworkMessage>().err(), Some(CodecError::UnknownFlags(0x80)));
+
+        reader.push(&[3, 0, 0, 0, 1, 0xff, 0xff, 0xff]);
+        assert_eq!(reader.next_message::<NetworkMessage>().err(), Some(CodecError::Inflate));
+
+        //a variant that doesn't exist
+        reader.push(&[4, 0, 0, 0, 0, 99, 0, 0, 0]);
+        assert!(matches!(reader.next_message::<NetworkMessage>(), Err(CodecError::Decode(_))));
+
+        //and the reader carries on after frames that were read to the end
+        let good = NetworkMessage::ClientToServer(ClientToServerMessage::RequestInitialChunks);
+        reader.push(&encode(&good, false).unwrap());
+        assert!(reader.next_message::<NetworkMessage>().unwrap().is_some());
+        assert_eq!(reader.buffer.len(), 0);
+        assert_eq!(HEADER_SIZE, 5);
+    }
+}
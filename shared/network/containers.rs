@@ -1,10 +1,13 @@
 use message_io::network::{Endpoint, NetworkController};
-use nalgebra::{Vector2, Vector3};
+use nalgebra::{Point3, Vector2, Vector3};
 use serde::{Deserialize, Serialize};
 use strum_macros::Display;
 
 use crate::world::{blockrepr::WorldBlock, chunkcompress::CompressedChunk};
 
+use super::{codec::encode, handshake::HandshakeMessage};
+
+//every message goes out in one of these, both ways. The order can't change either, the handshake goes out unauthenticated
 #[derive(serde::Serialize, serde::Deserialize)]
 pub enum Packet {
     Authenticated(AuthenticatedPacket),
@@ -22,33 +25,54 @@ pub struct UnauthenticatedPacket {
     pub data: NetworkMessage
 }
 
-pub fn send_authenticated_message(network: &NetworkController, endpoint: Endpoint, token: String, data: NetworkMessage) {
-    let message = AuthenticatedPacket {
-        data,
-        token
-    };
-    network.send(endpoint, &bincode::serialize(&Packet::Authenticated(message)).unwrap());
+impl Packet {
+    //the token is the client's session token. The server and clients that haven't joined have none
+    pub fn new(data: NetworkMessage, token: Option<String>) -> Self {
+        match token {
+            Some(token) => Packet::Authenticated(AuthenticatedPacket {data, token}),
+            None => Packet::Unauthenticated(UnauthenticatedPacket {data})
+        }
+    }
+
+    pub fn into_message(self) -> NetworkMessage {
+        match self {
+            Packet::Authenticated(packet) => packet.data,
+            Packet::Unauthenticated(packet) => packet.data
+        }
+    }
 }
 
-pub fn send_unauthenticated_message(network: &NetworkController, endpoint: Endpoint, data: NetworkMessage) {
-    let message = UnauthenticatedPacket {
-        data,
-    };
-    network.send(endpoint, &bincode::serialize(&Packet::Unauthenticated(message)).unwrap());
+//deflate if the other side has FEATURE_DEFLATE. A message that can't be encoded never will be, it's dropped
+pub fn send_packet(network: &NetworkController, endpoint: Endpoint, data: NetworkMessage, token: Option<String>, deflate: bool) {
+    match encode(&Packet::new(data, token), deflate) {
+        Ok(frame) => {network.send(endpoint, &frame);},
+        Err(e) => println!("couldn't send a message to {}: {}", endpoint, e)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Display)]
 pub enum NetworkMessage {
+    //has to stay the first variant, see Hello
+    Handshake(HandshakeMessage),
     Auth(AuthMessages),
     ServerToClient(ServerToClientMessage),
     ClientToServer(ClientToServerMessage)
 }
 
-#[derive(Serialize, Deserialize, Debug, Display)]
+#[derive(Serialize, Deserialize, Debug, Display, Clone)]
 pub enum ServerToClientMessage {
-    //position, deflate-compressed data
-    ChunkProvided((Vector2<i32>, Vec<u8>)),
+    //position, chunk. The codec deflates it like any other big message
+    ChunkProvided((Vector2<i32>, CompressedChunk)),
     ConcludeReceiveInitialChunks,
+    //absolute position, new block
+    BlockChanged(Vector3<i32>, WorldBlock),
+    //chunk position, then (local x, y, local z, new block) for every changed block in it. One change to a block
+    //usually relights many around it
+    MultiBlockChange(Vector2<i32>, Vec<(u8, u8, u8, WorldBlock)>),
+    //chunk position, the player moved away from it
+    UnloadChunk(Vector2<i32>),
+    //the reason, the server closes the connection right after
+    Kicked(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, Display)]
@@ -59,15 +83,26 @@ pub enum AuthMessages {
     AuthConfirmedUser(String),
     //client -> server (username)
     ClientRequestJoin(String),
-    //server -> client (session token)
-    GiveUserSessionToken(String),
-    //server -> client
-    JoinConfirmed,
+    //server -> client (session token). The token comes with it, a second message sent right behind it could arrive
+    //in the same read and be lost
+    JoinConfirmed(String),
+    //server -> client, answered with the shared secret
+    AuthChallenge(String),
+    //client -> server (username, answer)
+    AuthChallengeResponse(String, String),
+    //server -> client (reason)
+    JoinRejected(String),
 }
 #[derive(Serialize, Deserialize, Debug, Display)]
 pub enum ClientToServerMessage {
     RequestInitialChunks,
     RequestChunk(Vector2<i32>),
-    SetBlock(Vector3<i32>, WorldBlock),
-    BreakBlock(Vector3<i32>)
+    //absolute position and block, then the eye position and look direction it was targeted from so the server can
+    //repeat the raycast
+    SetBlock(Vector3<i32>, WorldBlock, Point3<f32>, Vector3<f32>),
+    BreakBlock(Vector3<i32>, Point3<f32>, Vector3<f32>),
+    //sent on connect and whenever the player crosses into another chunk, chunks are streamed around it
+    PlayerPosition(Point3<f32>),
+    //chunks in each direction the client wants, the server caps it
+    SetViewDistance(u32)
 }
\ No newline at end of file
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+
+//bumped whenever a message changes shape. Only the same protocol can talk, the game version is just for the humans
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+//frames over the codec's threshold can be sent deflated, without it everything goes out as plain bincode
+pub const FEATURE_DEFLATE: &str = "deflate";
+
+//optional things this build can do. Unknown ones from the other side are ignored, so features can come and go without
+//a protocol bump
+pub const SUPPORTED_FEATURES: &[&str] = &[FEATURE_DEFLATE];
+
+//the first message of every connection. It keeps its place and shape in every version, and so does the codec's frame
+//header around it, so two builds can always tell why they can't talk
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hello {
+    pub protocol_version: u32,
+    pub game_version: String,
+    pub features: Vec<String>
+}
+
+impl Hello {
+    //what this build speaks
+    pub fn current() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            game_version: GAME_VERSION.to_string(),
+            features: SUPPORTED_FEATURES.iter().map(|f| f.to_string()).collect()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Display)]
+pub enum HandshakeMessage {
+    //client -> server, right after connecting
+    Hello(Hello),
+    //server -> client, the features both sides have. The client can ask to join now
+    Accepted(Vec<String>),
+    //server -> client (reason), the server won't talk to it
+    Rejected(String)
+}
+
+//the features both sides will use, or why the client can't join
+pub fn negotiate(client: &Hello, server_features: &[&str]) -> Result<Vec<String>, String> {
+    if client.protocol_version != PROTOCOL_VERSION {
+        let outdated = if client.protocol_version < PROTOCOL_VERSION {"the client is out of date"} else {"the server is out of date"};
+        return Err(format!("{}: server is on {} (protocol {}), client is on {} (protocol {})",
+            outdated, GAME_VERSION, PROTOCOL_VERSION, client.game_version, client.protocol_version));
+    }
+
+    Ok(server_features.iter()
+        .filter(|feature| client.features.iter().any(|f| f == *feature))
+        .map(|feature| feature.to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::{codec::{encode, HEADER_SIZE}, containers::{NetworkMessage, Packet, UnauthenticatedPacket}};
+
+    use super::{negotiate, HandshakeMessage, Hello, FEATURE_DEFLATE, PROTOCOL_VERSION};
+
+    #[test]
+    fn features_are_the_ones_both_sides_have() {
+        let mut hello = Hello::current();
+        hello.features = vec![FEATURE_DEFLATE.to_string(), "from-the-future".to_string()];
+
+        assert_eq!(negotiate(&hello, &[FEATURE_DEFLATE, "server-only"]), Ok(vec![FEATURE_DEFLATE.to_string()]));
+        assert_eq!(negotiate(&hello, &[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn other_protocols_are_rejected_with_a_reason() {
+        let mut hello = Hello::current();
+        hello.protocol_version = PROTOCOL_VERSION + 1;
+        assert!(negotiate(&hello, &[]).unwrap_err().contains("server is out of date"));
+
+        hello.protocol_version = PROTOCOL_VERSION - 1;
+        assert!(negotiate(&hello, &[]).unwrap_err().contains("client is out of date"));
+    }
+
+    #[test]
+    fn hello_keeps_its_encoding() {
+        let packet = Packet::Unauthenticated(UnauthenticatedPacket {
+            data: NetworkMessage::Handshake(HandshakeMessage::Hello(Hello::current()))
+        });
+        let frame = encode(&packet, false).unwrap();
+
+        //the header: body length, then flags. Never deflated
+        assert_eq!(HEADER_SIZE, 5);
+        assert_eq!(frame[..4], ((frame.len() - HEADER_SIZE) as u32).to_le_bytes());
+        assert_eq!(frame[4], 0);
+
+        //Packet::Unauthenticated, NetworkMessage::Handshake, HandshakeMessage::Hello, then the version
+        let encoded = &frame[HEADER_SIZE..];
+        assert_eq!(encoded[..12], [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(encoded[12..16], PROTOCOL_VERSION.to_le_bytes());
+    }
+}
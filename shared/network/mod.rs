@@ -1 +1,4 @@
-pub mod containers;
\ No newline at end of file
+pub mod containers;
+pub mod auth;
+pub mod handshake;
+pub mod codec;
\ No newline at end of file
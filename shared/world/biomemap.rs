@@ -2,11 +2,11 @@ use std::{collections::HashMap, env, fs::File, io::BufReader};
 
 use nalgebra::Vector3;
 use once_cell::sync::Lazy;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use super::blockrepr::WorldBlock;
 
-#[derive(PartialEq, Eq, Hash, strum_macros::Display, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, strum_macros::Display, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Biome {
     Plains,
     Mountains,
@@ -18,7 +18,7 @@ pub enum Blocks {
 unsafe impl Send for Blocks {}
 unsafe impl Sync for Blocks {}
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
 pub enum BlockFace {
     Top = 0,
     Bottom = 1,
@@ -57,8 +57,21 @@ impl BlockFace {
             BlockFace::Right => Vector3::new(1, 0, 0),
             BlockFace::Bottom => Vector3::new(0, -1, 0),
             BlockFace::Top => Vector3::new(0, 1, 0),
-            BlockFace::Front => Vector3::new(0, 0, -1),
-            BlockFace::Back => Vector3::new(0, 0, 1),
+            BlockFace::Front => Vector3::new(0, 0, 1),
+            BlockFace::Back => Vector3::new(0, 0, -1),
+        }
+    }
+
+    //the face a unit axis vector points through
+    pub fn from_normal(normal: Vector3<i32>) -> Self {
+        match (normal.x, normal.y, normal.z) {
+            (-1, 0, 0) => BlockFace::Left,
+            (1, 0, 0) => BlockFace::Right,
+            (0, -1, 0) => BlockFace::Bottom,
+            (0, 1, 0) => BlockFace::Top,
+            (0, 0, 1) => BlockFace::Front,
+            (0, 0, -1) => BlockFace::Back,
+            _ => panic!("{} isn't a face normal", normal)
         }
     }
 
@@ -68,8 +81,8 @@ impl BlockFace {
             BlockFace::Bottom => [x as u32, axis as u32, y as u32],
             BlockFace::Left => [axis as u32, y as u32, x as u32],
             BlockFace::Right => [axis as u32 + 1, y as u32, x as u32],
-            BlockFace::Front => [x as u32, y as u32, axis as u32],
-            BlockFace::Back => [x as u32, y as u32, axis as u32 + 1],
+            BlockFace::Front => [x as u32, y as u32, axis as u32 + 1],
+            BlockFace::Back => [x as u32, y as u32, axis as u32],
         }
     }
 
@@ -79,8 +92,8 @@ impl BlockFace {
             BlockFace::Bottom => false,   //-1
             BlockFace::Left => false,   //-1
             BlockFace::Right => true,   //+1
-            BlockFace::Front => true, //-1
-            BlockFace::Back => false,   //+1
+            BlockFace::Front => false,  //+1
+            BlockFace::Back => true,    //-1
         }
     }
 }
@@ -99,7 +112,7 @@ impl From<usize> for BlockFace {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum FaceTexture {
     //static index
     Static(usize),
@@ -1,11 +1,12 @@
 //it has been decided that blocks will be stateless until they need state. This means that there will not be classes for each block
 //each block will also store its orientation and other data as the following
-//u8 -> 5bits for orientation, 4 bits for lighting
+//u8 -> upper 4 bits for orientation, lower 4 bits for lighting. Fluids are never oriented, their upper 4 bits hold the
+//fluid level instead (see get_fluid_level), so nothing reads one as the other
 
-//r-primary axis(lower 3 bits): 0: up, 1: down, 2: right, 3: left, 4: front, 5: back (where up vector ends up)
-//r-secondary axis(upper 2 bits): 0: up, 1: down, 2: right, 3: left (where the front vector ends up)
+//orientation bits 4-5: quarter turns around y, turning the front (+z) towards +x, then -z, then -x
+//orientation bit 6: upside down, turned half way around x before the quarter turns. Bit 7 is unused
 
-use nalgebra::{Matrix3, Vector3};
+use nalgebra::Matrix3;
 use serde::{Deserialize, Serialize};
 
 use crate::loaders::texture_loader::get_indices_from_texture;
@@ -18,88 +19,68 @@ pub enum WorldBlock {
     Dirt(u8),
     Grass(u8),
     Stone(u8),
-    Sand(u8)
+    Sand(u8),
+    Glass(u8),
+    Leaf(u8),
+    ArcaneLeaf(u8),
+    Water(u8),
+    Slab(u8),
+    Stairs(u8),
+    Fence(u8),
+    TallGrass(u8),
+    DiamondOre(u8)
 }
 
-pub fn calculate_block_rotation(store: u8, face: BlockFace) -> BlockFace {
-    let rotation = store & 0b11100000; //take the first 3 bits for primary orientation axis
-    let secondary = (store) & 0b11; //take the 4th and 5th bits for secondary orientation axis TODO: FIRST OF ALL ARE YOU HIGH? THAT'S 9 BITS TOTAL FOR A U8? SECONDLY, WHY EVEN USE THIS?
-
-    let uptarget = match rotation {
-        0 => Vector3::new(0, 1, 0),
-        1 => Vector3::new(0, -1, 0),
-        2 => Vector3::new(1, 0, 0),
-        3 => Vector3::new(-1, 0, 0),
-        4 => Vector3::new(0, 0, 1),
-        5 => Vector3::new(0, 0, -1),
-        _ => panic!("Not a valid rotation")
-    };
+const YAW_MASK: u8 = 0b00110000;
+const FLIPPED_BIT: u8 = 0b01000000;
 
-    let forwardtarget = match rotation {
-        0 => match secondary {
-            0 => Vector3::new(1, 0, 0),
-            1 => Vector3::new(-1, 0, 0),
-            2 => Vector3::new(0, 0, 1),
-            3 => Vector3::new(0, 0, -1),
-            _ => panic!("Not a valid rotation sec.")
-        },
-        1 => match secondary {
-            0 => Vector3::new(1, 0, 0),
-            1 => Vector3::new(-1, 0, 0),
-            2 => Vector3::new(0, 0, 1),
-            3 => Vector3::new(0, 0, -1),
-            _ => panic!("Not a valid rotation sec.")
-        },
-        2 => match secondary {
-            0 => Vector3::new(0, 1, 0),
-            1 => Vector3::new(0, -1, 0),
-            2 => Vector3::new(0, 0, 1),
-            3 => Vector3::new(0, 0, -1),
-            _ => panic!("Not a valid rotation sec.")
-        },
-        3 => match secondary {
-            0 => Vector3::new(0, 1, 0),
-            1 => Vector3::new(0, -1, 0),
-            2 => Vector3::new(0, 0, 1),
-            3 => Vector3::new(0, 0, -1),
-            _ => panic!("Not a valid rotation sec.")
-        },
-        4 => match secondary {
-            0 => Vector3::new(0, 1, 0),
-            1 => Vector3::new(0, -1, 0),
-            2 => Vector3::new(1, 0, 0),
-            3 => Vector3::new(-1, 0, 0),
-            _ => panic!("Not a valid rotation sec.")
-        },
-        5 => match secondary {
-            0 => Vector3::new(0, 1, 0),
-            1 => Vector3::new(0, -1, 0),
-            2 => Vector3::new(1, 0, 0),
-            3 => Vector3::new(-1, 0, 0),
-            _ => panic!("Not a valid rotation sec.")
-        },
-        _ => panic!("Not a valid rotation")
-    };
+pub fn get_block_yaw(block: &WorldBlock) -> u8 {
+    match get_on_block(block, |currentval| currentval) {
+        UnsignedNumbers::U8(x) => (x & YAW_MASK) >> 4,
+    }
+}
 
-    let righttarget = uptarget.cross(&forwardtarget);
+pub fn is_block_flipped(block: &WorldBlock) -> bool {
+    match get_on_block(block, |currentval| currentval) {
+        UnsignedNumbers::U8(x) => x & FLIPPED_BIT != 0,
+    }
+}
 
-    let transform = Matrix3::from_columns(&[righttarget, uptarget, forwardtarget]);
+//not for fluids, those keep their level there
+pub fn set_block_orientation(block: &mut WorldBlock, yaw: u8, flipped: bool) {
+    perform_op_on_block(block, |currentval| {
+        currentval & 0b10001111 | ((yaw & 0b11) << 4) | if flipped {FLIPPED_BIT} else {0}
+    });
+}
 
-    let new_vector = transform * match face {
-        BlockFace::Left => Vector3::new(-1, 0, 0),
-        BlockFace::Right => Vector3::new(1, 0, 0),
-        BlockFace::Bottom => Vector3::new(0, -1, 0),
-        BlockFace::Top => Vector3::new(0, 1, 0),
-        BlockFace::Front => Vector3::new(0, 0, 1),
-        BlockFace::Back => Vector3::new(0, 0, -1),
+//turns block space directions the way the block's orientation bits say
+pub fn orientation_matrix(store: u8) -> Matrix3<i32> {
+    let flip = if store & FLIPPED_BIT != 0 {
+        Matrix3::new(1, 0, 0, 0, -1, 0, 0, 0, -1)
+    } else {
+        Matrix3::identity()
     };
 
-    let newface = if new_vector.y == 1 {BlockFace::Top} else if new_vector.y == -1 {BlockFace::Bottom} 
-        else if new_vector.x == 1 {BlockFace::Right} else if new_vector.x == -1 {BlockFace::Left}
-        else if new_vector.z == 1 {BlockFace::Front} else if new_vector.z == -1 {BlockFace::Back}
-        else {panic!("Unable to determine face of vector ${}", new_vector)};
+    //one quarter turn takes +z to +x
+    let quarter = Matrix3::new(0, 0, 1, 0, 1, 0, -1, 0, 0);
+    let mut yaw = Matrix3::identity();
+    for _ in 0..(store & YAW_MASK) >> 4 {
+        yaw = quarter * yaw;
+    }
 
-    newface
+    yaw * flip
+}
+
+pub fn block_orientation_matrix(block: &WorldBlock) -> Matrix3<i32> {
+    match get_on_block(block, |currentval| currentval) {
+        UnsignedNumbers::U8(x) => orientation_matrix(x),
+    }
+}
+
+//which of the block's own faces ends up facing face once it's oriented
+pub fn calculate_block_rotation(store: u8, face: BlockFace) -> BlockFace {
+    //rotations are orthogonal, the transpose turns back
+    BlockFace::from_normal(orientation_matrix(store).transpose() * face.normal())
 }
 
 pub fn set_block_light(block: &mut WorldBlock, light: u8) {
@@ -134,10 +115,35 @@ pub fn get_surface_texture_indices(block: &WorldBlock, face: BlockFace) -> (Face
             }.into(), 0.into(), 0.into())
         },
         WorldBlock::Stone(r) => {
-            (get_indices_from_texture("stone").into(), 0.into(), 0.into())
+            (get_indices_from_texture("stone").into(), get_indices_from_texture("stone_normal").into(), 0.into())
+        },
+        WorldBlock::DiamondOre(r) => {
+            (get_indices_from_texture("diamond_ore").into(), get_indices_from_texture("stone_normal").into(), get_indices_from_texture("default_ore_emissive").into())
         },
         WorldBlock::Sand(r) => {
             (get_indices_from_texture("sand").into(), 0.into(), 0.into())
+        },
+        WorldBlock::Glass(r) => {
+            (get_indices_from_texture("glass").into(), 0.into(), 0.into())
+        },
+        WorldBlock::Leaf(r) => {
+            (get_indices_from_texture("leaf").into(), 0.into(), 0.into())
+        },
+        WorldBlock::ArcaneLeaf(r) => {
+            (get_indices_from_texture("arcane_leaf").into(), 0.into(), 0.into())
+        },
+        WorldBlock::Water(r) => {
+            (get_indices_from_texture("water").into(), 0.into(), 0.into())
+        },
+        //modelled blocks take their textures from the model, this is only used for particles and the like
+        WorldBlock::Slab(r) | WorldBlock::Stairs(r) => {
+            (get_indices_from_texture("stone").into(), 0.into(), 0.into())
+        },
+        WorldBlock::Fence(r) => {
+            (get_indices_from_texture("planks").into(), 0.into(), 0.into())
+        },
+        WorldBlock::TallGrass(r) => {
+            (get_indices_from_texture("tall_grass").into(), 0.into(), 0.into())
         }
     }
 }
@@ -145,7 +151,28 @@ pub fn get_surface_texture_indices(block: &WorldBlock, face: BlockFace) -> (Face
 pub fn has_partial_transparency(block: &WorldBlock) -> bool {
     //just add to the first arm to add more ofc because we only want to bother with those which are transparent
     match block {
-        WorldBlock::Air(_) => true,
+        WorldBlock::Air(_) | WorldBlock::Glass(_) | WorldBlock::Leaf(_) | WorldBlock::ArcaneLeaf(_)
+        | WorldBlock::Water(_) | WorldBlock::Slab(_) | WorldBlock::Stairs(_) | WorldBlock::Fence(_)
+        | WorldBlock::TallGrass(_) => true,
+        _ => false
+    }
+}
+
+//blocks that aren't cubes and get meshed from a model in the model manifest instead of the greedy mesher
+pub fn get_block_model_alias(block: &WorldBlock) -> Option<&'static str> {
+    match block {
+        WorldBlock::Slab(_) => Some("slab"),
+        WorldBlock::Stairs(_) => Some("stairs"),
+        WorldBlock::Fence(_) => Some("fence"),
+        WorldBlock::TallGrass(_) => Some("cross_plant"),
+        _ => None
+    }
+}
+
+//fully opaque or fully see-through pixels only, so these are alpha tested instead of blended
+pub fn is_cutout(block: &WorldBlock) -> bool {
+    match block {
+        WorldBlock::Leaf(_) | WorldBlock::ArcaneLeaf(_) => true,
         _ => false
     }
 }
@@ -165,9 +192,59 @@ pub fn does_not_render(block: &WorldBlock) -> bool {
 }
 pub fn is_fluid(block: &WorldBlock) -> bool {
     match block {
+        WorldBlock::Water(_) => true,
         _ => false
     }
 }
+
+//what the crosshair stops at. Fluids are looked through so blocks can be placed underwater
+pub fn is_targetable(block: &WorldBlock) -> bool {
+    !does_not_render(block) && !is_fluid(block)
+}
+
+//blocks that a placed block simply replaces
+pub fn is_replaceable(block: &WorldBlock) -> bool {
+    match block {
+        WorldBlock::Air(_) | WorldBlock::Water(_) => true,
+        _ => false
+    }
+}
+
+//falls into the block below it when that's replaceable
+pub fn has_gravity(block: &WorldBlock) -> bool {
+    match block {
+        WorldBlock::Sand(_) => true,
+        _ => false
+    }
+}
+
+//what players may place. Fluids and ores only come from world generation
+pub fn is_placeable(block: &WorldBlock) -> bool {
+    match block {
+        WorldBlock::Air(_) | WorldBlock::Water(_) | WorldBlock::DiamondOre(_) => false,
+        _ => true
+    }
+}
+
+pub const MAX_FLUID_LEVEL: u8 = 7;
+
+//fluids keep their level in the upper 4 bits, where other blocks keep their orientation. 0 is a full (source) block,
+//MAX_FLUID_LEVEL is the thinnest layer
+pub fn get_fluid_level(block: &WorldBlock) -> u8 {
+    let out = get_on_block(block, |currentval| {
+        currentval
+    });
+
+    match out {
+        UnsignedNumbers::U8(x) => (x >> 4).min(MAX_FLUID_LEVEL),
+    }
+}
+
+pub fn set_fluid_level(block: &mut WorldBlock, level: u8) {
+    perform_op_on_block(block, |currentval| {
+        currentval & 0b00001111 | (level.min(MAX_FLUID_LEVEL) << 4)
+    });
+}
 pub fn get_block_id(block: &WorldBlock) -> u64 {
     match block {
         WorldBlock::Air(x) => 0,
@@ -175,5 +252,59 @@ pub fn get_block_id(block: &WorldBlock) -> u64 {
         WorldBlock::Grass(x) => 2,
         WorldBlock::Stone(x) => 3,
         WorldBlock::Sand(x) => 4,
+        WorldBlock::Glass(x) => 5,
+        WorldBlock::Leaf(x) => 6,
+        WorldBlock::ArcaneLeaf(x) => 7,
+        WorldBlock::Water(x) => 8,
+        WorldBlock::Slab(x) => 9,
+        WorldBlock::Stairs(x) => 10,
+        WorldBlock::Fence(x) => 11,
+        WorldBlock::TallGrass(x) => 12,
+        WorldBlock::DiamondOre(x) => 13,
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::world::block::BlockFace;
+
+    use super::{calculate_block_rotation, get_block_light, get_block_yaw, is_block_flipped, orientation_matrix, set_block_light, set_block_orientation, WorldBlock};
+
+    #[test]
+    fn orientation_leaves_the_light_alone() {
+        let mut block = WorldBlock::Stairs(0);
+        set_block_light(&mut block, 11);
+        set_block_orientation(&mut block, 3, true);
+
+        assert_eq!(get_block_yaw(&block), 3);
+        assert!(is_block_flipped(&block));
+        assert_eq!(get_block_light(&block), 11);
+
+        set_block_orientation(&mut block, 1, false);
+        assert_eq!(get_block_yaw(&block), 1);
+        assert!(!is_block_flipped(&block));
+    }
+
+    #[test]
+    fn rotated_faces() {
+        let mut block = WorldBlock::Grass(0);
+        set_block_light(&mut block, 15);
+        let store = |block: WorldBlock| match block {WorldBlock::Grass(r) => r, _ => unreachable!()};
+
+        //light bits don't turn anything
+        assert_eq!(calculate_block_rotation(store(block), BlockFace::Top), BlockFace::Top);
+        assert_eq!(calculate_block_rotation(store(block), BlockFace::Front), BlockFace::Front);
+
+        //a quarter turn shows the front on the right
+        set_block_orientation(&mut block, 1, false);
+        assert_eq!(calculate_block_rotation(store(block), BlockFace::Right), BlockFace::Front);
+        assert_eq!(calculate_block_rotation(store(block), BlockFace::Top), BlockFace::Top);
+
+        set_block_orientation(&mut block, 0, true);
+        assert_eq!(calculate_block_rotation(store(block), BlockFace::Bottom), BlockFace::Top);
+        assert_eq!(calculate_block_rotation(store(block), BlockFace::Back), BlockFace::Front);
+
+        //four quarter turns go all the way around
+        let quarter = orientation_matrix(0b00010000);
+        assert_eq!(quarter * quarter * quarter * quarter, orientation_matrix(0));
+    }
+}
@@ -8,7 +8,10 @@ pub fn perform_op_on_block<F>(block: &mut WorldBlock, conditionu8: F) -> Unsigne
  where F: Fn(u8) -> u8 {
     match block {
         WorldBlock::Air(x) | WorldBlock::Dirt(x) | WorldBlock::Grass(x)
-        | WorldBlock::Stone(x) | WorldBlock::Sand(x) => {
+        | WorldBlock::Stone(x) | WorldBlock::Sand(x) | WorldBlock::Glass(x)
+        | WorldBlock::Leaf(x) | WorldBlock::ArcaneLeaf(x) | WorldBlock::Water(x)
+        | WorldBlock::Slab(x) | WorldBlock::Stairs(x) | WorldBlock::Fence(x) | WorldBlock::TallGrass(x)
+        | WorldBlock::DiamondOre(x) => {
             let updated_value = conditionu8(*x);
             *x = updated_value;
             UnsignedNumbers::U8(updated_value)
@@ -20,7 +23,10 @@ pub fn get_on_block<F>(block: &WorldBlock, conditionu8: F) -> UnsignedNumbers
  where F: Fn(u8) -> u8 {
     match block {
         WorldBlock::Air(x) | WorldBlock::Dirt(x) | WorldBlock::Grass(x)
-        | WorldBlock::Stone(x) | WorldBlock::Sand(x) => {
+        | WorldBlock::Stone(x) | WorldBlock::Sand(x) | WorldBlock::Glass(x)
+        | WorldBlock::Leaf(x) | WorldBlock::ArcaneLeaf(x) | WorldBlock::Water(x)
+        | WorldBlock::Slab(x) | WorldBlock::Stairs(x) | WorldBlock::Fence(x) | WorldBlock::TallGrass(x)
+        | WorldBlock::DiamondOre(x) => {
             let newvalue = conditionu8(*x);
             UnsignedNumbers::U8(newvalue)
         },
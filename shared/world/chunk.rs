@@ -1,12 +1,12 @@
 use std::{collections::HashMap, sync::Arc};
 
 use cached::proc_macro::cached;
-use nalgebra::{Vector2, Vector3};
+use nalgebra::{Point3, Vector2, Vector3};
 use noise::{OpenSimplex, Seedable};
 use serde::{Deserialize, Serialize};
 use stopwatch::Stopwatch;
 
-use crate::world::{blockrepr::WorldBlock, worldgen::{generate_surface_height, get_biome, get_gen_config, is_cave}};
+use crate::world::{biomemap::Biome, blockrepr::WorldBlock, worldgen::{generate_surface_height, get_biome, get_gen_config, is_cave, is_ore}};
 
 use super::blockrepr::has_partial_transparency;
 
@@ -37,48 +37,59 @@ pub fn xz_to_index(x: i32, z: i32) -> u32 {
     (0.5 * (x0 + z0) as f32 * (x0 + z0 + 1) as f32 + z0 as f32) as u32 //cantor pairing https://math.stackexchange.com/questions/3003672/convert-infinite-2d-plane-integer-coords-to-1d-number
 }
 
+//position of the chunk a point in the world is in
+pub fn chunk_of(position: Point3<f32>) -> Vector2<i32> {
+    Vector2::new((position.x / 16.0).floor() as i32, (position.z / 16.0).floor() as i32)
+}
+
+pub fn xz_to_column_index(x: u32, z: u32) -> usize {
+    (z * 16 + x) as usize
+}
+
 pub type ChunkGridType = Vec<Vec<WorldBlock>>;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Chunk {
     pub position: Vector2<i32>,
     pub grid: ChunkGridType,
-    pub fullair: [bool; 16]
+    pub fullair: [bool; 16],
+    //one per column, indexed like xz_to_column_index
+    pub biomes: Vec<Biome>
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ChunkState {
     PreMesh,
     Mesh,
-    Ready
+    Ready,
+    //the buffers are out of date and a remesh is in flight, the old ones keep getting drawn until it lands
+    Stale
+}
+
+impl ChunkState {
+    pub fn has_mesh(&self) -> bool {
+        matches!(self, ChunkState::Ready | ChunkState::Stale)
+    }
 }
 
 impl Chunk {
     pub fn from_blocks(position: Vector2<i32>, grid: ChunkGridType) -> Self {
         let mut fullair = [true; 16];
-        for x in 0..16 {
-            for z in 0..16 {
-                for y_slice in 0..16 {
-                    for y in 0..16 {
-                        match grid[y_slice / 16][local_xyz_to_index(x, y, z) as usize] {
-                            WorldBlock::Air(_) => {},
-                            _ => {
-                                fullair[y_slice] = false;
-                            }
-                        }
-                        if !fullair[y_slice] {
-                            break;
-                        }
-                    }
-                }
-            }
+        for (y_slice, slice) in grid.iter().enumerate() {
+            fullair[y_slice] = slice.iter().all(|block| matches!(block, WorldBlock::Air(_)));
         }
         Self {
             position,
             grid,
-            fullair
+            fullair,
+            biomes: vec![Biome::Plains; 256]
         }
     }
+
+    pub fn with_biomes(mut self, biomes: Vec<Biome>) -> Self {
+        self.biomes = biomes;
+        self
+    }
     pub fn new(position: Vector2<i32>, noisegen: OpenSimplex, extra_blocks: &mut HashMap<u32, Vec<WorldBlock>>) -> Self {
         let t = Stopwatch::start_new();
 
@@ -88,6 +99,19 @@ impl Chunk {
 
         let gencfg = get_gen_config(noisegen.seed());
 
+        //biome and surface height once per column, every slice needs them
+        let mut biomes = vec![Biome::Plains; 256];
+        //pushed in column index order
+        let mut columns = Vec::with_capacity(256);
+        for z in 0..16 {
+            for x in 0..16 {
+                let (abs_x, abs_z) = (x as i32 + position.x * 16, z as i32 + position.y * 16);
+                let (biome, biomegen) = get_biome(noisegen, abs_x, abs_z, &gencfg);
+                biomes[xz_to_column_index(x, z)] = biome;
+                columns.push((generate_surface_height(noisegen, abs_x, abs_z, &gencfg), biomegen));
+            }
+        }
+
         let mut blocks = iter_layers.map(|y_slice| {
             let mut out: Vec<WorldBlock> = Vec::with_capacity(4096);
 
@@ -98,10 +122,8 @@ impl Chunk {
                     let abs_x = ((x as i32) + position.x * 16) as i32;
                     let abs_z = ((z as i32) + position.y * 16) as i32;
 
-                    let floor_level = generate_surface_height(noisegen, abs_x, abs_z, &gencfg);
+                    let (floor_level, biomegen) = columns[xz_to_column_index(x, z)];
 
-                    let (biome, biomegen) = get_biome(noisegen, abs_x, abs_z, &gencfg);
-                    
                     for y in 0..16 {
                         let abs_y = (y + y_slice as u32 * 16) as i32;
                         let is_cave = is_cave(noisegen, abs_x, abs_y, abs_z);
@@ -116,7 +138,12 @@ impl Chunk {
                             biomegen.make_subsurface_block(Vector3::new(abs_x, abs_y, abs_z))
                         }
                         else if abs_y < floor_level {
-                            biomegen.make_earth_block(Vector3::new(abs_x, abs_y, abs_z))
+                            let earth = biomegen.make_earth_block(Vector3::new(abs_x, abs_y, abs_z));
+                            if matches!(earth, WorldBlock::Stone(_)) && is_ore(noisegen.seed(), abs_x, abs_y, abs_z) {
+                                WorldBlock::DiamondOre(0)
+                            } else {
+                                earth
+                            }
                         }
                         else {
                             WorldBlock::Air(0)
@@ -205,7 +232,8 @@ impl Chunk {
         Self {
             position,
             grid: blocks,
-            fullair: [false; 16]
+            fullair: [false; 16],
+            biomes
         }
     }
 
@@ -222,6 +250,10 @@ impl Chunk {
     //     self.slice_vertex_buffers = slice_vertex_buffers;
     // }
 
+    pub fn get_biome_at(&self, x: u32, z: u32) -> Biome {
+        self.biomes[xz_to_column_index(x % 16, z % 16)]
+    }
+
     pub fn get_block_at(&self, x: u32, y: u32, z: u32) -> &WorldBlock {
         &self.grid[(y / 16) as usize][local_xyz_to_index(x % 16, y % 16, z % 16) as usize]
     }
@@ -242,6 +274,13 @@ impl Chunk {
         0
     }
 
+    //keeps fullair in sync, everything else that sets blocks after generation should go through this
+    pub fn set_block_at(&mut self, x: u32, y: u32, z: u32, block: WorldBlock) {
+        let y_slice = (y / 16) as usize;
+        *self.get_block_at_mut(x, y, z) = block;
+        self.fullair[y_slice] = self.grid[y_slice].iter().all(|block| matches!(block, WorldBlock::Air(_)));
+    }
+
     pub fn modify_block_at<F>(&mut self, x: u32, y: u32, z: u32, mut callback: F) where F: FnMut(&mut WorldBlock) {
         callback(&mut self.grid[(y / 16) as usize][local_xyz_to_index(x, y % 16, z) as usize]);
     }
@@ -3,12 +3,14 @@ use std::{collections::HashMap, sync::Arc};
 use nalgebra::{Vector2, Vector3};
 use serde::{Deserialize, Serialize};
 
-use super::{blockrepr::WorldBlock, chunk::{index_to_local_xyz, local_xyz_to_index, Chunk}, constructblock::construct_block};
+use super::{biomemap::Biome, blockrepr::WorldBlock, chunk::{index_to_local_xyz, local_xyz_to_index, Chunk}, constructblock::construct_block};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CompressedChunk {
     position: Vector2<i32>,
-    slices: Vec<HashMap<WorldBlock, Vec<u16>>>
+    slices: Vec<HashMap<WorldBlock, Vec<u16>>>,
+    //one per column for the fog tint. Part of the format since biomes were added, chunks encoded without them don't decode
+    biomes: Vec<Biome>
 }
 
 pub fn compress_chunk(chunk: &Chunk) -> CompressedChunk {
@@ -33,7 +35,8 @@ pub fn compress_chunk(chunk: &Chunk) -> CompressedChunk {
 
     CompressedChunk {
         position: chunk.position,
-        slices: lists
+        slices: lists,
+        biomes: chunk.biomes.clone()
     }
 }
 pub fn decompress_chunk(chunkc: CompressedChunk) -> Arc<Chunk> {
@@ -59,5 +62,5 @@ pub fn decompress_chunk(chunkc: CompressedChunk) -> Arc<Chunk> {
         vec
     }).collect();
 
-    Arc::new(Chunk::from_blocks(chunkc.position, grid))
+    Arc::new(Chunk::from_blocks(chunkc.position, grid).with_biomes(chunkc.biomes))
 }
\ No newline at end of file
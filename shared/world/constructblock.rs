@@ -10,6 +10,15 @@ pub fn construct_block(blocktype: &WorldBlock, absolute_position: Vector3<i32>)
         WorldBlock::Grass(x) => WorldBlock::Grass(*x),
         WorldBlock::Stone(x) => WorldBlock::Stone(*x),
         WorldBlock::Sand(x) => WorldBlock::Sand(*x),
+        WorldBlock::Glass(x) => WorldBlock::Glass(*x),
+        WorldBlock::Leaf(x) => WorldBlock::Leaf(*x),
+        WorldBlock::ArcaneLeaf(x) => WorldBlock::ArcaneLeaf(*x),
+        WorldBlock::Water(x) => WorldBlock::Water(*x),
+        WorldBlock::Slab(x) => WorldBlock::Slab(*x),
+        WorldBlock::Stairs(x) => WorldBlock::Stairs(*x),
+        WorldBlock::Fence(x) => WorldBlock::Fence(*x),
+        WorldBlock::TallGrass(x) => WorldBlock::TallGrass(*x),
+        WorldBlock::DiamondOre(x) => WorldBlock::DiamondOre(*x),
         //add separate handling for blocks that require special handling and don't do anything too silly.
     }
 }
\ No newline at end of file
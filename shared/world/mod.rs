@@ -6,4 +6,5 @@ pub mod chunkcompress;
 pub mod constructblock;
 pub mod blockrepr;
 pub mod biomemap;
-pub mod butils;
\ No newline at end of file
+pub mod butils;
+pub mod raycast;
@@ -0,0 +1,115 @@
+use nalgebra::{Point3, Vector3};
+
+use super::blockrepr::{is_targetable, WorldBlock};
+
+//how far from the eye blocks can be broken and placed, the server checks it too
+pub const REACH: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    //absolute position of the hit block
+    pub block: Vector3<i32>,
+    //normal of the face the ray entered through, a block placed against it goes to block + face.
+    //zero if the ray started inside the block
+    pub face: Vector3<i32>,
+    //from the origin to where the ray entered the block
+    pub distance: f32
+}
+
+//steps through every block the ray touches in order (Amanatides & Woo) until one is targetable. get_block returns None
+//for unloaded or out of world positions, which the ray passes through like air
+pub fn raycast<F>(origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32, get_block: F) -> Option<RaycastHit> where F: Fn(Vector3<i32>) -> Option<WorldBlock> {
+    let direction = direction.try_normalize(f32::EPSILON)?;
+    let mut block = origin.map(|v| v.floor() as i32).coords;
+    let step = direction.map(|v| if v > 0.0 {1} else if v < 0.0 {-1} else {0});
+
+    //distance along the ray between two block boundaries on each axis, and to the first boundary
+    let delta = direction.map(|v| if v == 0.0 {f32::INFINITY} else {1.0 / v.abs()});
+    let mut next = Vector3::from_fn(|i, _| {
+        if step[i] > 0 {
+            (block[i] as f32 + 1.0 - origin[i]) * delta[i]
+        } else if step[i] < 0 {
+            (origin[i] - block[i] as f32) * delta[i]
+        } else {
+            f32::INFINITY
+        }
+    });
+
+    let mut face = Vector3::zeros();
+    let mut distance = 0.0;
+
+    while distance <= max_distance {
+        if get_block(block).is_some_and(|b| is_targetable(&b)) {
+            return Some(RaycastHit {block, face, distance});
+        }
+
+        let axis = next.imin();
+        distance = next[axis];
+        next[axis] += delta[axis];
+        block[axis] += step[axis];
+
+        face = Vector3::zeros();
+        face[axis] = -step[axis];
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, Vector3};
+
+    use crate::world::blockrepr::WorldBlock;
+
+    use super::raycast;
+
+    //stone at the given positions, air everywhere else
+    fn world(solid: &[Vector3<i32>]) -> impl Fn(Vector3<i32>) -> Option<WorldBlock> + '_ {
+        move |position| Some(if solid.contains(&position) {WorldBlock::Stone(0)} else {WorldBlock::Air(0)})
+    }
+
+    #[test]
+    fn hits_the_face_facing_the_ray() {
+        let solid = [Vector3::new(3, 0, 0)];
+        let hit = raycast(Point3::new(0.5, 0.5, 0.5), Vector3::x(), 6.0, world(&solid)).unwrap();
+
+        assert_eq!(hit.block, Vector3::new(3, 0, 0));
+        assert_eq!(hit.face, Vector3::new(-1, 0, 0));
+        assert!((hit.distance - 2.5).abs() < 1e-5);
+
+        //looking down onto it from above
+        let hit = raycast(Point3::new(3.5, 4.2, 0.5), -Vector3::y(), 6.0, world(&solid)).unwrap();
+        assert_eq!(hit.face, Vector3::new(0, 1, 0));
+        assert!((hit.distance - 3.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn walks_diagonals_and_negative_coordinates() {
+        let solid = [Vector3::new(-3, -1, -2)];
+        let origin = Point3::new(-0.5, 0.5, -0.5);
+        let target = Point3::new(-2.5, -0.5, -1.5);
+        let hit = raycast(origin, target - origin, 6.0, world(&solid)).unwrap();
+
+        assert_eq!(hit.block, Vector3::new(-3, -1, -2));
+        assert_eq!(hit.face.abs().sum(), 1);
+    }
+
+    #[test]
+    fn stops_at_the_reach() {
+        let solid = [Vector3::new(0, 0, 8)];
+        assert!(raycast(Point3::new(0.5, 0.5, 0.5), Vector3::z(), 6.0, world(&solid)).is_none());
+        assert!(raycast(Point3::new(0.5, 0.5, 0.5), Vector3::z(), 8.0, world(&solid)).is_some());
+
+        //unloaded blocks don't stop the ray
+        assert!(raycast(Point3::new(0.5, 0.5, 0.5), Vector3::z(), 6.0, |_| None).is_none());
+    }
+
+    #[test]
+    fn starting_inside_a_block_hits_it() {
+        let solid = [Vector3::new(0, 0, 0)];
+        let hit = raycast(Point3::new(0.5, 0.5, 0.5), Vector3::x(), 6.0, world(&solid)).unwrap();
+
+        assert_eq!(hit.face, Vector3::zeros());
+        assert_eq!(hit.distance, 0.0);
+    }
+}
@@ -105,6 +105,20 @@ pub fn is_cave(noisegen: OpenSimplex, x: i32, y: i32, z: i32) -> bool {
         * SPLINE_WORM.sample(y as f32).expect(&format!("y is {}", y)) <= 0.5 
     //|| !get_density_for_cave(noisegen, x, y, z)
 }
+//ores replace stone this deep or deeper, roughly one block in ORE_RARITY
+pub const ORE_MAX_Y: i32 = 40;
+pub const ORE_RARITY: u32 = 400;
+
+//a cheap integer hash so ores don't need another noise lookup per block
+#[inline]
+pub fn is_ore(seed: u32, x: i32, y: i32, z: i32) -> bool {
+    if y > ORE_MAX_Y {return false};
+    let mut h = seed ^ (x as u32).wrapping_mul(0x8da6b343) ^ (y as u32).wrapping_mul(0xd8163841) ^ (z as u32).wrapping_mul(0xcb1ab31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1e995);
+    h ^= h >> 15;
+    h % ORE_RARITY == 0
+}
 #[inline]
 pub fn get_density_for_cave(noisegen: OpenSimplex, x: i32, y: i32, z: i32) -> bool {
     let p = perlin_octaved_3d(noisegen, x, y, z, 1, 1.1, 1.35, 0.6, 1.1, 1.) * 10.;